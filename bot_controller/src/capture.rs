@@ -0,0 +1,118 @@
+use crate::log_tail::MAX_LINE_BYTES;
+use async_process::Child;
+use common::models::bot_controller::BotLog;
+use common::procs::log_cap::{CappedLog, LogCap};
+use futures_util::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use parking_lot::Mutex;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Copies the piped outputs of `process` to their own log in `log_dir` and, if there is a
+/// merged log, line by line with the time since `started` to that too. Each log is kept within
+/// `cap` as it is written.
+pub(crate) fn capture_output(
+    process: &mut Child,
+    log_dir: &Path,
+    stdout_file: File,
+    stderr_file: File,
+    merged_file: Option<File>,
+    cap: Option<LogCap>,
+    started: Instant,
+) -> std::io::Result<()> {
+    let open = |file, log: BotLog| CappedLog::new(file, &log_dir.join(log.file_name()), cap);
+    let stdout_log = open(stdout_file, BotLog::Stdout)?;
+    let stderr_log = open(stderr_file, BotLog::Stderr)?;
+    // Both outputs append to the merged log, which may be shrunk by either of them
+    let merged_log = match merged_file {
+        Some(file) => Some(Arc::new(Mutex::new(open(file, BotLog::Merged)?))),
+        None => None,
+    };
+    if let Some(stdout) = process.stdout.take() {
+        tokio::spawn(copy_lines(
+            stdout,
+            stdout_log,
+            merged_log.clone(),
+            "stdout",
+            started,
+        ));
+    }
+    if let Some(stderr) = process.stderr.take() {
+        tokio::spawn(copy_lines(
+            stderr, stderr_log, merged_log, "stderr", started,
+        ));
+    }
+    Ok(())
+}
+
+async fn copy_lines<R>(
+    reader: R,
+    mut log: CappedLog,
+    merged_log: Option<Arc<Mutex<CappedLog>>>,
+    stream: &'static str,
+    started: Instant,
+) where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    let mut write_failed = false;
+    loop {
+        line.clear();
+        match (&mut reader)
+            .take(MAX_LINE_BYTES as u64)
+            .read_until(b'\n', &mut line)
+            .await
+        {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("Could not read bot {}: {}", stream, e);
+                break;
+            }
+        }
+        // Each line goes out to the merged log in one write, so lines of both outputs don't mix
+        let written = log.write_all(&line).and_then(|_| match &merged_log {
+            Some(merged_log) => {
+                merged_log
+                    .lock()
+                    .write_all(&merged_line(&line, stream, started.elapsed()))
+            }
+            None => Ok(()),
+        });
+        // Keep reading after a failed write, so the bot doesn't block on a full pipe
+        if let Err(e) = written {
+            if !write_failed {
+                tracing::warn!("Could not write bot {}: {}", stream, e);
+            }
+            write_failed = true;
+        }
+    }
+}
+
+fn merged_line(line: &[u8], stream: &str, elapsed: Duration) -> Vec<u8> {
+    let mut merged = format!("[{:>10.3}] {stream}: ", elapsed.as_secs_f64()).into_bytes();
+    merged.extend_from_slice(line);
+    if !merged.ends_with(b"\n") {
+        merged.push(b'\n');
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merged_line() {
+        assert_eq!(
+            merged_line(b"hello\n", "stdout", Duration::from_millis(1500)),
+            b"[     1.500] stdout: hello\n".to_vec()
+        );
+        assert_eq!(
+            merged_line(b"partial", "stderr", Duration::from_secs(62)),
+            b"[    62.000] stderr: partial\n".to_vec()
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::utils::is_running;
+use common::api::state::AppState;
+use common::configuration::ac_config::ACConfig;
+use common::models::bot_controller::DataUsage;
+use common::procs::limits::LimitViolation;
+use common::utilities::directory::directory_size;
+use common::utilities::portpicker::Port;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// How often the data directory of a running bot is measured
+const DATA_QUOTA_INTERVAL: Duration = Duration::from_secs(10);
+/// Key in the extra info of a bot that holds the largest measured size of its data directory
+const PEAK_KEY: &str = "DataPeakBytes";
+
+/// Quota of each bot's data directory in bytes, if there is one
+pub(crate) fn quota_bytes(settings: &ACConfig) -> Option<u64> {
+    settings.bot_data_quota_mb.map(|mb| mb * 1024 * 1024)
+}
+
+/// Measures `data_dir` until the bot under `process_key` has exited, and keeps
+/// the largest size in the extra info of `bot_key`. Going over the quota is recorded as a limit
+/// violation of the process, but the bot is not stopped.
+pub(crate) async fn watch_data_quota(
+    state: AppState,
+    process_key: Port,
+    bot_key: String,
+    data_dir: PathBuf,
+    quota_bytes: u64,
+) {
+    loop {
+        tokio::time::sleep(DATA_QUOTA_INTERVAL).await;
+        let running = is_running(&state.process_map, process_key);
+        let dir = data_dir.clone();
+        match tokio::task::spawn_blocking(move || directory_size(&dir)).await {
+            Ok(Ok(size)) => {
+                let previous_peak = record_peak(&state, &bot_key, size);
+                if size > quota_bytes && size > previous_peak {
+                    if previous_peak <= quota_bytes {
+                        warn!(
+                            "Data directory of bot {} is at {} bytes, over its quota of {} bytes",
+                            bot_key, size, quota_bytes
+                        );
+                    }
+                    if let Some(limited) = state.process_limits.write().get_mut(&process_key) {
+                        limited.record(LimitViolation::DataQuota {
+                            quota_bytes,
+                            used_bytes: size,
+                        });
+                    }
+                }
+            }
+            Ok(Err(e)) => error!("Could not measure data directory {:?}: {}", data_dir, e),
+            Err(e) => error!("Could not measure data directory {:?}: {}", data_dir, e),
+        }
+        if !running {
+            break;
+        }
+    }
+}
+
+/// Stores `size` as the peak if it is larger than the current one, and returns the previous peak
+fn record_peak(state: &AppState, bot_key: &str, size: u64) -> u64 {
+    let mut extra_info = state.extra_info.write();
+    let info = extra_info.entry(bot_key.to_string()).or_default();
+    let previous = info
+        .get(PEAK_KEY)
+        .and_then(|peak| peak.parse().ok())
+        .unwrap_or(0);
+    if size > previous {
+        info.insert(PEAK_KEY.to_string(), size.to_string());
+    }
+    previous
+}
+
+/// Forgets the peak of an earlier run of the bot
+pub(crate) fn reset_peak(state: &AppState, bot_key: &str) {
+    if let Some(info) = state.extra_info.write().get_mut(bot_key) {
+        info.remove(PEAK_KEY);
+    }
+}
+
+/// Current size of `data_dir`, along with the peak measured while the bot was running
+pub(crate) fn data_usage(
+    state: &AppState,
+    bot_key: &str,
+    data_dir: &Path,
+) -> std::io::Result<DataUsage> {
+    let used_bytes = directory_size(data_dir)?;
+    let peak_bytes = state
+        .extra_info
+        .read()
+        .get(bot_key)
+        .and_then(|info| info.get(PEAK_KEY))
+        .and_then(|peak| peak.parse().ok())
+        .unwrap_or(0);
+    Ok(DataUsage {
+        used_bytes,
+        peak_bytes: peak_bytes.max(used_bytes),
+        quota_bytes: quota_bytes(&state.settings),
+    })
+}
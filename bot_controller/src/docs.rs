@@ -8,8 +8,11 @@ use utoipa::OpenApi;
     openapi(
         paths(
             crate::routes::terminate_bot,
+            crate::routes::preflight,
             crate::routes::start_bot,
             crate::routes::download_bot_data,
+            crate::routes::bot_data_usage,
+            crate::routes::tail_bot_log,
             common::api::process::stats,
             common::api::process::stats_host,
             common::api::process::terminate_all,
@@ -19,12 +22,18 @@ use utoipa::OpenApi;
         ),
         components(schemas(
             common::models::bot_controller::StartBot,
-            common::models::bot_controller::BotType,
+            common::models::bot_controller::BotLog,
+            common::models::bot_controller::DataSnapshot,
+            common::models::bot_controller::DataUsage,
             common::models::Status,
+            common::preflight::PreflightReport,
+            common::preflight::PreflightCheck,
+            common::preflight::CheckStatus,
             common::models::TerminateResponse,
             common::models::StartResponse,
             common::models::ProcessStatusResponse,
-            common::api::process::ProcStatus
+            common::api::process::ProcStatus,
+            common::procs::limits::LimitViolation
         ))
     )
 )]
@@ -0,0 +1,233 @@
+use axum::response::sse::Event;
+use common::utilities::scrub::scrub_secrets;
+use futures_util::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::io::SeekFrom;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// How often the log file is checked for new output
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How much of the existing log is sent when a tail starts from the end
+const TAIL_START_BYTES: u64 = 16 * 1024;
+/// Lines longer than this are split up, in the merged log as well as in tails. Partial lines
+/// longer than this are sent without waiting for the line to end.
+pub(crate) const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Follows a log file that a running process writes to.
+///
+/// Each complete line becomes one `log` event, with the configured secrets scrubbed from it. Once
+/// `is_running` returns false and the rest of the file has been sent, a final `end` event is sent
+/// and the stream ends.
+pub struct LogTail<F> {
+    file: File,
+    secrets: Vec<String>,
+    is_running: F,
+    pending: Vec<u8>,
+    lines: VecDeque<String>,
+    discard_partial: bool,
+    finished: bool,
+}
+
+impl<F> LogTail<F>
+where
+    F: Fn() -> bool + Send + 'static,
+{
+    /// Opens a tail on `file`, starting at the last few KiB of output unless `from_start` is set.
+    pub async fn new(
+        mut file: File,
+        from_start: bool,
+        secrets: Vec<String>,
+        is_running: F,
+    ) -> std::io::Result<Self> {
+        let len = file.metadata().await?.len();
+        let start = if from_start {
+            0
+        } else {
+            len.saturating_sub(TAIL_START_BYTES)
+        };
+        file.seek(SeekFrom::Start(start)).await?;
+        Ok(Self {
+            file,
+            secrets,
+            is_running,
+            pending: Vec::new(),
+            lines: VecDeque::new(),
+            // Starting in the middle of the file most likely cuts a line in half
+            discard_partial: start > 0,
+            finished: false,
+        })
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<Event, Infallible>> {
+        stream::unfold(Some(self), |tail| async move {
+            let mut tail = tail?;
+            match tail.next_line().await {
+                Some(line) => Some((Ok(Event::default().event("log").data(line)), Some(tail))),
+                None => Some((Ok(Event::default().event("end").data("")), None)),
+            }
+        })
+    }
+
+    async fn next_line(&mut self) -> Option<String> {
+        let line = self.next_raw_line().await?;
+        if self.secrets.iter().all(String::is_empty) {
+            return Some(line);
+        }
+        Some(String::from_utf8_lossy(&scrub_secrets(line.as_bytes(), &self.secrets)).into_owned())
+    }
+
+    async fn next_raw_line(&mut self) -> Option<String> {
+        let mut buffer = vec![0u8; 8192];
+        loop {
+            if let Some(line) = self.lines.pop_front() {
+                return Some(line);
+            }
+            if self.finished {
+                if self.pending.is_empty() {
+                    return None;
+                }
+                return Some(to_line(&std::mem::take(&mut self.pending)));
+            }
+            // Check before reading, so output written right before the process exited is not lost
+            let running = (self.is_running)();
+            let read = match self.file.read(&mut buffer).await {
+                Ok(read) => read,
+                Err(e) => {
+                    tracing::warn!("Could not read bot log: {}", e);
+                    0
+                }
+            };
+            if read > 0 {
+                self.pending.extend_from_slice(&buffer[..read]);
+                let mut lines = split_lines(&mut self.pending);
+                if self.discard_partial && !lines.is_empty() {
+                    lines.remove(0);
+                    self.discard_partial = false;
+                }
+                self.lines.extend(lines);
+            } else if self.was_shrunk().await {
+                // The log was shrunk to its size cap, so continue at its new end
+                if let Err(e) = self.file.seek(SeekFrom::End(0)).await {
+                    tracing::warn!("Could not seek in bot log: {}", e);
+                    self.finished = true;
+                }
+            } else if running {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            } else {
+                self.finished = true;
+            }
+        }
+    }
+
+    async fn was_shrunk(&mut self) -> bool {
+        match (
+            self.file.metadata().await,
+            self.file.stream_position().await,
+        ) {
+            (Ok(metadata), Ok(position)) => metadata.len() < position,
+            _ => false,
+        }
+    }
+}
+
+/// Removes all complete lines from `pending`.
+///
+/// A partial line is left in `pending`, unless it grew beyond `MAX_LINE_BYTES`.
+fn split_lines(pending: &mut Vec<u8>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while let Some(end) = pending[start..].iter().position(|b| *b == b'\n') {
+        lines.push(to_line(&pending[start..start + end]));
+        start += end + 1;
+    }
+    pending.drain(..start);
+    if pending.len() > MAX_LINE_BYTES {
+        lines.push(to_line(&std::mem::take(pending)));
+    }
+    lines
+}
+
+/// SSE data can't contain carriage returns, which Windows bots tend to write
+fn to_line(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).replace('\r', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_split_lines() {
+        let mut pending = b"first\r\nsecond\nthi".to_vec();
+        let lines = split_lines(&mut pending);
+        assert_eq!(lines, vec!["first".to_string(), "second".to_string()]);
+        assert_eq!(pending, b"thi");
+
+        let mut long = vec![b'a'; MAX_LINE_BYTES + 1];
+        assert_eq!(split_lines(&mut long).len(), 1);
+        assert!(long.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tail_follows_until_process_exits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stderr.log");
+        let mut writer = std::fs::File::create(&path).unwrap();
+        writer.write_all(b"started\n").unwrap();
+
+        let running = Arc::new(AtomicBool::new(true));
+        let is_running = {
+            let running = running.clone();
+            move || running.load(Ordering::SeqCst)
+        };
+        let file = File::open(&path).await.unwrap();
+        let tail = LogTail::new(file, true, vec![], is_running).await.unwrap();
+        let mut stream = Box::pin(tail.into_stream());
+        assert!(stream.next().await.is_some());
+
+        writer.write_all(b"step 1\nno newline").unwrap();
+        running.store(false, Ordering::SeqCst);
+        let mut count = 1;
+        while stream.next().await.is_some() {
+            count += 1;
+        }
+        // "step 1", "no newline" and the end event
+        assert_eq!(count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_tail_skips_cut_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stderr.log");
+        let mut content = vec![b'x'; TAIL_START_BYTES as usize];
+        content.extend_from_slice(b"\nlast\n");
+        std::fs::write(&path, content).unwrap();
+
+        let file = File::open(&path).await.unwrap();
+        let mut tail = LogTail::new(file, false, vec![], || false).await.unwrap();
+        assert_eq!(tail.next_line().await, Some("last".to_string()));
+        assert_eq!(tail.next_line().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_tail_scrubs_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stdout.log");
+        std::fs::write(&path, b"token abc123 at /home/bot1\n").unwrap();
+
+        let secrets = vec!["abc123".to_string(), "/home/bot1".to_string()];
+        let file = File::open(&path).await.unwrap();
+        let mut tail = LogTail::new(file, true, secrets, || false).await.unwrap();
+        assert_eq!(
+            tail.next_line().await,
+            Some("token [REDACTED] at [REDACTED]".to_string())
+        );
+    }
+}
@@ -1,12 +1,23 @@
+mod capture;
+mod data_quota;
 #[cfg(feature = "swagger")]
 mod docs;
+mod log_tail;
+mod preflight;
+#[cfg(target_os = "linux")]
+mod proxy_relay;
 mod routes;
+mod runtime;
+#[cfg(target_os = "linux")]
+mod sandbox;
 mod utils;
+mod venv;
 
 #[cfg(feature = "swagger")]
 use crate::docs::ApiDoc;
 use crate::routes::{
-    download_bot_data, download_bot_log, download_controller_log, start_bot, terminate_bot,
+    bot_data_usage, download_bot_data, download_bot_log, download_controller_log, preflight,
+    start_bot, tail_bot_log, terminate_bot,
 };
 use axum::http::Request;
 use axum::response::Response;
@@ -20,6 +31,7 @@ use common::api::process::{
 use common::api::state::AppState;
 use common::configuration::{get_config_from_proxy, get_host_url, get_proxy_url_from_env};
 use common::logging::init_logging;
+use common::preflight::{configuration_failed, exit_with_report};
 use tower::{BoxError, ServiceBuilder};
 use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer};
 use tracing::{debug, Span};
@@ -39,6 +51,7 @@ static PREFIX: &str = "ACBOT";
 async fn main() {
     let matches = command!()
         .arg(arg!(--port <VALUE>).value_parser(value_parser!(u16)))
+        .arg(arg!(--check "Check the environment, print a report and exit"))
         .get_matches();
 
     let port = *matches.get_one::<u16>("port").unwrap_or(&8081);
@@ -49,9 +62,15 @@ async fn main() {
     let config_url = format!("http://{proxy_url}/configuration");
     let health_url = format!("http://{proxy_url}/health");
 
-    let settings = get_config_from_proxy(config_url, health_url, PREFIX)
-        .await
-        .unwrap(); //panic if we can't get the config
+    let check = matches.get_flag("check");
+    let settings = match get_config_from_proxy(config_url, health_url, PREFIX).await {
+        Ok(settings) => settings,
+        Err(e) if check => exit_with_report(&configuration_failed("bot_controller", e)),
+        Err(e) => panic!("Could not get the config from the proxy: {e:?}"),
+    };
+    if check {
+        exit_with_report(&preflight::run_checks(&settings));
+    }
     let env_log = std::env::var("RUST_LOG").unwrap_or_else(|_| {
         format!(
             "info,common={},bot_controller={}",
@@ -76,6 +95,7 @@ async fn main() {
         settings,
         shutdown_sender: tx,
         extra_info: Default::default(),
+        process_limits: Default::default(),
     };
 
     #[allow(unused_mut)]
@@ -95,9 +115,12 @@ async fn main() {
         .route("/terminate_all", post(terminate_all))
         .route("/terminate/:bot_name", post(terminate_bot))
         .route("/shutdown", post(shutdown))
+        .route("/preflight", get(preflight))
         .route("/download/controller_log", get(download_controller_log))
         .route("/download/bot/:port/log", get(download_bot_log))
         .route("/download/bot/:port/data", get(download_bot_data))
+        .route("/download/bot/:port/data/usage", get(bot_data_usage))
+        .route("/logs/bot/:bot_name/tail", get(tail_bot_log))
         // Add middleware to all routes
         .layer(
             TraceLayer::new_for_http()
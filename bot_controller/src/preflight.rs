@@ -0,0 +1,117 @@
+use common::configuration::ac_config::ACConfig;
+use common::preflight::{
+    check_directory, check_program, check_writable_directory, common_checks, PreflightCheck,
+    PreflightReport,
+};
+use std::path::Path;
+
+pub fn run_checks(settings: &ACConfig) -> PreflightReport {
+    let mut checks = common_checks(settings);
+    if settings.secure_mode {
+        checks.push(check_writable_directory(
+            "Bot 1 directory",
+            Path::new(&settings.bot1_directory),
+        ));
+        checks.push(check_writable_directory(
+            "Bot 2 directory",
+            Path::new(&settings.bot2_directory),
+        ));
+    } else {
+        checks.push(check_directory(
+            "Bots directory",
+            Path::new(&settings.bots_directory),
+        ));
+    }
+    // Each runtime is only needed for bots of its type
+    let runtimes = [
+        ("Python runtime", settings.python.as_str()),
+        ("Wine runtime", "wine"),
+        ("Dotnet runtime", "dotnet"),
+        ("Java runtime", "java"),
+        ("Node runtime", "node"),
+    ];
+    checks.extend(
+        runtimes
+            .into_iter()
+            .map(|(name, program)| check_program(name, program).optional()),
+    );
+    // Configured runtimes whose program is looked up in PATH, not shipped with the bot
+    let mut configured: Vec<_> = settings
+        .bot_runtimes
+        .iter()
+        .filter(|(_, runtime)| !runtime.command.contains('{') && !runtime.command.starts_with('.'))
+        .collect();
+    configured.sort_by_key(|(name, _)| name.as_str());
+    checks.extend(configured.into_iter().map(|(name, runtime)| {
+        check_program(&format!("{name} runtime"), &runtime.command).optional()
+    }));
+    if let Some(wheelhouse) = &settings.python_wheelhouse {
+        checks.push(check_directory("Python wheelhouse", Path::new(wheelhouse)));
+        checks.push(check_writable_directory(
+            "Python venv directory",
+            Path::new(&settings.python_venv_directory),
+        ));
+        if settings.bot_sandbox && Path::new(&settings.python_venv_directory).starts_with("/tmp") {
+            checks.push(PreflightCheck::failed(
+                "Python venv directory",
+                "Sandboxed bots get an empty /tmp and can't see virtualenvs in it",
+                "Move PYTHON_VENV_DIRECTORY out of /tmp",
+            ));
+        }
+    }
+    if settings.bot_sandbox {
+        checks.push(check_sandbox(settings));
+    }
+    PreflightReport::new("bot_controller", checks)
+}
+
+#[cfg(target_os = "linux")]
+fn check_sandbox(settings: &ACConfig) -> PreflightCheck {
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+    let name = "Bot sandbox";
+    let fix = "Run the controller as root on a kernel with user and network namespaces, or \
+               disable BOT_SANDBOX";
+    let bot_dir = match tempfile::tempdir() {
+        Ok(bot_dir) => bot_dir,
+        Err(e) => return PreflightCheck::failed(name, format!("No temp directory: {e}"), fix),
+    };
+    let data_dir = bot_dir.path().join("data");
+    let uid = settings.bot_sandbox_uid;
+    // The bot's user has to get through the directories above its data
+    let prepared = std::fs::set_permissions(bot_dir.path(), Permissions::from_mode(0o755))
+        .and_then(|_| std::fs::create_dir(&data_dir))
+        .and_then(|_| crate::sandbox::give_to(&data_dir, uid, uid))
+        .and_then(|_| {
+            crate::sandbox::Sandbox::new(
+                &data_dir,
+                0,
+                &bot_dir.path().join(".proxy.sock"),
+                uid,
+                uid,
+                settings.bot_seccomp,
+            )
+        });
+    let sandbox = match prepared {
+        Ok(sandbox) => sandbox,
+        Err(e) => return PreflightCheck::failed(name, e.to_string(), fix),
+    };
+    match sandbox.probe() {
+        Ok(()) => PreflightCheck::ok(name, format!("Bots can run as user {uid} in a sandbox")),
+        Err(e) => PreflightCheck::failed(
+            name,
+            format!("Could not set up the user, namespaces and mounts of the sandbox: {e}"),
+            "Run the controller as root and allow user namespaces and mounts (e.g. a seccomp \
+             profile that permits unshare and mount), or disable BOT_SANDBOX",
+        ),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_sandbox(_settings: &ACConfig) -> PreflightCheck {
+    PreflightCheck::failed(
+        "Bot sandbox",
+        "The bot sandbox is only supported on Linux",
+        "Disable BOT_SANDBOX",
+    )
+}
@@ -0,0 +1,76 @@
+//! Controller side of the path from a sandboxed bot to the proxy. The network namespace of the
+//! bot has no route out, so the relay inside it forwards connections to a unix socket that is
+//! served here.
+
+use crate::utils::is_running;
+use common::api::process::ProcessMap;
+use common::utilities::portpicker::Port;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::net::{TcpStream, UnixListener};
+use tracing::{debug, error};
+
+/// How often the relay checks whether the bot is still running
+const RUNNING_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct RelaySocket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl RelaySocket {
+    /// Binds the socket at `path`, which only the user of the bot can connect to
+    pub fn bind(path: &Path, uid: u32, gid: u32) -> io::Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+        // Left over by an earlier run of the same bot
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = UnixListener::bind(path)?;
+        std::os::unix::fs::chown(path, Some(uid), Some(gid))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Forwards every connection to `proxy_addr` until the bot with `process_key` stops
+    pub async fn serve(self, proxy_addr: String, process_map: ProcessMap, process_key: Port) {
+        while is_running(&process_map, process_key) {
+            let accepted =
+                tokio::time::timeout(RUNNING_CHECK_INTERVAL, self.listener.accept()).await;
+            let mut bot_stream = match accepted {
+                Ok(Ok((stream, _))) => stream,
+                Ok(Err(e)) => {
+                    error!("Proxy relay of bot {} failed: {}", process_key, e);
+                    break;
+                }
+                Err(_) => continue,
+            };
+            let proxy_addr = proxy_addr.clone();
+            tokio::spawn(async move {
+                let mut proxy_stream = match TcpStream::connect(&proxy_addr).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        error!("Could not relay bot {} to the proxy: {}", process_key, e);
+                        return;
+                    }
+                };
+                if let Err(e) =
+                    tokio::io::copy_bidirectional(&mut bot_stream, &mut proxy_stream).await
+                {
+                    debug!("Proxy relay of bot {} ended: {}", process_key, e);
+                }
+            });
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -1,30 +1,45 @@
-use crate::utils::{download_and_extract, move_bot_to_internal_dir};
+use crate::capture::capture_output;
+use crate::data_quota::{data_usage, quota_bytes, reset_peak, watch_data_quota};
+use crate::log_tail::LogTail;
+use crate::runtime::{bot_command, prepare_bot_files, resolve_runtime, run_setup};
+use crate::utils::{
+    apply_data_snapshot, copy_bot_data_back, download_and_extract, enforce_bot_log_caps,
+    is_running, is_valid_bot_name, move_bot_to_internal_dir, package_bot_logs,
+};
+use crate::venv::{prepare_venv, venv_python};
 use crate::PREFIX;
 use axum::body::StreamBody;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::header;
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::Json;
 use common::api::errors::app_error::AppError;
 use common::api::errors::download_error::DownloadError;
 use common::api::errors::process_error::ProcessError;
 use common::api::state::AppState;
+use common::configuration::ac_config::{ACConfig, DataQuotaPolicy};
 use common::configuration::{get_proxy_host, get_proxy_port, get_proxy_url_from_env};
-use common::models::bot_controller::{BotType, StartBot};
+use common::models::bot_controller::{BotLog, BotType, DataUsage, LogTailQuery, StartBot};
 use common::models::{StartResponse, Status, TerminateResponse};
-use common::procs::tcp_port::get_ipv4_port_for_pid;
+use common::preflight::PreflightReport;
+use common::procs::limits::{exit_violation, LimitViolation, LimitedProcess, ResourceLimits};
+use common::procs::log_cap::LogCap;
 
-use common::utilities::directory::ensure_directory_structure;
+use common::utilities::directory::{copy_directory_within, ensure_directory_structure};
 use common::utilities::portpicker::Port;
 use common::utilities::zip_utils::zip_directory;
+use common::PlayerNum;
 use tokio::net::lookup_host;
 use tokio_util::io::ReaderStream;
 use tracing::debug;
 
 use common::api::{BytesResponse, FileResponse};
-use common::procs::create_stdout_and_stderr_files;
+use common::procs::{create_log_file, create_stdout_and_stderr_files};
+use futures_util::Stream;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
 use tracing::log::trace;
 
 #[tracing::instrument(skip(state))]
@@ -43,17 +58,31 @@ pub async fn terminate_bot(
     State(state): State<AppState>,
 ) -> Result<Json<TerminateResponse>, AppError> {
     tracing::info!("Terminating bot with key {}", process_key);
+    let mut violations = Vec::new();
     if let Some((_, mut child)) = state.process_map.write().remove_entry(&process_key) {
-        if let Err(e) = child.kill() {
-            return Err(ProcessError::TerminateError(e.to_string()).into());
+        match child.try_status() {
+            Ok(Some(exit_status)) => violations.extend(exit_violation(exit_status)),
+            _ => {
+                if let Err(e) = child.kill() {
+                    return Err(ProcessError::TerminateError(e.to_string()).into());
+                }
+            }
         }
     } else {
         let message = format!("Bot {process_key} entry does not exist");
         return Err(ProcessError::TerminateError(message).into());
     }
+    if let Some(limited) = state.process_limits.write().remove(&process_key) {
+        violations.extend(limited.recorded().iter().cloned());
+        limited.cleanup();
+    }
+    if !violations.is_empty() {
+        tracing::warn!("Bot {} ran into limits: {:?}", process_key, violations);
+    }
 
     Ok(Json(TerminateResponse {
         status: Status::Success,
+        violations,
     }))
 }
 
@@ -78,6 +107,8 @@ pub async fn start_bot(
         match_id: _match_id,
         process_key,
         should_download,
+        proxy_port,
+        data_snapshot,
     } = &start_bot;
     let bot_path =
         std::path::PathBuf::from(format!("{}/{}", &state.settings.bots_directory, bot_name));
@@ -122,15 +153,6 @@ pub async fn start_bot(
     }
     let mut bot_path = format!("{}/{}", &state.settings.bots_directory, bot_name);
 
-    let (program, filename) = match bot_type {
-        BotType::CppWin32 => ("wine".to_string(), format!("{bot_name}.exe")),
-        BotType::CppLinux => (format!("./{bot_name}"), String::new()),
-        BotType::DotnetCore => ("dotnet".to_string(), format!("{bot_name}.dll")),
-        BotType::Java => ("java".to_string(), format!("{bot_name}.jar")),
-        BotType::NodeJs => ("node".to_string(), format!("{bot_name}.js")),
-        BotType::Python => (state.settings.python.clone(), "run.py".to_string()),
-    };
-
     if !std::path::Path::new(&bot_path).exists() {
         return Err(ProcessError::StartError(format!(
             "Supplied bot path does not exist: {:?}",
@@ -138,10 +160,11 @@ pub async fn start_bot(
         ))
         .into());
     }
+    let mut source_directory = None;
     if state.settings.secure_mode {
         match move_bot_to_internal_dir(&state.settings, &bot_path, *player_num) {
             Ok(new_path) => {
-                bot_path = new_path;
+                source_directory = Some(std::mem::replace(&mut bot_path, new_path));
             }
             Err(e) => {
                 let message = format!("Could not move bots to internal directory:\n{e}");
@@ -153,6 +176,11 @@ pub async fn start_bot(
         let message = format!("Could not validate directory structure:\n{e}");
         return Err(ProcessError::StartError(message).into());
     }
+    let data_dir = std::path::Path::new(&bot_path).join("data");
+    if let Err(e) = apply_data_snapshot(&state.settings, *data_snapshot, &data_dir, *player_num) {
+        let message = format!("Data snapshot ({data_snapshot:?}) failed:\n{e}");
+        return Err(ProcessError::StartError(message).into());
+    }
     if !std::path::Path::new(&state.settings.log_root).exists() {
         if let Err(e) = tokio::fs::create_dir_all(&state.settings.log_root).await {
             return Err(ProcessError::StartError(e.to_string()).into());
@@ -164,12 +192,13 @@ pub async fn start_bot(
     }
     debug!("Bot log dir exists");
 
-    let log_file_path = std::path::Path::new(&state.settings.log_root)
-        .join(bot_name)
-        .join("stderr.log");
-    debug!("Bot log path: {:?}", log_file_path);
+    let mut runtime = resolve_runtime(&state.settings, bot_type, bot_name, &bot_path)?;
+    prepare_bot_files(&runtime, &bot_path)?;
+
+    let log_dir = std::path::Path::new(&state.settings.log_root).join(bot_name);
+    debug!("Bot log directory: {:?}", log_dir);
 
-    let (stdout_file, stderr_file) = match create_stdout_and_stderr_files(&log_file_path) {
+    let (stdout_file, stderr_file) = match create_stdout_and_stderr_files(&log_dir) {
         Ok(files) => files,
         Err(e) => {
             return Err(AppError::Process(ProcessError::StartError(format!(
@@ -179,57 +208,44 @@ pub async fn start_bot(
         }
     };
 
-    debug!("Log files created: {:?}", log_file_path);
-
-    let encoded_bot_name = urlencoding::encode(bot_name);
-
-    match state.extra_info.write().entry(encoded_bot_name.to_string()) {
-        Entry::Occupied(mut occ) => {
-            occ.get_mut()
-                .insert("BotDirectory".to_string(), bot_path.clone());
-        }
-        Entry::Vacant(vac) => {
-            vac.insert(HashMap::new())
-                .insert("BotDirectory".to_string(), bot_path.clone());
+    let merged_file = if state.settings.bot_log_merged {
+        let merged_path = log_dir.join(BotLog::Merged.file_name());
+        match create_log_file(&merged_path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                return Err(ProcessError::StartError(format!(
+                    "Failed to create merged log file: {e}"
+                ))
+                .into());
+            }
         }
-    }
+    } else {
+        // Don't leave the merged log of an earlier run around
+        let _ = std::fs::remove_file(log_dir.join(BotLog::Merged.file_name()));
+        None
+    };
 
-    let mut command = async_process::Command::new(&program);
+    debug!("Log files created in {:?}", log_dir);
 
-    if bot_type == &BotType::Java {
-        command.arg("-jar");
-    }
-    if !filename.is_empty() {
-        command.arg(filename);
-    }
+    record_bot_directory(&state, bot_name, &bot_path, source_directory.as_deref());
 
-    #[cfg(not(windows))]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        use tracing::debug;
-        if bot_type == &BotType::CppLinux {
-            let bot_file_path = std::path::Path::new(&bot_path).join(&program);
-            if let Ok(bot_file) = std::fs::metadata(&bot_file_path) {
-                debug!("Setting bot file permissions");
-                let mut perms = bot_file.permissions();
-                perms.set_mode(0o777);
-                let _ = std::fs::set_permissions(&bot_file_path, perms);
-            }
-            // if std::path::Path::new(&bot_path).is_dir() {
-            //     for item in std::fs::read_dir(&bot_path).unwrap() {
-            //         debug!("Setting bot folder item's permissions");
-            //         if let Ok(item) = item {
-            //             if let Ok(bot_dir_file) = std::fs::metadata(&item.path()) {
-            //                 let mut perms = bot_dir_file.permissions();
-            //                 perms.set_mode(0o777);
-            //                 perms.set_mode(mode)
-            //             }
-            //         }
-            //     }
-            // }
+    // Only the built-in python runtime is switched to the virtualenv, configured ones keep their
+    // own command
+    if bot_type == &BotType::Python {
+        if let Some(venv) =
+            prepare_venv(&state.settings, &bot_path, &stdout_file, &stderr_file).await?
+        {
+            runtime.command = venv_python(&venv).to_string_lossy().into_owned();
         }
     }
-    let (proxy_host, proxy_port) = (get_proxy_host(PREFIX), get_proxy_port(PREFIX));
+    run_setup(&runtime, &bot_path, &stdout_file, &stderr_file).await?;
+    let mut command = bot_command(&runtime, &bot_path);
+
+    let proxy_host = get_proxy_host(PREFIX);
+    let proxy_port = match proxy_port {
+        Some(proxy_port) => proxy_port.to_string(),
+        None => get_proxy_port(PREFIX),
+    };
 
     let temp_proxy_host = format!("{proxy_host}:{proxy_port}");
 
@@ -239,28 +255,82 @@ pub async fn start_bot(
     }
     .unwrap_or(proxy_host);
 
+    let limits = resource_limits(&state.settings);
+    limits
+        .apply_to_command(&mut command)
+        .map_err(|e| ProcessError::StartError(format!("Invalid bot limits: {e}")))?;
+    // Sandboxed bots reach the proxy through a relay of the controller
+    let relay_socket = if state.settings.bot_sandbox {
+        Some(sandbox_command(
+            &mut command,
+            &state.settings,
+            &bot_path,
+            &proxy_port,
+            *player_num,
+        )?)
+    } else {
+        None
+    };
+    let ladder_server = match &relay_socket {
+        Some(relay_socket) => sandbox_proxy_host(relay_socket).to_string(),
+        None => resolved_proxy_host.clone(),
+    };
+
+    // With a merged log or a size cap the outputs are piped through the controller, otherwise
+    // the bot writes to its log files directly
+    let log_cap = LogCap::for_bots(&state.settings);
+    let log_files = if merged_file.is_some() || log_cap.is_some() {
+        command
+            .stdout(async_process::Stdio::piped())
+            .stderr(async_process::Stdio::piped());
+        Some((stdout_file, stderr_file, merged_file))
+    } else {
+        command.stdout(stdout_file).stderr(stderr_file);
+        None
+    };
     command
-        .stdout(stdout_file)
-        .stderr(stderr_file)
         .arg("--GamePort")
         .arg(&proxy_port)
         .arg("--LadderServer")
-        .arg(resolved_proxy_host)
+        .arg(ladder_server)
         .arg("--StartPort")
         .arg(&proxy_port)
         .arg("--OpponentId")
-        .arg(opponent_id)
-        .current_dir(&bot_path);
+        .arg(opponent_id);
+    let mut violations = Vec::new();
 
     debug!("Starting bot with command {:?}", &command);
-    let mut process = match command.spawn() {
+    let started = Instant::now();
+    let process = match command.spawn() {
         Ok(mut process) => {
+            if let Some((stdout_file, stderr_file, merged_file)) = log_files {
+                if let Err(e) = capture_output(
+                    &mut process,
+                    &log_dir,
+                    stdout_file,
+                    stderr_file,
+                    merged_file,
+                    log_cap,
+                    started,
+                ) {
+                    let _ = process.kill();
+                    let _ = process.status().await;
+                    return Err(ProcessError::StartError(format!(
+                        "Could not capture bot output: {e}"
+                    ))
+                    .into());
+                }
+            }
             tokio::time::sleep(Duration::from_secs(2)).await;
             match process.try_status() {
                 Ok(None) => {}
                 Ok(Some(exit_status)) => {
+                    let reason = match exit_violation(exit_status) {
+                        Some(LimitViolation::Killed { reason, .. }) => format!(": {reason}"),
+                        _ => String::new(),
+                    };
                     return Err(ProcessError::StartError(format!(
-                        "Bot {bot_name} has exited within 5 seconds with status {exit_status}"
+                        "Bot {bot_name} has exited within 5 seconds with status {exit_status}{reason}"
                     ))
                     .into());
                 }
@@ -277,36 +347,53 @@ pub async fn start_bot(
             return Err(ProcessError::StartError(e.to_string()).into());
         }
     };
+    // The bot is ready once it connects to the proxy, which the proxy detects itself. The
+    // process is kept under the key the proxy gave it.
     let pid = process.id();
-
-    let max_retries = 10;
-    let mut counter = 0;
-    let mut port = None;
-
-    while port.is_none() && counter < max_retries {
-        counter += 1;
-
-        port = get_ipv4_port_for_pid(pid);
-        if port.is_some() {
-            break;
-        }
-        tokio::time::sleep(Duration::from_secs(3)).await;
+    // The data quota watcher records its violations with the limits of the process
+    if !limits.is_empty() || quota_bytes(&state.settings).is_some() {
+        let limited = LimitedProcess::new(&limits, &format!("bot_{process_key}"), pid);
+        violations.extend(limited.violations(0));
+        state.process_limits.write().insert(*process_key, limited);
     }
-
-    if let Some(port) = port {
-        state.process_map.write().insert(port, process);
-
-        let start_response = StartResponse {
-            status: Status::Success,
-            status_reason: "".to_string(),
-            port,
-            process_key: *process_key,
-        };
-        Ok(Json(start_response))
-    } else {
-        process.kill().expect("Could not kill process");
-        Err(ProcessError::StartError("Could not find port for started process".to_string()).into())
+    state.process_map.write().insert(*process_key, process);
+    if let Some(relay_socket) = relay_socket {
+        serve_proxy_relay(
+            relay_socket,
+            format!("{resolved_proxy_host}:{proxy_port}"),
+            &state,
+            *process_key,
+        );
+    }
+    if let Some(info) = state.extra_info.write().get_mut(bot_name) {
+        info.insert("ProcessKey".to_string(), process_key.to_string());
+    }
+    reset_peak(&state, bot_name);
+    if let Some(quota_bytes) = quota_bytes(&state.settings) {
+        tokio::spawn(watch_data_quota(
+            state.clone(),
+            *process_key,
+            bot_name.clone(),
+            std::path::Path::new(&bot_path).join("data"),
+            quota_bytes,
+        ));
     }
+    if !violations.is_empty() {
+        tracing::warn!(
+            "Bot {} started with limit issues: {:?}",
+            bot_name,
+            violations
+        );
+    }
+
+    let start_response = StartResponse {
+        status: Status::Success,
+        status_reason: "".to_string(),
+        port: *process_key,
+        process_key: *process_key,
+        violations,
+    };
+    Ok(Json(start_response))
 }
 
 #[tracing::instrument(skip(state))]
@@ -344,30 +431,78 @@ params(
 ("process_key" = u16, Path, description = "process_key of bot process to fetch logs for")
 ),
 responses(
-(status = 200, description = "Request Completed", body = FileResponse)
+(status = 200, description = "Zip with stdout.log, stderr.log and, if enabled, merged.log", content_type = "application/zip")
 )
 ))]
 pub async fn download_bot_log(
     Path(bot_name): Path<String>,
     State(state): State<AppState>,
-) -> Result<FileResponse, AppError> {
+) -> Result<BytesResponse, AppError> {
+    let log_dir = std::path::Path::new(&state.settings.log_root).join(&bot_name);
+
+    debug!("Log directory: {:?}", log_dir);
+
+    // Every bot that was started has a stderr log
+    std::fs::metadata(log_dir.join(BotLog::Stderr.file_name()))
+        .map_err(|e| AppError::Download(DownloadError::FileNotFound(e)))?;
+
+    if let Some(cap) = LogCap::for_bots(&state.settings) {
+        enforce_bot_log_caps(&log_dir, &cap);
+    }
+
+    let tmp_dir = tempfile::tempdir().map_err(DownloadError::from)?;
+    let path = tmp_dir.path().join("logs.zip");
+    package_bot_logs(&log_dir, &path)?;
+    let buffer = tokio::fs::read(&path).await.map_err(DownloadError::from)?;
+    let body = buffer.into();
+    let headers = [(header::CONTENT_TYPE, "application/zip; charset=utf-8")];
+
+    Ok((headers, body))
+}
+
+#[tracing::instrument(skip(state))]
+#[cfg_attr(feature = "swagger", utoipa::path(
+get,
+path = "/logs/bot/{bot_name}/tail",
+params(
+("bot_name" = String, Path, description = "Name of the bot to follow the log of"),
+LogTailQuery
+),
+responses(
+(status = 200, description = "Server-sent events with one `log` event per line, and an `end` event once the bot has exited", content_type = "text/event-stream")
+)
+))]
+pub async fn tail_bot_log(
+    Path(bot_name): Path<String>,
+    Query(query): Query<LogTailQuery>,
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    if !is_valid_bot_name(&bot_name) {
+        return Err(ProcessError::Custom(format!("Invalid bot name {bot_name:?}")).into());
+    }
     let log_path = std::path::Path::new(&state.settings.log_root)
         .join(&bot_name)
-        .join("stderr.log");
-
-    debug!("Log path: {:?}", log_path);
+        .join(query.log.file_name());
 
     let file = tokio::fs::File::open(&log_path)
         .await
         .map_err(|e| AppError::Download(DownloadError::FileNotFound(e)))?;
 
-    // convert the `AsyncRead` into a `Stream`
-    let stream = ReaderStream::new(file);
-    // convert the `Stream` into an `axum::body::HttpBody`
-    let body = StreamBody::new(stream);
+    let process_key = state
+        .extra_info
+        .read()
+        .get(&bot_name)
+        .and_then(|x| x.get("ProcessKey"))
+        .and_then(|x| x.parse::<Port>().ok());
+    let process_map = state.process_map.clone();
+    let bot_running = move || process_key.map_or(false, |key| is_running(&process_map, key));
 
-    let headers = [(header::CONTENT_TYPE, "text/log; charset=utf-8")];
-    Ok((headers, body))
+    let secrets = state.settings.bot_log_scrub.clone();
+    let tail = LogTail::new(file, query.from_start, secrets, bot_running)
+        .await
+        .map_err(|e| AppError::Download(DownloadError::FileNotFound(e)))?;
+
+    Ok(Sse::new(tail.into_stream()).keep_alive(KeepAlive::default()))
 }
 
 #[tracing::instrument(skip(state))]
@@ -378,31 +513,69 @@ params(
 ("process_key" = u16, Path, description = "process_key of bot process to fetch data for")
 ),
 responses(
-(status = 200, description = "Request Completed", content_type = "application/octet")
+(status = 200, description = "Request Completed", content_type = "application/octet"),
+(status = 413, description = "The data is over its quota and the quota policy is to reject it")
 )
 ))]
 pub async fn download_bot_data(
     Path(bot_name): Path<String>,
     State(state): State<AppState>,
 ) -> Result<BytesResponse, AppError> {
-    let bot_directory = state
+    let bot_directory = bot_directory(&state, &bot_name)?;
+    let usage = data_usage(
+        &state,
+        &bot_name,
+        &std::path::Path::new(&bot_directory).join("data"),
+    )
+    .map_err(DownloadError::from)?;
+    if let Some(quota_bytes) = usage.quota_bytes {
+        if usage.over_quota() && state.settings.bot_data_quota_policy == DataQuotaPolicy::Reject {
+            return Err(DownloadError::QuotaExceeded(format!(
+                "Data of bot {bot_name} is {} bytes, over its quota of {quota_bytes} bytes",
+                usage.used_bytes
+            ))
+            .into());
+        }
+    }
+    let zip_file = format!("{bot_name}_temp.zip");
+    let tmp_dir = tempfile::tempdir().map_err(DownloadError::from)?;
+    let path = tmp_dir.path().join(zip_file);
+
+    // The truncate policy only keeps the files that fit in the quota. This happens before the data
+    // is copied back, so the bot directory ends up with the same files that get uploaded.
+    let bot_data_directory = std::path::Path::new(&bot_directory).join("data");
+    let bot_data_directory = match usage.quota_bytes {
+        Some(quota_bytes) if usage.over_quota() => {
+            let truncated = tmp_dir.path().join("data");
+            let omitted = copy_directory_within(&bot_data_directory, &truncated, quota_bytes)
+                .map_err(DownloadError::from)?;
+            tracing::warn!(
+                "Left {} bytes of files out of the data of bot {} to fit its quota",
+                omitted,
+                bot_name
+            );
+            truncated
+        }
+        _ => bot_data_directory,
+    };
+
+    let source_directory = state
         .extra_info
         .read()
         .get(&bot_name)
-        .and_then(|x| x.get("BotDirectory"))
-        .ok_or_else(|| {
-            AppError::Download(DownloadError::BotFolderNotFound(format!(
-                "Could not find directory entry for bot {bot_name:?}"
-            )))
-        })?
-        .clone();
-    let bot_data_directory = std::path::Path::new(&bot_directory).join("data");
+        .and_then(|x| x.get("SourceDirectory"))
+        .cloned();
+    let bot_data_directory = match source_directory {
+        Some(source_directory) => {
+            let source_directory = std::path::Path::new(&source_directory);
+            copy_bot_data_back(&bot_data_directory, source_directory)
+                .map_err(DownloadError::from)?;
+            source_directory.join("data")
+        }
+        None => bot_data_directory,
+    };
     trace!("{:?}", bot_data_directory.metadata());
 
-    let zip_file = format!("{bot_name}_temp.zip");
-    let tmp_dir = tempfile::tempdir().map_err(DownloadError::from)?;
-    let path = tmp_dir.path().join(zip_file);
-
     zip_directory(&path, &bot_data_directory).map_err(DownloadError::from)?;
     let buffer = tokio::fs::read(&path).await.map_err(DownloadError::from)?;
     let body = buffer.into();
@@ -411,3 +584,229 @@ pub async fn download_bot_data(
 
     Ok((headers, body))
 }
+
+#[tracing::instrument(skip(state))]
+#[cfg_attr(feature = "swagger", utoipa::path(
+get,
+path = "/download/bot/{process_key}/data/usage",
+params(
+("process_key" = u16, Path, description = "process_key of bot process to measure the data of")
+),
+responses(
+(status = 200, description = "Size of the data directory and its quota", body = DataUsage)
+)
+))]
+pub async fn bot_data_usage(
+    Path(bot_name): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<DataUsage>, AppError> {
+    let bot_directory = bot_directory(&state, &bot_name)?;
+    let usage = data_usage(
+        &state,
+        &bot_name,
+        &std::path::Path::new(&bot_directory).join("data"),
+    )
+    .map_err(DownloadError::from)?;
+    Ok(Json(usage))
+}
+
+/// Remembers where the bot runs from for the routes that act on it afterwards. In secure mode the
+/// bot runs from a copy, and its data has to be copied back to `source_directory`.
+///
+/// The info of a bot is keyed by its plain name. Routes get it percent-decoded from their path.
+fn record_bot_directory(
+    state: &AppState,
+    bot_name: &str,
+    bot_path: &str,
+    source_directory: Option<&str>,
+) {
+    let mut extra_info = state.extra_info.write();
+    let info = match extra_info.entry(bot_name.to_string()) {
+        Entry::Occupied(occ) => occ.into_mut(),
+        Entry::Vacant(vac) => vac.insert(HashMap::new()),
+    };
+    info.insert("BotDirectory".to_string(), bot_path.to_string());
+    match source_directory {
+        Some(source) => info.insert("SourceDirectory".to_string(), source.to_string()),
+        None => info.remove("SourceDirectory"),
+    };
+}
+
+/// Directory the bot runs from
+fn bot_directory(state: &AppState, bot_name: &str) -> Result<String, AppError> {
+    state
+        .extra_info
+        .read()
+        .get(bot_name)
+        .and_then(|x| x.get("BotDirectory"))
+        .cloned()
+        .ok_or_else(|| {
+            AppError::Download(DownloadError::BotFolderNotFound(format!(
+                "Could not find directory entry for bot {bot_name:?}"
+            )))
+        })
+}
+
+#[tracing::instrument(skip(state))]
+#[cfg_attr(feature = "swagger", utoipa::path(
+    get,
+    path = "/preflight",
+    responses(
+        (status = 200, description = "Environment check report", body = PreflightReport)
+    )
+))]
+pub async fn preflight(State(state): State<AppState>) -> Json<PreflightReport> {
+    Json(crate::preflight::run_checks(&state.settings))
+}
+
+fn resource_limits(settings: &ACConfig) -> ResourceLimits {
+    ResourceLimits {
+        memory_limit_mb: settings.bot_memory_limit_mb,
+        max_processes: settings.bot_max_processes,
+        max_open_files: settings.bot_max_open_files,
+        ..Default::default()
+    }
+}
+
+#[cfg(target_os = "linux")]
+type RelaySocket = crate::proxy_relay::RelaySocket;
+#[cfg(not(target_os = "linux"))]
+type RelaySocket = Infallible;
+
+/// User and group id the bot of `player_num` runs as in the sandbox
+fn sandbox_uid(settings: &ACConfig, player_num: PlayerNum) -> u32 {
+    match player_num {
+        PlayerNum::One => settings.bot_sandbox_uid,
+        PlayerNum::Two => settings.bot_sandbox_uid.saturating_add(1),
+    }
+}
+
+/// Sets up the bot sandbox for `command`. The bot can only reach the proxy through the returned
+/// socket, which has to be served once the bot runs.
+#[cfg(target_os = "linux")]
+fn sandbox_command(
+    command: &mut async_process::Command,
+    settings: &ACConfig,
+    bot_path: &str,
+    proxy_port: &str,
+    player_num: PlayerNum,
+) -> Result<RelaySocket, ProcessError> {
+    let proxy_port = proxy_port
+        .parse::<u16>()
+        .map_err(|e| ProcessError::StartError(format!("Invalid proxy port {proxy_port:?}: {e}")))?;
+    let uid = sandbox_uid(settings, player_num);
+    let data_dir = std::fs::canonicalize(std::path::Path::new(bot_path).join("data"))
+        .map_err(|e| ProcessError::StartError(format!("Could not resolve bot data dir: {e}")))?;
+    crate::sandbox::give_to(&data_dir, uid, uid).map_err(|e| {
+        ProcessError::StartError(format!("Could not hand the bot data dir to its user: {e}"))
+    })?;
+    let relay_socket = RelaySocket::bind(
+        &std::path::Path::new(bot_path).join(".proxy.sock"),
+        uid,
+        uid,
+    )
+    .map_err(|e| {
+        ProcessError::StartError(format!("Could not create the proxy relay socket: {e}"))
+    })?;
+    let sandbox = crate::sandbox::Sandbox::new(
+        &data_dir,
+        proxy_port,
+        relay_socket.path(),
+        uid,
+        uid,
+        settings.bot_seccomp,
+    )
+    .map_err(|e| ProcessError::StartError(format!("Could not set up bot sandbox: {e}")))?;
+    sandbox.apply_to_command(command);
+    Ok(relay_socket)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sandbox_command(
+    _command: &mut async_process::Command,
+    _settings: &ACConfig,
+    _bot_path: &str,
+    _proxy_port: &str,
+    _player_num: PlayerNum,
+) -> Result<RelaySocket, ProcessError> {
+    Err(ProcessError::StartError(
+        "BOT_SANDBOX is only supported on Linux".to_string(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn sandbox_proxy_host(_relay_socket: &RelaySocket) -> &'static str {
+    crate::sandbox::PROXY_HOST
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sandbox_proxy_host(relay_socket: &RelaySocket) -> &'static str {
+    match *relay_socket {}
+}
+
+/// Forwards the connections of a sandboxed bot to the proxy at `proxy_addr` while it runs
+#[cfg(target_os = "linux")]
+fn serve_proxy_relay(
+    relay_socket: RelaySocket,
+    proxy_addr: String,
+    state: &AppState,
+    process_key: Port,
+) {
+    tokio::spawn(relay_socket.serve(proxy_addr, state.process_map.clone(), process_key));
+}
+
+#[cfg(not(target_os = "linux"))]
+fn serve_proxy_relay(
+    relay_socket: RelaySocket,
+    _proxy_addr: String,
+    _state: &AppState,
+    _process_key: Port,
+) {
+    match relay_socket {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_state(settings: ACConfig) -> AppState {
+        AppState {
+            process_map: Default::default(),
+            settings,
+            shutdown_sender: tokio::sync::mpsc::channel(1).0,
+            extra_info: Default::default(),
+            process_limits: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_routes_find_encodable_bot_name() {
+        let bots = tempfile::tempdir().unwrap();
+        // Sent percent-encoded in the URL, which the path extractor decodes
+        let bot_name = "my bot+1%";
+        let bot_path = bots.path().join(bot_name);
+        std::fs::create_dir_all(bot_path.join("data")).unwrap();
+        std::fs::write(bot_path.join("data").join("model"), [0u8; 100]).unwrap();
+        let state = app_state(ACConfig {
+            bot_data_quota_mb: Some(1),
+            ..Default::default()
+        });
+        record_bot_directory(&state, bot_name, bot_path.to_str().unwrap(), None);
+        state
+            .extra_info
+            .write()
+            .get_mut(bot_name)
+            .unwrap()
+            .insert("DataPeakBytes".to_string(), "300".to_string());
+
+        let usage = bot_data_usage(Path(bot_name.to_string()), State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(usage.0.used_bytes, 100);
+        assert_eq!(usage.0.peak_bytes, 300);
+
+        let download = download_bot_data(Path(bot_name.to_string()), State(state.clone())).await;
+        assert!(download.is_ok());
+        assert!(bot_directory(&state, "my%20bot%2B1%25").is_err());
+    }
+}
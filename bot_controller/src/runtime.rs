@@ -0,0 +1,179 @@
+use common::api::errors::process_error::ProcessError;
+use common::configuration::ac_config::ACConfig;
+use common::configuration::bot_runtime::{BotRuntime, RuntimeVariables};
+use common::models::bot_controller::BotType;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Runtime for a bot of `bot_type`, with its placeholders filled in for the bot in `bot_path`
+pub(crate) fn resolve_runtime(
+    settings: &ACConfig,
+    bot_type: &BotType,
+    bot_name: &str,
+    bot_path: &str,
+) -> Result<BotRuntime, ProcessError> {
+    let runtime = BotRuntime::resolve(settings, bot_type).ok_or_else(|| {
+        ProcessError::StartError(format!(
+            "No runtime configured for bot type {bot_type:?}. Add it to BOT_RUNTIMES"
+        ))
+    })?;
+    Ok(runtime.expand(RuntimeVariables {
+        bot_name,
+        bot_dir: bot_path,
+        python: &settings.python,
+    }))
+}
+
+pub(crate) fn working_dir(runtime: &BotRuntime, bot_path: &str) -> PathBuf {
+    match &runtime.working_dir {
+        Some(working_dir) => Path::new(bot_path).join(working_dir),
+        None => PathBuf::from(bot_path),
+    }
+}
+
+/// Checks the required file of the runtime, and makes it executable if the runtime asks for it
+pub(crate) fn prepare_bot_files(runtime: &BotRuntime, bot_path: &str) -> Result<(), ProcessError> {
+    let required_file = match &runtime.required_file {
+        Some(required_file) => Path::new(bot_path).join(required_file),
+        None => return Ok(()),
+    };
+    if !required_file.is_file() {
+        return Err(ProcessError::StartError(format!(
+            "Bot file does not exist: {required_file:?}"
+        )));
+    }
+    #[cfg(unix)]
+    if runtime.make_executable {
+        use std::os::unix::fs::PermissionsExt;
+        debug!("Setting bot file permissions");
+        let result = std::fs::metadata(&required_file).and_then(|metadata| {
+            let mut perms = metadata.permissions();
+            perms.set_mode(perms.mode() | 0o755);
+            std::fs::set_permissions(&required_file, perms)
+        });
+        if let Err(e) = result {
+            return Err(ProcessError::StartError(format!(
+                "Could not make {required_file:?} executable: {e}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Command that starts the bot, without the ladder arguments
+pub(crate) fn bot_command(runtime: &BotRuntime, bot_path: &str) -> async_process::Command {
+    let mut command = async_process::Command::new(&runtime.command);
+    command
+        .args(&runtime.args)
+        .envs(runtime.env_vars())
+        .current_dir(working_dir(runtime, bot_path));
+    command
+}
+
+/// Runs the setup step of the runtime, if it has one. Its output goes to the bot logs.
+pub(crate) async fn run_setup(
+    runtime: &BotRuntime,
+    bot_path: &str,
+    stdout_file: &File,
+    stderr_file: &File,
+) -> Result<(), ProcessError> {
+    let (program, args) = match runtime.setup.split_first() {
+        Some(setup) => setup,
+        None => return Ok(()),
+    };
+    let (stdout, stderr) = clone_log_files(stdout_file, stderr_file)?;
+    debug!("Running bot setup {:?}", runtime.setup);
+    let budget = runtime.setup_timeout();
+    let mut command = async_process::Command::new(program);
+    command
+        .args(args)
+        .envs(runtime.env_vars())
+        .current_dir(working_dir(runtime, bot_path))
+        .stdout(stdout)
+        .stderr(stderr)
+        // A setup that runs out of time is killed when its future is dropped
+        .kill_on_drop(true);
+    let status = tokio::time::timeout(budget, command.status())
+        .await
+        .map_err(|_| {
+            ProcessError::StartError(format!(
+                "Bot setup {:?} took longer than {}s",
+                runtime.setup,
+                budget.as_secs()
+            ))
+        })?
+        .map_err(|e| ProcessError::StartError(format!("Could not run bot setup: {e}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ProcessError::StartError(format!(
+            "Bot setup {:?} failed with status {status}",
+            runtime.setup
+        )))
+    }
+}
+
+/// Copies of the bot log files, so helper processes can log to them as well
+pub(crate) fn clone_log_files(
+    stdout_file: &File,
+    stderr_file: &File,
+) -> Result<(File, File), ProcessError> {
+    match (stdout_file.try_clone(), stderr_file.try_clone()) {
+        (Ok(stdout), Ok(stderr)) => Ok((stdout, stderr)),
+        (Err(e), _) | (_, Err(e)) => Err(ProcessError::StartError(format!(
+            "Could not pass log files to setup: {e}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_bot_files() {
+        let bot = tempfile::tempdir().unwrap();
+        let bot_path = bot.path().to_str().unwrap();
+        let runtime = BotRuntime {
+            command: "./my_bot".to_string(),
+            required_file: Some("my_bot".to_string()),
+            make_executable: true,
+            ..Default::default()
+        };
+        assert!(prepare_bot_files(&runtime, bot_path).is_err());
+
+        std::fs::write(bot.path().join("my_bot"), "").unwrap();
+        prepare_bot_files(&runtime, bot_path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(bot.path().join("my_bot")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o111, 0o111);
+        }
+    }
+
+    #[test]
+    fn test_unknown_bot_type() {
+        let settings = ACConfig::default();
+        let bot_type = BotType::Custom("cobol".to_string());
+        assert!(resolve_runtime(&settings, &bot_type, "bot", "/bots/bot").is_err());
+        let runtime = resolve_runtime(&settings, &BotType::NodeJs, "bot", "/bots/bot").unwrap();
+        assert_eq!(runtime.args, vec!["bot.js"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_setup_timeout() {
+        let bot = tempfile::tempdir().unwrap();
+        let log = bot.path().join("setup.log");
+        let log_file = File::create(&log).unwrap();
+        let runtime = BotRuntime {
+            setup: vec!["sleep".to_string(), "30".to_string()],
+            setup_timeout_secs: Some(0),
+            ..Default::default()
+        };
+        let result = run_setup(&runtime, bot.path().to_str().unwrap(), &log_file, &log_file).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,605 @@
+//! Per-bot sandbox, applied between fork and exec.
+//!
+//! The bot runs as its own user in its own user, mount and network namespace. Every mount is
+//! read-only except the bot's `data` directory and a private `/tmp`. The network namespace only
+//! has a loopback interface, on which a relay process forwards the proxy port to a unix socket
+//! of the controller. Sockets other than TCP/IP and netlink can't be created, and an optional
+//! seccomp filter kills the bot on system calls that are only useful to escape.
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Address the bot reaches the proxy at inside its network namespace
+pub const PROXY_HOST: &str = "127.0.0.1";
+
+pub struct Sandbox {
+    data_dir: CString,
+    proxy_port: u16,
+    relay_socket: libc::sockaddr_un,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+    seccomp_filter: Vec<libc::sock_filter>,
+}
+
+impl Sandbox {
+    /// `relay_socket` is the unix socket connections to `proxy_port` are forwarded to. The bot
+    /// runs as `uid` and `gid`, which only the root user can switch to.
+    pub fn new(
+        data_dir: &Path,
+        proxy_port: u16,
+        relay_socket: &Path,
+        uid: u32,
+        gid: u32,
+        seccomp: bool,
+    ) -> io::Result<Self> {
+        let data_dir = CString::new(data_dir.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if uid == 0 || gid == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Sandboxed bots can't run as root",
+            ));
+        }
+        let seccomp_filter = seccomp::filter(seccomp).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Seccomp filters are not supported on this architecture",
+            )
+        })?;
+        Ok(Self {
+            data_dir,
+            proxy_port,
+            relay_socket: relay::socket_address(relay_socket)?,
+            uid,
+            gid,
+            uid_map: format!("{uid} {uid} 1").into_bytes(),
+            gid_map: format!("{gid} {gid} 1").into_bytes(),
+            seccomp_filter,
+        })
+    }
+
+    /// Makes the command set up the sandbox before running the bot. Spawning fails if the
+    /// sandbox can't be set up.
+    pub fn apply_to_command(self, command: &mut async_process::Command) {
+        use async_process::unix::CommandExt;
+        // Safety: the closure does not allocate and only calls async-signal-safe functions
+        unsafe {
+            command.pre_exec(move || self.apply_in_child());
+        }
+    }
+
+    /// Sets up the sandbox in a forked child that exits right away. Fails if the environment
+    /// does not allow the namespaces or mounts, like a container with the default seccomp
+    /// profile, or if the controller can't switch to the bot's user.
+    pub fn probe(&self) -> io::Result<()> {
+        unsafe {
+            let pid = libc::fork();
+            check(pid)?;
+            if pid == 0 {
+                // Only async-signal-safe calls from here on, like between fork and exec
+                let code = match self.apply_in_child() {
+                    Ok(()) => 0,
+                    Err(e) => e.raw_os_error().unwrap_or(libc::EINVAL),
+                };
+                libc::_exit(code);
+            }
+            let mut status = 0;
+            check(libc::waitpid(pid, &mut status, 0))?;
+            if !libc::WIFEXITED(status) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("The sandbox test process ended with status {status}"),
+                ));
+            }
+            match libc::WEXITSTATUS(status) {
+                0 => Ok(()),
+                code => Err(io::Error::from_raw_os_error(code)),
+            }
+        }
+    }
+
+    fn apply_in_child(&self) -> io::Result<()> {
+        unsafe {
+            // A user of its own, so the bot can't signal the controller or the other bot and
+            // RLIMIT_NPROC only counts its own processes
+            check(libc::setgroups(0, std::ptr::null()))?;
+            check(libc::setresgid(self.gid, self.gid, self.gid))?;
+            check(libc::setresuid(self.uid, self.uid, self.uid))?;
+            // Switching users makes /proc/self owned by root, which would keep us from writing
+            // the id maps below
+            check(libc::prctl(libc::PR_SET_DUMPABLE, 1, 0, 0, 0))?;
+
+            check(libc::unshare(
+                libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET,
+            ))?;
+            write_file(b"/proc/self/setgroups\0", b"deny")?;
+            write_file(b"/proc/self/uid_map\0", &self.uid_map)?;
+            write_file(b"/proc/self/gid_map\0", &self.gid_map)?;
+
+            let root = b"/\0".as_ptr().cast();
+            // Keep our mount changes from propagating back to the host
+            check(libc::mount(
+                std::ptr::null(),
+                root,
+                std::ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            ))?;
+            mount::set_readonly(root, true, true)?;
+            let data_dir = self.data_dir.as_ptr();
+            check(libc::mount(
+                data_dir,
+                data_dir,
+                std::ptr::null(),
+                libc::MS_BIND,
+                std::ptr::null(),
+            ))?;
+            mount::set_readonly(data_dir, false, false)?;
+            check(libc::mount(
+                b"tmpfs\0".as_ptr().cast(),
+                b"/tmp\0".as_ptr().cast(),
+                b"tmpfs\0".as_ptr().cast(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                std::ptr::null(),
+            ))?;
+
+            relay::loopback_up()?;
+            let listener = relay::listen(self.proxy_port)?;
+            relay::spawn(listener, &self.relay_socket)?;
+            libc::close(listener);
+
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+            seccomp::install(&self.seccomp_filter)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hands `dir` and everything in it to the user a sandboxed bot runs as. Symlinks themselves
+/// are changed, not their targets.
+pub fn give_to(dir: &Path, uid: u32, gid: u32) -> io::Result<()> {
+    std::os::unix::fs::lchown(dir, Some(uid), Some(gid))?;
+    if std::fs::symlink_metadata(dir)?.is_dir() {
+        for entry in std::fs::read_dir(dir)? {
+            give_to(&entry?.path(), uid, gid)?;
+        }
+    }
+    Ok(())
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes `contents` to the nul-terminated `path` without allocating
+unsafe fn write_file(path: &[u8], contents: &[u8]) -> io::Result<()> {
+    let fd = libc::open(path.as_ptr().cast(), libc::O_WRONLY | libc::O_CLOEXEC);
+    check(fd)?;
+    let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+    libc::close(fd);
+    if written < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// The in-namespace side of the path to the proxy. All of it runs between fork and exec, so it
+/// must not allocate.
+mod relay {
+    use super::check;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    const BUFFER_SIZE: usize = 16 * 1024;
+
+    pub fn socket_address(path: &Path) -> io::Result<libc::sockaddr_un> {
+        // Safety: all zeroes is a valid, empty address
+        let mut address: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+        address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let path = path.as_os_str().as_bytes();
+        // The path has to be nul-terminated
+        if path.len() >= address.sun_path.len() || path.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid relay socket path {path:?}"),
+            ));
+        }
+        for (dest, byte) in address.sun_path.iter_mut().zip(path) {
+            *dest = *byte as libc::c_char;
+        }
+        Ok(address)
+    }
+
+    /// A new network namespace starts with its loopback interface down
+    pub unsafe fn loopback_up() -> io::Result<()> {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+        check(fd)?;
+        let mut request: libc::ifreq = std::mem::zeroed();
+        for (dest, byte) in request.ifr_name.iter_mut().zip(b"lo") {
+            *dest = *byte as libc::c_char;
+        }
+        let result = check(libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut request)).and_then(|_| {
+            request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+            check(libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &request))
+        });
+        libc::close(fd);
+        result
+    }
+
+    /// Listens on `port` of the loopback interface, where the bot expects the proxy
+    pub unsafe fn listen(port: u16) -> io::Result<libc::c_int> {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0);
+        check(fd)?;
+        let mut address: libc::sockaddr_in = std::mem::zeroed();
+        address.sin_family = libc::AF_INET as libc::sa_family_t;
+        address.sin_port = port.to_be();
+        address.sin_addr.s_addr = u32::from_be_bytes([127, 0, 0, 1]).to_be();
+        let result = check(libc::bind(
+            fd,
+            &address as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        ))
+        .and_then(|_| check(libc::listen(fd, 16)));
+        match result {
+            Ok(()) => Ok(fd),
+            Err(e) => {
+                libc::close(fd);
+                Err(e)
+            }
+        }
+    }
+
+    /// Forks the relay, which forwards every connection to `listener` to the unix socket at
+    /// `target`. It dies with the bot.
+    pub unsafe fn spawn(listener: libc::c_int, target: &libc::sockaddr_un) -> io::Result<()> {
+        let parent = libc::getpid();
+        let pid = libc::fork();
+        check(pid)?;
+        if pid > 0 {
+            return Ok(());
+        }
+        // Once the bot runs it has no capabilities left, so without being dumpable the relay
+        // can't be traced and made to connect anywhere else
+        if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL, 0, 0, 0) != 0
+            || libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) != 0
+            || libc::getppid() != parent
+        {
+            libc::_exit(1);
+        }
+        // Don't hold the bot's output open, or its logs would never see the end of it
+        for fd in 0..3 {
+            libc::close(fd);
+        }
+        // Connections are served by children of their own, which are reaped automatically
+        libc::signal(libc::SIGCHLD, libc::SIG_IGN);
+        loop {
+            let connection = libc::accept4(
+                listener,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                libc::SOCK_CLOEXEC,
+            );
+            if connection < 0 {
+                if io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                libc::_exit(1);
+            }
+            if libc::fork() == 0 {
+                libc::close(listener);
+                forward(connection, target);
+                libc::_exit(0);
+            }
+            libc::close(connection);
+        }
+    }
+
+    unsafe fn forward(connection: libc::c_int, target: &libc::sockaddr_un) {
+        let upstream = libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0);
+        if upstream < 0
+            || libc::connect(
+                upstream,
+                target as *const libc::sockaddr_un as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+            ) != 0
+        {
+            return;
+        }
+        let mut buffer = [0u8; BUFFER_SIZE];
+        let mut fds = [
+            libc::pollfd {
+                fd: connection,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: upstream,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let mut open = fds.len();
+        while open > 0 {
+            if libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) < 0 {
+                if io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                return;
+            }
+            for (index, (from, to)) in [(connection, upstream), (upstream, connection)]
+                .into_iter()
+                .enumerate()
+            {
+                // Closed directions are ignored by poll
+                if fds[index].fd < 0 || fds[index].revents == 0 {
+                    continue;
+                }
+                let read = libc::read(from, buffer.as_mut_ptr().cast(), buffer.len());
+                if read <= 0 {
+                    libc::shutdown(to, libc::SHUT_WR);
+                    fds[index].fd = -1;
+                    open -= 1;
+                } else if !send_all(to, &buffer[..read as usize]) {
+                    return;
+                }
+            }
+        }
+    }
+
+    unsafe fn send_all(fd: libc::c_int, mut data: &[u8]) -> bool {
+        while !data.is_empty() {
+            let sent = libc::send(fd, data.as_ptr().cast(), data.len(), libc::MSG_NOSIGNAL);
+            if sent < 0 {
+                if io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                return false;
+            }
+            data = &data[sent as usize..];
+        }
+        true
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_socket_address() {
+            let address = socket_address(Path::new("/bots/bot/.proxy.sock")).unwrap();
+            assert_eq!(address.sun_family, libc::AF_UNIX as libc::sa_family_t);
+            let path: Vec<u8> = address
+                .sun_path
+                .iter()
+                .take_while(|c| **c != 0)
+                .map(|c| *c as u8)
+                .collect();
+            assert_eq!(path, b"/bots/bot/.proxy.sock");
+
+            let too_long = "a".repeat(address.sun_path.len());
+            assert!(socket_address(Path::new(&too_long)).is_err());
+        }
+    }
+}
+
+mod mount {
+    use super::check;
+    use std::io;
+
+    const SYS_MOUNT_SETATTR: libc::c_long = 442;
+    const AT_RECURSIVE: libc::c_uint = 0x8000;
+    const MOUNT_ATTR_RDONLY: u64 = 0x1;
+
+    #[repr(C)]
+    struct MountAttr {
+        attr_set: u64,
+        attr_clr: u64,
+        propagation: u64,
+        userns_fd: u64,
+    }
+
+    /// Changes only the read-only flag, so flags locked by the parent namespace are kept
+    pub unsafe fn set_readonly(
+        path: *const libc::c_char,
+        readonly: bool,
+        recursive: bool,
+    ) -> io::Result<()> {
+        let attr = MountAttr {
+            attr_set: if readonly { MOUNT_ATTR_RDONLY } else { 0 },
+            attr_clr: if readonly { 0 } else { MOUNT_ATTR_RDONLY },
+            propagation: 0,
+            userns_fd: 0,
+        };
+        let flags = if recursive { AT_RECURSIVE } else { 0 };
+        check(libc::syscall(
+            SYS_MOUNT_SETATTR,
+            libc::AT_FDCWD,
+            path,
+            flags,
+            &attr as *const MountAttr,
+            std::mem::size_of::<MountAttr>(),
+        ) as libc::c_int)
+    }
+}
+
+mod seccomp {
+    use super::check;
+    use std::io;
+
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JEQ_K: u16 = 0x15;
+    const BPF_JGE_K: u16 = 0x35;
+    const BPF_RET_K: u16 = 0x06;
+    const SECCOMP_MODE_FILTER: libc::c_ulong = 2;
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+    /// Offsets into `struct seccomp_data`
+    const NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+    /// Lower half of the first argument, which is enough for the socket domain
+    const ARG0_OFFSET: u32 = 16;
+    /// Syscalls of the x32 ABI have this bit set, and would bypass the filter
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_003E);
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_00B7);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: Option<u32> = None;
+
+    /// Socket domains a bot may create. Unix sockets would reach the services of the host, while
+    /// TCP/IP and netlink are confined to the network namespace of the bot.
+    const ALLOWED_SOCKET_DOMAINS: &[libc::c_int] =
+        &[libc::AF_INET, libc::AF_INET6, libc::AF_NETLINK];
+
+    /// System calls a bot has no business making
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_reboot,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_acct,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_userfaultfd,
+    ];
+
+    fn statement(code: u16, k: u32) -> libc::sock_filter {
+        jump(code, k, 0, 0)
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    /// Builds a filter that fails the creation of sockets outside of
+    /// [`ALLOWED_SOCKET_DOMAINS`], and with `deny_syscalls` kills the process on any of
+    /// [`DENIED_SYSCALLS`]
+    pub fn filter(deny_syscalls: bool) -> Option<Vec<libc::sock_filter>> {
+        let audit_arch = AUDIT_ARCH?;
+        let mut filter = vec![
+            statement(BPF_LD_W_ABS, ARCH_OFFSET),
+            jump(BPF_JEQ_K, audit_arch, 1, 0),
+            statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            statement(BPF_LD_W_ABS, NR_OFFSET),
+            jump(BPF_JGE_K, X32_SYSCALL_BIT, 0, 1),
+            statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+        ];
+        // Other system calls skip the domain checks and their two returns, with the system call
+        // number still loaded
+        let domains = ALLOWED_SOCKET_DOMAINS.len() as u8;
+        filter.push(jump(BPF_JEQ_K, libc::SYS_socket as u32, 0, domains + 3));
+        filter.push(statement(BPF_LD_W_ABS, ARG0_OFFSET));
+        for (index, domain) in ALLOWED_SOCKET_DOMAINS.iter().enumerate() {
+            // Jump to the allow below the errno return
+            filter.push(jump(BPF_JEQ_K, *domain as u32, domains - index as u8, 0));
+        }
+        filter.push(statement(
+            BPF_RET_K,
+            SECCOMP_RET_ERRNO | libc::EACCES as u32,
+        ));
+        filter.push(statement(BPF_RET_K, SECCOMP_RET_ALLOW));
+        if deny_syscalls {
+            for syscall in DENIED_SYSCALLS {
+                filter.push(jump(BPF_JEQ_K, *syscall as u32, 0, 1));
+                filter.push(statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS));
+            }
+        }
+        filter.push(statement(BPF_RET_K, SECCOMP_RET_ALLOW));
+        Some(filter)
+    }
+
+    pub unsafe fn install(filter: &[libc::sock_filter]) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        check(libc::prctl(
+            libc::PR_SET_SECCOMP,
+            SECCOMP_MODE_FILTER,
+            &program as *const libc::sock_fprog,
+        ))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Follows a jump of the instruction at `index`
+        fn target(filter: &[libc::sock_filter], index: usize, taken: bool) -> usize {
+            let offset = if taken {
+                filter[index].jt
+            } else {
+                filter[index].jf
+            };
+            index + 1 + offset as usize
+        }
+
+        #[test]
+        fn test_socket_domains() {
+            let filter = match filter(false) {
+                Some(filter) => filter,
+                None => return,
+            };
+            let socket_check = 6;
+            let domains = ALLOWED_SOCKET_DOMAINS.len();
+            let errno = socket_check + 2 + domains;
+            assert_eq!(filter[socket_check].k, libc::SYS_socket as u32);
+            assert_eq!(filter[errno].k, SECCOMP_RET_ERRNO | libc::EACCES as u32);
+            // Other system calls go on to the final allow
+            assert_eq!(target(&filter, socket_check, false), errno + 2);
+            assert_eq!(filter[errno + 2].k, SECCOMP_RET_ALLOW);
+            // Allowed domains jump to the allow right after the errno return
+            for index in socket_check + 2..errno {
+                assert_eq!(target(&filter, index, true), errno + 1);
+                assert_eq!(filter[errno + 1].k, SECCOMP_RET_ALLOW);
+            }
+            assert_eq!(filter.len(), errno + 3);
+        }
+
+        #[test]
+        fn test_denied_syscalls_layout() {
+            let filter = match filter(true) {
+                Some(filter) => filter,
+                None => return,
+            };
+            let denied = 6 + 2 + ALLOWED_SOCKET_DOMAINS.len() + 2;
+            assert_eq!(filter.len(), denied + 2 * DENIED_SYSCALLS.len() + 1);
+            // Every deny check either falls through to a kill or skips over it
+            for pair in filter[denied..filter.len() - 1].chunks(2) {
+                assert_eq!(pair[0].code, BPF_JEQ_K);
+                assert_eq!((pair[0].jt, pair[0].jf), (0, 1));
+                assert_eq!(pair[1].k, SECCOMP_RET_KILL_PROCESS);
+            }
+            assert_eq!(filter.last().unwrap().k, SECCOMP_RET_ALLOW);
+        }
+    }
+}
@@ -2,30 +2,110 @@ use axum::body::Bytes;
 use axum::http::StatusCode;
 use reqwest::Client;
 use std::io;
-use tracing::error;
+use std::path::Path;
+use tracing::{error, trace};
 
 use common::api::errors::app_error::AppError;
 use common::api::errors::download_error::DownloadError;
 use common::api::errors::process_error::ProcessError;
+use common::api::process::ProcessMap;
+use common::models::bot_controller::{BotLog, DataSnapshot};
+use common::procs::log_cap::{enforce_log_cap, LogCap};
+use common::utilities::directory::{sync_directory, Symlinks};
+use common::utilities::portpicker::Port;
+use common::utilities::zip_utils::zip_directory;
 use common::{configuration::ac_config::ACConfig, PlayerNum};
 
+/// Whether the bot under `process_key` was started and has not exited yet. Entries stay in the
+/// process map until the bot is terminated, so the child itself is asked.
+pub(crate) fn is_running(process_map: &ProcessMap, process_key: Port) -> bool {
+    process_map
+        .write()
+        .get_mut(&process_key)
+        .map_or(false, |child| matches!(child.try_status(), Ok(None)))
+}
+
+/// Whether `bot_name` can be joined onto a directory without leaving it
+pub(crate) fn is_valid_bot_name(bot_name: &str) -> bool {
+    !bot_name.is_empty() && !bot_name.contains(['/', '\\']) && !bot_name.contains("..")
+}
+
+/// Replaces the contents of the internal directory of `player_num` with a copy of the bot, and
+/// returns the internal directory. Leftovers from the previous match are wiped.
 pub(crate) fn move_bot_to_internal_dir(
     settings: &ACConfig,
     bot_path: &str,
     player_num: PlayerNum,
 ) -> io::Result<String> {
-    match player_num {
-        PlayerNum::One => {
-            std::fs::copy(bot_path, &settings.bot1_directory)?;
-            Ok(settings.bot1_directory.clone())
+    let internal_dir = match player_num {
+        PlayerNum::One => &settings.bot1_directory,
+        PlayerNum::Two => &settings.bot2_directory,
+    };
+    sync_directory(Path::new(bot_path), Path::new(internal_dir), Symlinks::Keep)?;
+    Ok(internal_dir.clone())
+}
+
+/// Takes or restores the snapshot of the data of the bot playing as `player_num`. Snapshots are
+/// kept next to the bots, out of reach of a sandboxed bot.
+pub(crate) fn apply_data_snapshot(
+    settings: &ACConfig,
+    snapshot: DataSnapshot,
+    data_dir: &Path,
+    player_num: PlayerNum,
+) -> io::Result<()> {
+    let snapshot_dir = Path::new(&settings.bots_directory)
+        .join(".data_snapshots")
+        .join(format!("{player_num:?}"));
+    match snapshot {
+        DataSnapshot::Skip => Ok(()),
+        DataSnapshot::Take => sync_directory(data_dir, &snapshot_dir, Symlinks::Keep),
+        DataSnapshot::Restore => {
+            if !snapshot_dir.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No data snapshot in {}", snapshot_dir.display()),
+                ));
+            }
+            sync_directory(&snapshot_dir, data_dir, Symlinks::Keep)
         }
-        PlayerNum::Two => {
-            std::fs::copy(bot_path, &settings.bot2_directory)?;
-            Ok(settings.bot2_directory.clone())
+    }
+}
+
+/// Copies `data_dir`, the data a bot wrote in its internal directory or the part of it that gets
+/// uploaded, back to the original bot directory. Symlinks are left out, so a bot can't get files
+/// from outside its directory uploaded. Fails if the bot replaced the `data` directory itself with
+/// a symlink.
+pub(crate) fn copy_bot_data_back(data_dir: &Path, bot_path: &Path) -> io::Result<()> {
+    sync_directory(data_dir, &bot_path.join("data"), Symlinks::Skip)
+}
+
+/// Shrinks each log of a bot in `log_dir` to `cap`
+pub(crate) fn enforce_bot_log_caps(log_dir: &Path, cap: &LogCap) {
+    for log in BotLog::ALL {
+        let path = log_dir.join(log.file_name());
+        if !path.exists() {
+            continue;
+        }
+        match enforce_log_cap(&path, cap) {
+            Ok(0) => {}
+            Ok(omitted) => trace!("Omitted {} bytes from {:?}", omitted, path),
+            Err(e) => error!("Could not cap bot log {:?}: {}", path, e),
         }
     }
 }
 
+/// Zips the logs of a bot in `log_dir` into `archive_file`
+pub(crate) fn package_bot_logs(log_dir: &Path, archive_file: &Path) -> Result<(), DownloadError> {
+    let staging = tempfile::tempdir()?;
+    for log in BotLog::ALL {
+        let path = log_dir.join(log.file_name());
+        if path.exists() {
+            std::fs::copy(&path, staging.path().join(log.file_name()))?;
+        }
+    }
+    Ok(zip_directory(archive_file, staging.path())?)
+}
+
 pub async fn download_and_extract(
     url: &str,
     path: &std::path::Path,
@@ -165,3 +245,103 @@ async fn get_md5hash(
         Ok(Some(body))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_bot_name() {
+        assert!(is_valid_bot_name("basic_bot"));
+        assert!(is_valid_bot_name("Bot.v2"));
+        assert!(!is_valid_bot_name(""));
+        assert!(!is_valid_bot_name("../other_bot"));
+        assert!(!is_valid_bot_name(".."));
+        assert!(!is_valid_bot_name("bots/basic_bot"));
+        assert!(!is_valid_bot_name("bots\\basic_bot"));
+    }
+
+    #[test]
+    fn test_restore_data_snapshot() {
+        let bots = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let settings = ACConfig {
+            bots_directory: bots.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let restore = || {
+            apply_data_snapshot(
+                &settings,
+                DataSnapshot::Restore,
+                data.path(),
+                PlayerNum::One,
+            )
+        };
+        assert!(restore().is_err());
+
+        std::fs::write(data.path().join("model.bin"), "first").unwrap();
+        apply_data_snapshot(&settings, DataSnapshot::Take, data.path(), PlayerNum::One).unwrap();
+        // The failed attempt changes and adds data
+        std::fs::write(data.path().join("model.bin"), "changed").unwrap();
+        std::fs::write(data.path().join("games.txt"), "1").unwrap();
+
+        restore().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(data.path().join("model.bin")).unwrap(),
+            "first"
+        );
+        assert!(!data.path().join("games.txt").exists());
+    }
+
+    #[test]
+    fn test_move_bot_to_internal_dir() {
+        let bot = tempfile::tempdir().unwrap();
+        let internal = tempfile::tempdir().unwrap();
+        std::fs::write(bot.path().join("run.py"), "print()").unwrap();
+        std::fs::write(internal.path().join("previous_bot.py"), "").unwrap();
+        let settings = ACConfig {
+            bot2_directory: internal.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+
+        let moved =
+            move_bot_to_internal_dir(&settings, bot.path().to_str().unwrap(), PlayerNum::Two)
+                .unwrap();
+
+        assert_eq!(moved, settings.bot2_directory);
+        assert!(internal.path().join("run.py").is_file());
+        assert!(!internal.path().join("previous_bot.py").exists());
+    }
+
+    #[test]
+    fn test_copy_bot_data_back() {
+        let bot = tempfile::tempdir().unwrap();
+        let internal = tempfile::tempdir().unwrap();
+        std::fs::create_dir(bot.path().join("data")).unwrap();
+        std::fs::write(bot.path().join("data").join("old.txt"), "old").unwrap();
+        std::fs::create_dir(internal.path().join("data")).unwrap();
+        std::fs::write(internal.path().join("data").join("new.txt"), "new").unwrap();
+
+        copy_bot_data_back(&internal.path().join("data"), bot.path()).unwrap();
+
+        let data = bot.path().join("data");
+        assert_eq!(
+            std::fs::read_to_string(data.join("new.txt")).unwrap(),
+            "new"
+        );
+        assert!(!data.join("old.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_bot_data_back_refuses_symlinked_data() {
+        let bot = tempfile::tempdir().unwrap();
+        let internal = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(outside.path(), internal.path().join("data")).unwrap();
+
+        assert!(copy_bot_data_back(&internal.path().join("data"), bot.path()).is_err());
+        assert!(!bot.path().join("data").join("secret.txt").exists());
+    }
+}
@@ -0,0 +1,342 @@
+//! Virtualenvs for Python bots with dependencies. The dependencies are installed from an offline
+//! wheelhouse, and bots with the same requirements share a virtualenv.
+
+use crate::runtime::clone_log_files;
+use common::api::errors::process_error::ProcessError;
+use common::configuration::ac_config::ACConfig;
+use common::models::bot_controller::BotType;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Written once all dependencies are installed, so half-built virtualenvs are never reused
+const COMPLETE_MARKER: &str = ".complete";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Requirements {
+    /// A `requirements.txt`
+    File(PathBuf),
+    /// The `project.dependencies` of a `pyproject.toml`. The project itself is not installed into
+    /// the shared virtualenv, the bot runs from its own directory.
+    Dependencies(Vec<String>),
+}
+
+impl Requirements {
+    /// Finds the requirements of the bot in `bot_path` and reads them for hashing.
+    /// `requirements.txt` takes precedence.
+    fn find(bot_path: &Path) -> Result<Option<(Self, Vec<u8>)>, ProcessError> {
+        let requirements = bot_path.join("requirements.txt");
+        if let Ok(contents) = std::fs::read(&requirements) {
+            return Ok(Some((Self::File(requirements), contents)));
+        }
+        let contents = match std::fs::read_to_string(bot_path.join("pyproject.toml")) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+        let dependencies = project_dependencies(&contents).map_err(|e| {
+            ProcessError::StartError(format!("Could not read pyproject.toml dependencies: {e}"))
+        })?;
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+        let contents = dependencies.join("\n").into_bytes();
+        Ok(Some((Self::Dependencies(dependencies), contents)))
+    }
+
+    fn pip_args(&self) -> Vec<&OsStr> {
+        match self {
+            Self::File(path) => vec![OsStr::new("-r"), path.as_os_str()],
+            Self::Dependencies(dependencies) => dependencies.iter().map(OsStr::new).collect(),
+        }
+    }
+}
+
+/// Reads the `[project] dependencies` array of a `pyproject.toml`
+fn project_dependencies(pyproject: &str) -> Result<Vec<String>, String> {
+    let table = pyproject
+        .parse::<toml::Table>()
+        .map_err(|e| e.to_string())?;
+    let dependencies = match table.get("project").and_then(|p| p.get("dependencies")) {
+        Some(dependencies) => dependencies,
+        None => return Ok(Vec::new()),
+    };
+    dependencies
+        .as_array()
+        .ok_or_else(|| "dependencies is not an array".to_string())?
+        .iter()
+        .map(|dependency| {
+            dependency
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("{dependency} is not a string"))
+        })
+        .collect()
+}
+
+/// Cache key of a virtualenv: the interpreter and the requirements it was built from
+fn venv_key(python: &str, requirements: &[u8]) -> String {
+    let mut context = md5::Context::new();
+    context.consume(python.as_bytes());
+    context.consume([0u8]);
+    context.consume(requirements);
+    format!("{:x}", context.compute())
+}
+
+pub(crate) fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+/// Builds or reuses the virtualenv for the Python bot in `bot_path`, and returns its directory.
+/// Returns `None` if no wheelhouse is configured, `BOT_RUNTIMES` has its own python runtime, or
+/// the bot has no requirements.
+pub(crate) async fn prepare_venv(
+    settings: &ACConfig,
+    bot_path: &str,
+    stdout_file: &File,
+    stderr_file: &File,
+) -> Result<Option<PathBuf>, ProcessError> {
+    let wheelhouse = match &settings.python_wheelhouse {
+        Some(wheelhouse) => Path::new(wheelhouse),
+        None => return Ok(None),
+    };
+    // A configured runtime decides on its own interpreter
+    if settings.bot_runtimes.contains_key(BotType::Python.name()) {
+        return Ok(None);
+    }
+    let (requirements, contents) = match Requirements::find(Path::new(bot_path))? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    // The bot runs in its own directory, so the interpreter path has to be absolute
+    let venv_directory = std::env::current_dir()
+        .map(|cwd| cwd.join(&settings.python_venv_directory))
+        .map_err(|e| ProcessError::StartError(format!("Could not resolve venv directory: {e}")))?;
+    let venv = venv_directory.join(venv_key(&settings.python, &contents));
+    if venv.join(COMPLETE_MARKER).is_file() {
+        debug!("Reusing virtualenv {:?}", venv);
+        return Ok(Some(venv));
+    }
+
+    info!("Building virtualenv {:?} for {:?}", venv, requirements);
+    let budget = Duration::from_secs(settings.python_setup_timeout_secs);
+    let build = build_venv(
+        &settings.python,
+        wheelhouse,
+        &requirements,
+        &venv,
+        stdout_file,
+        stderr_file,
+    );
+    match tokio::time::timeout(budget, build).await {
+        Ok(result) => result.map(|_| Some(venv)),
+        Err(_) => {
+            // The build was dropped half-way, so it could not clean up after itself
+            let _ = tokio::fs::remove_dir_all(staging_path(&venv)).await;
+            Err(ProcessError::StartError(format!(
+                "Setting up the Python environment took longer than {}s",
+                budget.as_secs()
+            )))
+        }
+    }
+}
+
+/// Where the virtualenv for `venv` is built by this controller before it is moved into place
+fn staging_path(venv: &Path) -> PathBuf {
+    let mut staging = venv.as_os_str().to_owned();
+    staging.push(format!(".{}", std::process::id()));
+    PathBuf::from(staging)
+}
+
+/// Builds the virtualenv next to `venv` and moves it into place once it is complete, so
+/// controllers building the same virtualenv at the same time don't get in each other's way.
+async fn build_venv(
+    python: &str,
+    wheelhouse: &Path,
+    requirements: &Requirements,
+    venv: &Path,
+    stdout_file: &File,
+    stderr_file: &File,
+) -> Result<(), ProcessError> {
+    let io_error = |e: std::io::Error| {
+        ProcessError::StartError(format!("Could not build virtualenv {venv:?}: {e}"))
+    };
+    let staging = staging_path(venv);
+    if let Some(parent) = venv.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+    }
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+
+    let result = async {
+        let venv_args = [OsStr::new("-m"), OsStr::new("venv"), staging.as_os_str()];
+        run_logged(OsStr::new(python), &venv_args, stdout_file, stderr_file).await?;
+        let mut pip_args: Vec<&OsStr> = vec![
+            OsStr::new("-m"),
+            OsStr::new("pip"),
+            OsStr::new("install"),
+            OsStr::new("--no-index"),
+            OsStr::new("--find-links"),
+            wheelhouse.as_os_str(),
+        ];
+        pip_args.extend(requirements.pip_args());
+        run_logged(
+            venv_python(&staging).as_os_str(),
+            &pip_args,
+            stdout_file,
+            stderr_file,
+        )
+        .await?;
+        tokio::fs::write(staging.join(COMPLETE_MARKER), b"")
+            .await
+            .map_err(io_error)?;
+        match tokio::fs::rename(&staging, venv).await {
+            Ok(()) => Ok(()),
+            // Another controller finished the same virtualenv first
+            Err(_) if venv.join(COMPLETE_MARKER).is_file() => {
+                let _ = tokio::fs::remove_dir_all(&staging).await;
+                Ok(())
+            }
+            Err(e) => Err(io_error(e)),
+        }
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+    }
+    result
+}
+
+/// Runs a setup command with its output going to the bot logs. It is killed if the returned future
+/// is dropped, e.g. when the setup runs out of time.
+async fn run_logged(
+    program: &OsStr,
+    args: &[&OsStr],
+    stdout_file: &File,
+    stderr_file: &File,
+) -> Result<(), ProcessError> {
+    let (stdout, stderr) = clone_log_files(stdout_file, stderr_file)?;
+    debug!("Running {:?} {:?}", program, args);
+    let status = async_process::Command::new(program)
+        .args(args)
+        .stdout(stdout)
+        .stderr(stderr)
+        .kill_on_drop(true)
+        .status()
+        .await
+        .map_err(|e| ProcessError::StartError(format!("Could not run {program:?}: {e}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ProcessError::StartError(format!(
+            "{program:?} {args:?} failed with status {status}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::configuration::bot_runtime::BotRuntime;
+
+    fn log_files(dir: &Path) -> (File, File) {
+        (
+            File::create(dir.join("stdout.log")).unwrap(),
+            File::create(dir.join("stderr.log")).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_venv_key() {
+        let key = venv_key("python3", b"burnysc2==6.5.0\n");
+        assert_eq!(key, venv_key("python3", b"burnysc2==6.5.0\n"));
+        assert_ne!(key, venv_key("python3.12", b"burnysc2==6.5.0\n"));
+        assert_ne!(key, venv_key("python3", b"burnysc2==6.4.0\n"));
+    }
+
+    #[test]
+    fn test_find_requirements() {
+        let bot = tempfile::tempdir().unwrap();
+        assert!(Requirements::find(bot.path()).unwrap().is_none());
+
+        // A project without dependencies runs on the global interpreter
+        let pyproject = bot.path().join("pyproject.toml");
+        std::fs::write(&pyproject, "[project]\nname = \"bot\"").unwrap();
+        assert!(Requirements::find(bot.path()).unwrap().is_none());
+
+        std::fs::write(
+            &pyproject,
+            "[project]\ndependencies = [\"numpy\", \"burnysc2\"]",
+        )
+        .unwrap();
+        let (requirements, contents) = Requirements::find(bot.path()).unwrap().unwrap();
+        let dependencies = vec!["numpy".to_string(), "burnysc2".to_string()];
+        assert_eq!(requirements, Requirements::Dependencies(dependencies));
+        assert_eq!(contents, b"numpy\nburnysc2");
+
+        std::fs::write(&pyproject, "[project]\ndependencies = \"numpy\"").unwrap();
+        assert!(Requirements::find(bot.path()).is_err());
+
+        std::fs::write(bot.path().join("requirements.txt"), "numpy").unwrap();
+        let (requirements, contents) = Requirements::find(bot.path()).unwrap().unwrap();
+        let file = bot.path().join("requirements.txt");
+        assert_eq!(requirements, Requirements::File(file));
+        assert_eq!(contents, b"numpy");
+    }
+
+    #[tokio::test]
+    async fn test_prepare_venv_reuses_complete_venv() {
+        let bot = tempfile::tempdir().unwrap();
+        let venvs = tempfile::tempdir().unwrap();
+        let bot_path = bot.path().to_str().unwrap();
+        let (stdout, stderr) = log_files(bot.path());
+        std::fs::write(bot.path().join("requirements.txt"), "numpy").unwrap();
+        let mut settings = ACConfig {
+            python: "python3".to_string(),
+            python_venv_directory: venvs.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+
+        // Without a wheelhouse, bots use the global interpreter
+        let venv = prepare_venv(&settings, bot_path, &stdout, &stderr).await;
+        assert_eq!(venv.unwrap(), None);
+
+        settings.python_wheelhouse = Some("/wheelhouse".to_string());
+        let expected = venvs.path().join(venv_key("python3", b"numpy"));
+        std::fs::create_dir_all(&expected).unwrap();
+        std::fs::write(expected.join(COMPLETE_MARKER), b"").unwrap();
+        let venv = prepare_venv(&settings, bot_path, &stdout, &stderr).await;
+        assert_eq!(venv.unwrap(), Some(expected));
+
+        // A configured python runtime keeps its own interpreter
+        settings
+            .bot_runtimes
+            .insert("python".to_string(), BotRuntime::default());
+        let venv = prepare_venv(&settings, bot_path, &stdout, &stderr).await;
+        assert_eq!(venv.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_venv_fails_without_interpreter() {
+        let bot = tempfile::tempdir().unwrap();
+        let venvs = tempfile::tempdir().unwrap();
+        let (stdout, stderr) = log_files(bot.path());
+        std::fs::write(bot.path().join("requirements.txt"), "numpy").unwrap();
+        let settings = ACConfig {
+            python: "definitely-not-an-installed-python".to_string(),
+            python_wheelhouse: Some("/wheelhouse".to_string()),
+            python_venv_directory: venvs.path().to_str().unwrap().to_string(),
+            python_setup_timeout_secs: 30,
+            ..Default::default()
+        };
+
+        let venv = prepare_venv(&settings, bot.path().to_str().unwrap(), &stdout, &stderr).await;
+        assert!(venv.is_err());
+        // Nothing half-built is left behind
+        assert!(venvs.path().read_dir().unwrap().next().is_none());
+    }
+}
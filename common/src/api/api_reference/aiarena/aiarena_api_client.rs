@@ -332,8 +332,10 @@ mod tests {
     use crate::api::api_reference::aiarena::AiArenaResultForm;
     use crate::api::api_reference::bot_controller_client::BotController;
     use crate::api::api_reference::ControllerApi;
+    use crate::models::aiarena::aiarena_end_reason::EndReason;
     use crate::models::aiarena::aiarena_game_result::AiArenaGameResult;
     use crate::models::aiarena::aiarena_result::AiArenaResult;
+    use crate::models::stats::StepTimeStats;
     use httpmock::prelude::*;
     use httpmock::MockServer;
 
@@ -373,8 +375,16 @@ mod tests {
             match_id: 1,
             bot1_avg_step_time: Some(0.1),
             bot1_tags: Some(vec!["tag1".to_string()]),
+            bot1_step_time_stats: Some(StepTimeStats::default()),
+            bot1_realtime_stats: None,
+            bot1_end_reason: Some(EndReason::Normal),
+            bot1_limit_violations: Vec::new(),
             bot2_avg_step_time: None,
             bot2_tags: None,
+            bot2_step_time_stats: None,
+            bot2_realtime_stats: None,
+            bot2_end_reason: Some(EndReason::Surrender),
+            bot2_limit_violations: Vec::new(),
             result: AiArenaResult::Player1Win,
             game_steps: 10,
         };
@@ -1,4 +1,5 @@
 use reqwest::multipart::Form;
+use serde::Serialize;
 use std::path::Path;
 use tracing::error;
 
@@ -94,6 +95,60 @@ impl AiArenaResultForm {
         self.add_avg_step_time(PlayerNum::One, game_result.bot1_avg_step_time)
             .add_avg_step_time(PlayerNum::Two, game_result.bot2_avg_step_time)
     }
+    fn add_json_part<T: Serialize>(self, part_name: &'static str, value: Option<&T>) -> Self {
+        match value.map(serde_json::to_string) {
+            Some(Ok(json)) => Self {
+                inner: self.inner.text(part_name, json),
+            },
+            Some(Err(e)) => {
+                error!("{:?}", e);
+                self
+            }
+            None => self,
+        }
+    }
+    fn add_step_time_stats(self, game_result: &AiArenaGameResult) -> Self {
+        self.add_json_part(
+            "bot1_step_time_stats",
+            game_result.bot1_step_time_stats.as_ref(),
+        )
+        .add_json_part(
+            "bot2_step_time_stats",
+            game_result.bot2_step_time_stats.as_ref(),
+        )
+    }
+    fn add_realtime_stats(self, game_result: &AiArenaGameResult) -> Self {
+        self.add_json_part(
+            "bot1_realtime_stats",
+            game_result.bot1_realtime_stats.as_ref(),
+        )
+        .add_json_part(
+            "bot2_realtime_stats",
+            game_result.bot2_realtime_stats.as_ref(),
+        )
+    }
+    fn add_limit_violations(mut self, game_result: &AiArenaGameResult) -> Self {
+        for (part_name, violations) in [
+            ("bot1_limit_violations", &game_result.bot1_limit_violations),
+            ("bot2_limit_violations", &game_result.bot2_limit_violations),
+        ] {
+            if !violations.is_empty() {
+                self = self.add_json_part(part_name, Some(violations));
+            }
+        }
+        self
+    }
+    fn add_end_reasons(mut self, game_result: &AiArenaGameResult) -> Self {
+        for (part_name, end_reason) in [
+            ("bot1_end_reason", game_result.bot1_end_reason),
+            ("bot2_end_reason", game_result.bot2_end_reason),
+        ] {
+            if let Some(end_reason) = end_reason {
+                self.inner = self.inner.text(part_name, end_reason.to_string());
+            }
+        }
+        self
+    }
     fn add_bot_tag(mut self, player_num: PlayerNum, bot_tags: Option<&Vec<String>>) -> Self {
         if let Some(bot1_tags) = bot_tags {
             let part_name = match player_num {
@@ -127,6 +182,10 @@ impl From<&AiArenaGameResult> for AiArenaResultForm {
         let ret_value = Self { inner: form };
         ret_value
             .add_avg_step_times(game_result)
+            .add_step_time_stats(game_result)
+            .add_realtime_stats(game_result)
+            .add_end_reasons(game_result)
+            .add_limit_violations(game_result)
             .add_bot_tags(game_result)
     }
 }
@@ -1,6 +1,6 @@
 use crate::api::api_reference::{ApiError, ControllerApi};
 use crate::api::errors::app_error::ApiErrorMessage;
-use crate::models::bot_controller::StartBot;
+use crate::models::bot_controller::{DataUsage, LogTailQuery, StartBot};
 use crate::models::StartResponse;
 use crate::portpicker::Port;
 use async_trait::async_trait;
@@ -70,6 +70,39 @@ impl BotController {
 
         self.execute_request_file(request).await
     }
+
+    pub async fn bot_data_usage(&self) -> Result<DataUsage, ApiError<ApiErrorMessage>> {
+        let path = format!(
+            "/download/bot/{}/data/usage",
+            urlencoding::encode(&self.start_bot.as_ref().unwrap().bot_name)
+        );
+        let usage_url = self.url.join(&path).unwrap(); // static string, so the constructor should catch any parse
+                                                       // errors
+        let request = self
+            .client
+            .request(reqwest::Method::GET, usage_url)
+            .build()?;
+
+        self.execute_request(request).await
+    }
+
+    /// Opens the live log tail of `bot_name`, which is streamed as server-sent events.
+    pub async fn tail_bot_log(
+        &self,
+        bot_name: &str,
+        query: &LogTailQuery,
+    ) -> Result<reqwest::Response, ApiError<ApiErrorMessage>> {
+        let path = format!("/logs/bot/{}/tail", urlencoding::encode(bot_name));
+        let tail_url = self.url.join(&path).unwrap(); // static string, so the constructor should catch any parse
+                                                      // errors
+        let request = self
+            .client
+            .request(reqwest::Method::GET, tail_url)
+            .query(query)
+            .build()?;
+
+        self.execute_request_stream(request).await
+    }
 }
 
 #[async_trait]
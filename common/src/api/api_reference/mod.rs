@@ -272,6 +272,39 @@ pub trait ControllerApi {
             }
         }
     }
+
+    /// Executes the request and returns the response without reading its body, so it can be
+    /// streamed.
+    async fn execute_request_stream(
+        &self,
+        request: Request,
+    ) -> Result<reqwest::Response, ApiError<ApiErrorMessage>> {
+        let response = self.client().execute(request).await?;
+
+        let status = response.status();
+
+        if !status.is_client_error() && !status.is_server_error() {
+            Ok(response)
+        } else {
+            let content = response.text().await?;
+            match serde_json::from_str::<ApiErrorMessage>(&content).map_err(ApiError::from) {
+                Ok(api_error_message) => {
+                    let error = ResponseContent {
+                        status,
+                        api_error_message,
+                    };
+                    let err = ApiError::ResponseError(error);
+                    error!("{:?}", err);
+                    Err(err)
+                }
+                Err(e) => {
+                    tracing::error!("status={},error{}", status, e);
+                    tracing::debug!("{}", &content);
+                    Err(e)
+                }
+            }
+        }
+    }
     async fn download_controller_log(&self) -> Result<Bytes, ApiError<ApiErrorMessage>> {
         let log_url = self.url().join("/download/controller_log").unwrap(); // static string, so the constructor should catch any parse
                                                                             // errors
@@ -1,5 +1,6 @@
 use crate::api::errors::app_error::ApiErrorMessage;
 use crate::models::bot_controller::MapData;
+use crate::models::sc2_controller::{InstalledVersions, StartSc2};
 use crate::models::StartResponse;
 use crate::portpicker::Port;
 use async_trait::async_trait;
@@ -29,24 +30,34 @@ impl SC2Controller {
         self.process_key = process_key
     }
 
-    pub async fn start(&self) -> Result<StartResponse, ApiError<ApiErrorMessage>> {
+    pub async fn start(
+        &self,
+        sc2_version: Option<u32>,
+    ) -> Result<StartResponse, ApiError<ApiErrorMessage>> {
         let start_url = self.url.join("/start").unwrap(); // static string, so the constructor should catch any parse
                                                           // errors
 
         let request = self
             .client
             .request(reqwest::Method::POST, start_url)
+            .json(&StartSc2 { sc2_version })
             .build()?;
 
         self.execute_request(request).await
     }
-    pub async fn start_owned(self) -> Result<StartResponse, ApiError<ApiErrorMessage>> {
-        let start_url = self.url.join("/start").unwrap(); // static string, so the constructor should catch any parse
-                                                          // errors
+    pub async fn start_owned(
+        self,
+        sc2_version: Option<u32>,
+    ) -> Result<StartResponse, ApiError<ApiErrorMessage>> {
+        self.start(sc2_version).await
+    }
+
+    pub async fn versions(&self) -> Result<InstalledVersions, ApiError<ApiErrorMessage>> {
+        let versions_url = self.url.join("/versions")?;
 
         let request = self
             .client
-            .request(reqwest::Method::POST, start_url)
+            .request(reqwest::Method::GET, versions_url)
             .build()?;
 
         self.execute_request(request).await
@@ -99,6 +99,7 @@ impl IntoResponse for AppError {
             }
             Self::Download(DownloadError::Other(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e),
             Self::Download(DownloadError::NotAvailable(e)) => (StatusCode::NOT_IMPLEMENTED, e),
+            Self::Download(DownloadError::QuotaExceeded(e)) => (StatusCode::PAYLOAD_TOO_LARGE, e),
         };
 
         let body = Json(ApiErrorMessage {
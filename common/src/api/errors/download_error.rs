@@ -11,6 +11,8 @@ pub enum DownloadError {
     Io(Error),
     ZipError(anyhow::Error),
     NotAvailable(String),
+    /// The bot data is over its quota and can't be downloaded
+    QuotaExceeded(String),
     Other(String),
 }
 
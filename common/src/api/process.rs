@@ -11,6 +11,7 @@ use crate::api::errors::process_error::ProcessError;
 use crate::api::state::AppState;
 use crate::models::stats::{HostStats, ProcessStats};
 use crate::models::{ProcessStatusResponse, Status, TerminateResponse};
+use crate::procs::limits::exit_violation;
 use crate::utilities::portpicker::Port;
 use serde::{Deserialize, Serialize};
 use sysinfo::{CpuRefreshKind, Pid, PidExt, ProcessExt, ProcessStatus, RefreshKind, SystemExt};
@@ -55,7 +56,10 @@ pub async fn stats(
     if let Some(child) = state.process_map.read().get(&port) {
         let pid = Pid::from_u32(child.id());
         if let Some(process) = sys.process(pid) {
-            let process_stats = ProcessStats::new(process);
+            let mut process_stats = ProcessStats::new(process);
+            if let Some(limited) = state.process_limits.read().get(&port) {
+                process_stats.set_limit_violations(limited.violations(process.virtual_memory()));
+            }
             Ok(Json(process_stats))
         } else {
             state.process_map.write().remove(&port);
@@ -115,21 +119,21 @@ pub async fn terminate_all(
 ) -> Result<Json<TerminateResponse>, AppError> {
     let mut status = Status::Success;
     let mut temp_status_reason = String::new();
+    let mut violations = Vec::new();
 
     for (process_key, mut child) in state.process_map.write().drain() {
         tracing::debug!("Terminating procs on port {}", process_key);
         let mut exited = false;
-        if terminate_type != "kill" {
-            for _ in 0..5 {
-                if let Ok(status) = child.try_status() {
-                    if status.is_some() {
-                        exited = true;
-                        break;
-                    }
-                }
-
+        let attempts = if terminate_type != "kill" { 5 } else { 1 };
+        for attempt in 0..attempts {
+            if attempt > 0 {
                 std::thread::sleep(Duration::from_secs(1));
             }
+            if let Ok(Some(exit_status)) = child.try_status() {
+                violations.extend(exit_violation(exit_status));
+                exited = true;
+                break;
+            }
         }
         if !exited {
             if let Err(e) = child.kill() {
@@ -142,8 +146,12 @@ pub async fn terminate_all(
         }
     }
     state.extra_info.write().clear();
+    for (_, limited) in state.process_limits.write().drain() {
+        violations.extend(limited.recorded().iter().cloned());
+        limited.cleanup();
+    }
 
-    let response = TerminateResponse { status };
+    let response = TerminateResponse { status, violations };
     if temp_status_reason.is_empty() {
         Ok(Json(response))
     } else {
@@ -162,13 +170,19 @@ pub async fn terminate_all(
 ))]
 pub async fn stats_all(State(state): State<AppState>) -> Result<Json<Vec<ProcessStats>>, AppError> {
     let sys = sysinfo::System::new_all();
+    let process_limits = state.process_limits.read();
     let process_stats: Vec<ProcessStats> = state
         .process_map
         .read()
         .iter()
-        .filter_map(|(_, child)| {
+        .filter_map(|(port, child)| {
             let pid = Pid::from_u32(child.id());
-            sys.process(pid).map(ProcessStats::new)
+            let process = sys.process(pid)?;
+            let mut process_stats = ProcessStats::new(process);
+            if let Some(limited) = process_limits.get(port) {
+                process_stats.set_limit_violations(limited.violations(process.virtual_memory()));
+            }
+            Some(process_stats)
         })
         .collect();
     Ok(Json(process_stats))
@@ -196,6 +210,7 @@ pub async fn shutdown(state: State<AppState>) -> Result<Json<TerminateResponse>,
         tracing::info!("Shutting down...");
         let response = TerminateResponse {
             status: Status::Success,
+            violations: Vec::new(),
         };
         Ok(Json(response))
     }
@@ -1,5 +1,6 @@
 use crate::api::process::ProcessMap;
 use crate::configuration::ac_config::ACConfig;
+use crate::procs::limits::LimitMap;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -11,4 +12,5 @@ pub struct AppState {
     pub settings: ACConfig,
     pub shutdown_sender: Sender<()>,
     pub extra_info: Arc<RwLock<HashMap<String, HashMap<String, String>>>>,
+    pub process_limits: LimitMap,
 }
@@ -1,5 +1,7 @@
+use crate::configuration::bot_runtime::BotRuntime;
 use crate::utilities::portpicker::Port;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 #[cfg(feature = "swagger")]
 use utoipa::ToSchema;
 
@@ -43,6 +45,85 @@ pub struct ACConfig {
     pub visualize: bool,
     pub aws: bool,
     pub caching_server_url: String,
+    pub opponent_race_policy: OpponentRacePolicy,
+    pub realtime_step_emulation: bool,
+    pub replay_name_template: String,
+    pub max_annotations: usize,
+    pub max_annotation_length: usize,
+    /// Defaults to allowed, except when running ladder matches
+    pub allow_pause: Option<bool>,
+    /// How often a match is replayed after SC2 itself failed
+    pub max_match_retries: usize,
+    /// Base build of the SC2 version to play on, unless a match asks for another one
+    pub sc2_version: Option<u32>,
+    /// CPUs SC2 processes may run on. Empty means all of them.
+    pub sc2_cpu_affinity: Vec<usize>,
+    /// Address space limit for each SC2 process, also used as the cgroup memory limit
+    pub sc2_memory_limit_mb: Option<u64>,
+    pub sc2_nice: Option<i32>,
+    /// cgroup v2 directory to create a cgroup for each SC2 process in
+    pub sc2_cgroup: Option<String>,
+    /// CPU quota for each SC2 process in number of CPUs. Requires `sc2_cgroup`.
+    pub sc2_cpu_quota: Option<f32>,
+    /// Run bots as their own user in their own user, mount and network namespace, with a
+    /// read-only filesystem except for their data directory and no network but the proxy.
+    /// Needs the controller to run as root.
+    pub bot_sandbox: bool,
+    /// Kill sandboxed bots that make system calls only needed to escape the sandbox
+    pub bot_seccomp: bool,
+    /// Address space limit for each bot process
+    pub bot_memory_limit_mb: Option<u64>,
+    pub bot_max_processes: Option<u64>,
+    pub bot_max_open_files: Option<u64>,
+    /// Runtimes by bot type, in addition to or replacing the built-in ones
+    #[serde(default)]
+    pub bot_runtimes: HashMap<String, BotRuntime>,
+    /// Offline wheelhouse to install Python bot dependencies from. Python bots with a
+    /// `requirements.txt` or `pyproject.toml` get their own virtualenv when this is set, unless
+    /// `bot_runtimes` has a python runtime.
+    pub python_wheelhouse: Option<String>,
+    /// Virtualenvs are cached here by the hash of the bot's requirements. Sandboxed bots see an
+    /// empty `/tmp`, so this must not be below it.
+    pub python_venv_directory: String,
+    /// Time budget for creating a virtualenv and installing the dependencies
+    pub python_setup_timeout_secs: u64,
+    /// How long the proxy waits for a started bot to connect
+    pub bot_connect_timeout_secs: u64,
+    /// Ports the proxy listens on for each bot, one per player. Free ports are picked if empty.
+    pub bot_listener_ports: Vec<Port>,
+    /// Size cap of each bot log. The start and the end of the log are kept, with a marker in
+    /// between. Logs are shrunk as they are written, and may go over the cap by a quarter of
+    /// their tail (at least 64 KiB) until then. Unbounded if not set.
+    pub bot_log_max_size_kb: Option<u64>,
+    /// How much of a capped bot log is kept from the start
+    pub bot_log_head_kb: u64,
+    /// Strings, like tokens or paths, that are redacted from bot logs before they are uploaded or
+    /// tailed
+    pub bot_log_scrub: Vec<String>,
+    /// Also keep `merged.log` with the stdout and stderr lines of each bot in order, prefixed
+    /// with the time since the bot started
+    pub bot_log_merged: bool,
+    /// Size quota of each bot's data directory. Bots are not stopped when they go over it while
+    /// they run, that is only reported as a limit violation. The quota is enforced when the data
+    /// is packaged for upload, following `bot_data_quota_policy`.
+    pub bot_data_quota_mb: Option<u64>,
+    /// What happens to the data of a bot that is over its quota when the match ends
+    pub bot_data_quota_policy: DataQuotaPolicy,
+    /// How long the proxy waits for the human player of a match to connect
+    pub human_connect_timeout_secs: u64,
+    /// User and group id sandboxed bots run as. The second bot uses the next id.
+    pub bot_sandbox_uid: u32,
+    /// Let bots connect to `/sc2api` on the main proxy port as well as to their own listener.
+    /// They are matched to the bot players in the order they connect.
+    pub legacy_bot_endpoint: bool,
+}
+
+impl ACConfig {
+    /// Whether matches can be paused through the proxy control endpoints
+    pub fn allow_pause(&self) -> bool {
+        self.allow_pause
+            .unwrap_or(self.run_type != RunType::AiArena)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -63,9 +144,49 @@ impl Default for RunType {
     }
 }
 
+/// Controls when a bot is allowed to learn the actual race of its opponent
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum OpponentRacePolicy {
+    /// Always forward the actual race reported by SC2
+    #[serde(rename = "reveal")]
+    Reveal,
+    /// Report the requested race until an enemy unit has been observed
+    #[serde(rename = "until_scouted")]
+    UntilScouted,
+    /// Always report the requested race
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl Default for OpponentRacePolicy {
+    fn default() -> Self {
+        Self::UntilScouted
+    }
+}
+
+/// What to do with bot data that is over the quota when it is packaged
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum DataQuotaPolicy {
+    /// Upload the files that fit in the quota and leave out the rest
+    #[serde(rename = "truncate")]
+    Truncate,
+    /// Don't upload the data, so the bot keeps the data of its previous match
+    #[serde(rename = "reject")]
+    Reject,
+}
+
+impl Default for DataQuotaPolicy {
+    fn default() -> Self {
+        Self::Reject
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::configuration::ac_config::{ACConfig, RunType};
+    use crate::configuration::ac_config::{ACConfig, DataQuotaPolicy, OpponentRacePolicy, RunType};
+    use std::collections::HashMap;
 
     fn ac_config() -> ACConfig {
         ACConfig {
@@ -105,9 +226,52 @@ mod tests {
             visualize: false,
             aws: false,
             caching_server_url: "".to_string(),
+            opponent_race_policy: OpponentRacePolicy::UntilScouted,
+            realtime_step_emulation: false,
+            replay_name_template: "{match_id}_{player1}_vs_{player2}".to_string(),
+            max_annotations: 0,
+            max_annotation_length: 0,
+            allow_pause: None,
+            max_match_retries: 0,
+            sc2_version: None,
+            sc2_cpu_affinity: vec![],
+            sc2_memory_limit_mb: None,
+            sc2_nice: None,
+            sc2_cgroup: None,
+            sc2_cpu_quota: None,
+            bot_sandbox: false,
+            bot_seccomp: false,
+            bot_memory_limit_mb: None,
+            bot_max_processes: None,
+            bot_max_open_files: None,
+            bot_runtimes: HashMap::new(),
+            python_wheelhouse: None,
+            python_venv_directory: "123".to_string(),
+            python_setup_timeout_secs: 0,
+            bot_connect_timeout_secs: 0,
+            bot_listener_ports: vec![],
+            bot_log_max_size_kb: None,
+            bot_log_head_kb: 0,
+            bot_log_scrub: vec![],
+            bot_log_merged: false,
+            bot_data_quota_mb: None,
+            bot_data_quota_policy: DataQuotaPolicy::Reject,
+            human_connect_timeout_secs: 0,
+            bot_sandbox_uid: 20001,
+            legacy_bot_endpoint: true,
         }
     }
 
+    #[test]
+    fn test_allow_pause_default() {
+        let mut ac_config = ac_config();
+        assert!(ac_config.allow_pause());
+        ac_config.run_type = RunType::AiArena;
+        assert!(!ac_config.allow_pause());
+        ac_config.allow_pause = Some(true);
+        assert!(ac_config.allow_pause());
+    }
+
     #[test]
     fn test_api_key_obfuscation() {
         let ac_config = ac_config();
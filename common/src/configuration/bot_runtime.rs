@@ -0,0 +1,220 @@
+//! Runtimes that bots are started with, looked up by bot type. The built-in bot types have
+//! defaults here, and `BOT_RUNTIMES` can override them or add new types.
+
+use crate::configuration::ac_config::ACConfig;
+use crate::models::bot_controller::BotType;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+#[cfg(feature = "swagger")]
+use utoipa::ToSchema;
+
+/// Time budget for the setup command of a runtime, unless it sets its own
+const DEFAULT_SETUP_TIMEOUT_SECS: u64 = 300;
+
+/// How to start a bot. Every string may contain the placeholders `{bot_name}`, `{bot_dir}` and
+/// `{python}`, the latter being the `PYTHON` setting.
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BotRuntime {
+    /// Program to run. Paths starting with `./` are relative to the working directory.
+    pub command: String,
+    /// Arguments passed before the ladder arguments (`--GamePort`, `--LadderServer`, ...)
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables as `NAME=value`
+    #[serde(default)]
+    pub env: Vec<String>,
+    /// Working directory relative to the bot directory. Defaults to the bot directory.
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// File relative to the bot directory that has to exist, e.g. the bot binary
+    #[serde(default)]
+    pub required_file: Option<String>,
+    /// Mark `required_file` as executable before starting the bot
+    #[serde(default)]
+    pub make_executable: bool,
+    /// Command run in the working directory before the bot is started, e.g. to install
+    /// dependencies. The bot is not started if it fails.
+    #[serde(default)]
+    pub setup: Vec<String>,
+    /// Time budget for `setup`, 300 seconds if not set
+    #[serde(default)]
+    pub setup_timeout_secs: Option<u64>,
+}
+
+/// Values for the placeholders in a [`BotRuntime`]
+#[derive(Debug, Clone, Copy)]
+pub struct RuntimeVariables<'a> {
+    pub bot_name: &'a str,
+    pub bot_dir: &'a str,
+    pub python: &'a str,
+}
+
+impl BotRuntime {
+    /// The configured runtime for `bot_type`, or the built-in one
+    pub fn resolve(settings: &ACConfig, bot_type: &BotType) -> Option<Self> {
+        settings
+            .bot_runtimes
+            .get(bot_type.name())
+            .cloned()
+            .or_else(|| Self::builtin(bot_type))
+    }
+
+    pub fn builtin(bot_type: &BotType) -> Option<Self> {
+        let (command, args, required_file) = match bot_type {
+            BotType::CppWin32 => ("wine", vec!["{bot_name}.exe"], "{bot_name}.exe"),
+            BotType::CppLinux => ("./{bot_name}", vec![], "{bot_name}"),
+            BotType::DotnetCore => ("dotnet", vec!["{bot_name}.dll"], "{bot_name}.dll"),
+            BotType::Java => ("java", vec!["-jar", "{bot_name}.jar"], "{bot_name}.jar"),
+            BotType::NodeJs => ("node", vec!["{bot_name}.js"], "{bot_name}.js"),
+            BotType::Python => ("{python}", vec!["run.py"], "run.py"),
+            BotType::Custom(_) => return None,
+        };
+        Some(Self {
+            command: command.to_string(),
+            args: args.into_iter().map(String::from).collect(),
+            required_file: Some(required_file.to_string()),
+            make_executable: bot_type == &BotType::CppLinux,
+            ..Default::default()
+        })
+    }
+
+    /// Returns a copy with all placeholders filled in
+    pub fn expand(&self, variables: RuntimeVariables) -> Self {
+        let expand = |s: &String| expand_placeholders(s, variables);
+        Self {
+            command: expand(&self.command),
+            args: self.args.iter().map(expand).collect(),
+            env: self.env.iter().map(expand).collect(),
+            working_dir: self.working_dir.as_ref().map(expand),
+            required_file: self.required_file.as_ref().map(expand),
+            make_executable: self.make_executable,
+            setup: self.setup.iter().map(expand).collect(),
+            setup_timeout_secs: self.setup_timeout_secs,
+        }
+    }
+
+    pub fn setup_timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.setup_timeout_secs
+                .unwrap_or(DEFAULT_SETUP_TIMEOUT_SECS),
+        )
+    }
+
+    /// The `env` entries split into name and value. Entries without `=` are skipped.
+    pub fn env_vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env.iter().filter_map(|entry| entry.split_once('='))
+    }
+}
+
+/// Checks that every key of `BOT_RUNTIMES` is a bot type that matches can refer to
+pub fn validate_bot_runtimes(settings: &ACConfig) -> Result<(), String> {
+    for name in settings.bot_runtimes.keys() {
+        match BotType::from_str(name) {
+            Ok(bot_type) if bot_type.name() == name => {}
+            _ => {
+                return Err(format!(
+                    "Invalid BOT_RUNTIMES entry {name:?}: bot types are lowercase letters, \
+                     digits, '-' and '_'"
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn expand_placeholders(template: &str, variables: RuntimeVariables) -> String {
+    template
+        .replace("{bot_name}", variables.bot_name)
+        .replace("{bot_dir}", variables.bot_dir)
+        .replace("{python}", variables.python)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARIABLES: RuntimeVariables = RuntimeVariables {
+        bot_name: "my_bot",
+        bot_dir: "/bots/my_bot",
+        python: "python3.11",
+    };
+
+    #[test]
+    fn test_builtin_runtimes() {
+        let java = BotRuntime::builtin(&BotType::Java)
+            .unwrap()
+            .expand(VARIABLES);
+        assert_eq!(java.command, "java");
+        assert_eq!(java.args, vec!["-jar", "my_bot.jar"]);
+        assert_eq!(java.required_file.as_deref(), Some("my_bot.jar"));
+
+        let python = BotRuntime::builtin(&BotType::Python)
+            .unwrap()
+            .expand(VARIABLES);
+        assert_eq!(python.command, "python3.11");
+
+        let linux = BotRuntime::builtin(&BotType::CppLinux)
+            .unwrap()
+            .expand(VARIABLES);
+        assert_eq!(linux.command, "./my_bot");
+        assert!(linux.make_executable);
+
+        assert!(BotRuntime::builtin(&BotType::Custom("rust".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_configured_runtime_overrides_builtin() {
+        let mut settings = ACConfig::default();
+        let rust = BotRuntime {
+            command: "./{bot_name}".to_string(),
+            env: vec![
+                "RUST_BACKTRACE=1".to_string(),
+                "BOT_DIR={bot_dir}".to_string(),
+            ],
+            ..Default::default()
+        };
+        let python = BotRuntime {
+            command: "{bot_dir}/venv/bin/python".to_string(),
+            args: vec!["run.py".to_string()],
+            ..Default::default()
+        };
+        settings
+            .bot_runtimes
+            .insert("rust".to_string(), rust.clone());
+        settings.bot_runtimes.insert("python".to_string(), python);
+
+        let custom = BotType::Custom("rust".to_string());
+        assert_eq!(BotRuntime::resolve(&settings, &custom), Some(rust));
+        let expanded = BotRuntime::resolve(&settings, &BotType::Python)
+            .unwrap()
+            .expand(VARIABLES);
+        assert_eq!(expanded.command, "/bots/my_bot/venv/bin/python");
+        assert_eq!(
+            BotRuntime::resolve(&settings, &custom)
+                .unwrap()
+                .expand(VARIABLES)
+                .env_vars()
+                .collect::<Vec<_>>(),
+            vec![("RUST_BACKTRACE", "1"), ("BOT_DIR", "/bots/my_bot")]
+        );
+        assert!(BotRuntime::resolve(&settings, &BotType::Custom("go".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_validate_bot_runtimes() {
+        let mut settings = ACConfig::default();
+        settings
+            .bot_runtimes
+            .insert("rust".to_string(), BotRuntime::default());
+        assert!(validate_bot_runtimes(&settings).is_ok());
+        for name in ["Rust", "rust bot", ""] {
+            let mut invalid = settings.clone();
+            invalid
+                .bot_runtimes
+                .insert(name.to_string(), BotRuntime::default());
+            assert!(validate_bot_runtimes(&invalid).is_err(), "{name:?}");
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod ac_config;
+pub mod bot_runtime;
 
 use crate::configuration::ac_config::ACConfig;
 use crate::utilities::portpicker::Port;
@@ -5,6 +5,7 @@ pub mod configuration;
 pub mod logging;
 pub mod models;
 pub mod paths;
+pub mod preflight;
 pub mod procs;
 pub mod utilities;
 
@@ -27,4 +28,11 @@ impl PlayerNum {
             PlayerNum::Two => PlayerNum::One,
         }
     }
+    /// Zero-based index of the player, e.g. into the controller lists
+    pub const fn index(&self) -> usize {
+        match self {
+            PlayerNum::One => 0,
+            PlayerNum::Two => 1,
+        }
+    }
 }
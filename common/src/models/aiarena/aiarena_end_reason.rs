@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// How a player's participation in a match ended
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
+pub enum EndReason {
+    /// The game ended with a result reported by SC2, or reached the maximum game time
+    Normal,
+    /// The bot left the game with leave_game while the game was running
+    Surrender,
+    /// The bot sent quit, before or during the game
+    Quit,
+    /// The bot quit, disconnected or misbehaved before the game started
+    InitCrash,
+    /// The bot or SC2 crashed while the game was running
+    GameCrash,
+    /// The bot disconnected after SC2 had already ended the game
+    DisconnectAfterGameEnd,
+    /// The bot exceeded the step timeout
+    Timeout,
+}
+
+impl EndReason {
+    /// Whether the game was concluded by this player's end of the match
+    pub const fn concludes_game(self) -> bool {
+        matches!(self, Self::Normal | Self::Surrender | Self::Quit)
+    }
+}
+
+impl Display for EndReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Default for EndReason {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
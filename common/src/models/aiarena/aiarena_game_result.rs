@@ -1,4 +1,8 @@
+use crate::models::aiarena::aiarena_end_reason::EndReason;
 use crate::models::aiarena::aiarena_result::AiArenaResult;
+use crate::models::stats::{RealtimeStats, StepTimeStats};
+use crate::procs::limits::LimitViolation;
+use crate::PlayerNum;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -9,11 +13,38 @@ pub struct AiArenaGameResult {
     pub bot1_avg_step_time: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bot1_tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot1_step_time_stats: Option<StepTimeStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot1_realtime_stats: Option<RealtimeStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot1_end_reason: Option<EndReason>,
+    /// Limits the bot ran into that are only known once the match is over, like its data quota
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bot1_limit_violations: Vec<LimitViolation>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bot2_avg_step_time: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bot2_tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot2_step_time_stats: Option<StepTimeStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot2_realtime_stats: Option<RealtimeStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot2_end_reason: Option<EndReason>,
+    /// Limits the bot ran into that are only known once the match is over, like its data quota
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bot2_limit_violations: Vec<LimitViolation>,
     #[serde(rename = "type")]
     pub result: AiArenaResult,
     pub game_steps: u32,
 }
+
+impl AiArenaGameResult {
+    pub fn limit_violations_mut(&mut self, player_num: PlayerNum) -> &mut Vec<LimitViolation> {
+        match player_num {
+            PlayerNum::One => &mut self.bot1_limit_violations,
+            PlayerNum::Two => &mut self.bot2_limit_violations,
+        }
+    }
+}
@@ -8,4 +8,10 @@ pub struct AiArenaMatch {
     pub bot1: AiArenaBot,
     pub bot2: AiArenaBot,
     pub map: AiArenaMap,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realtime: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_fog: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u32>,
 }
@@ -1,4 +1,5 @@
 pub mod aiarena_bot;
+pub mod aiarena_end_reason;
 pub mod aiarena_game_result;
 pub mod aiarena_map;
 pub mod aiarena_match;
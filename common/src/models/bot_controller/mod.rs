@@ -1,41 +1,144 @@
+use crate::procs::limits::LimitViolation;
 use crate::utilities::portpicker::Port;
 use crate::PlayerNum;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::str::FromStr;
 #[cfg(feature = "swagger")]
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 #[cfg_attr(feature = "swagger", derive(ToSchema))]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct StartBot {
     pub bot_name: String,
+    #[cfg_attr(feature = "swagger", schema(value_type = String, example = "python"))]
     pub bot_type: BotType,
     pub opponent_id: String,
     pub player_num: PlayerNum,
     pub match_id: u32,
     pub process_key: Port,
     pub should_download: bool,
+    /// Proxy port dedicated to this bot, passed as `--GamePort` and `--StartPort`
+    #[serde(default)]
+    pub proxy_port: Option<Port>,
+    #[serde(default)]
+    pub data_snapshot: DataSnapshot,
 }
 
+/// What to do with the data directory of a bot before starting it, so every attempt of a
+/// retried match starts from the same data
 #[cfg_attr(feature = "swagger", derive(ToSchema))]
-#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSnapshot {
+    /// The match won't be retried
+    #[default]
+    Skip,
+    /// Keep a copy of the data for later attempts
+    Take,
+    /// Replace the data with the copy taken on the first attempt
+    Restore,
+}
+
+/// Log files kept for each bot
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BotLog {
+    Stdout,
+    #[default]
+    Stderr,
+    /// Lines of both outputs in order, prefixed with the time since the bot started. Only kept
+    /// if `BOT_LOG_MERGED` is set.
+    Merged,
+}
+
+impl BotLog {
+    pub const ALL: [BotLog; 3] = [BotLog::Stdout, BotLog::Stderr, BotLog::Merged];
+
+    pub const fn file_name(&self) -> &'static str {
+        match self {
+            BotLog::Stdout => "stdout.log",
+            BotLog::Stderr => "stderr.log",
+            BotLog::Merged => "merged.log",
+        }
+    }
+}
+
+/// Size of a bot's data directory, compared to its quota
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DataUsage {
+    /// Current size of the data directory
+    pub used_bytes: u64,
+    /// Largest size measured while the bot was running
+    pub peak_bytes: u64,
+    pub quota_bytes: Option<u64>,
+}
+
+impl DataUsage {
+    /// The quota violation, if the data directory went over the quota at any point
+    pub fn violation(&self) -> Option<LimitViolation> {
+        let quota_bytes = self.quota_bytes?;
+        let used_bytes = self.used_bytes.max(self.peak_bytes);
+        (used_bytes > quota_bytes).then_some(LimitViolation::DataQuota {
+            quota_bytes,
+            used_bytes,
+        })
+    }
+
+    /// Whether the data directory is over the quota right now
+    pub fn over_quota(&self) -> bool {
+        self.quota_bytes
+            .map_or(false, |quota_bytes| self.used_bytes > quota_bytes)
+    }
+}
+
+/// Query parameters of the live bot log tail
+#[cfg_attr(feature = "swagger", derive(IntoParams))]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LogTailQuery {
+    /// Stream the whole log instead of only its most recent output
+    #[serde(default)]
+    pub from_start: bool,
+    /// Which log to follow
+    #[serde(default)]
+    pub log: BotLog,
+}
+
+/// Type of a bot, which selects the runtime it is started with
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(into = "String", try_from = "String")]
 pub enum BotType {
-    #[serde(rename = "cppwin32")]
     CppWin32,
-    #[serde(rename = "cpplinux")]
     CppLinux,
-    #[serde(rename = "dotnetcore")]
     DotnetCore,
-    #[serde(rename = "java")]
     Java,
-    #[serde(rename = "nodejs")]
     NodeJs,
-    #[serde(rename = "python")]
     Python,
+    /// A runtime from the `BOT_RUNTIMES` configuration
+    Custom(String),
+}
+
+impl BotType {
+    /// Name of the type, which is also its key in `BOT_RUNTIMES`
+    pub fn name(&self) -> &str {
+        match self {
+            Self::CppWin32 => "cppwin32",
+            Self::CppLinux => "cpplinux",
+            Self::DotnetCore => "dotnetcore",
+            Self::Java => "java",
+            Self::NodeJs => "nodejs",
+            Self::Python => "python",
+            Self::Custom(name) => name,
+        }
+    }
 }
 
 impl FromStr for BotType {
     type Err = ();
+    /// Parses a built-in type, or any other name made of letters, digits, `-` and `_` as a custom
+    /// type. Names are case-insensitive.
     fn from_str(t: &str) -> Result<Self, Self::Err> {
         match &t.to_lowercase()[..] {
             "cppwin32" => Ok(Self::CppWin32),
@@ -44,14 +147,84 @@ impl FromStr for BotType {
             "java" => Ok(Self::Java),
             "nodejs" => Ok(Self::NodeJs),
             "python" => Ok(Self::Python),
+            name if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                Ok(Self::Custom(name.to_string()))
+            }
             _ => Err(()),
         }
     }
 }
 
+impl fmt::Display for BotType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl From<BotType> for String {
+    fn from(bot_type: BotType) -> Self {
+        bot_type.name().to_string()
+    }
+}
+
+impl TryFrom<String> for BotType {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value).map_err(|_| format!("Invalid bot type {value:?}"))
+    }
+}
+
 #[cfg_attr(feature = "swagger", derive(ToSchema))]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MapData {
     pub query: String,
     pub map_path: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_type_names() {
+        assert_eq!(BotType::from_str("CppLinux"), Ok(BotType::CppLinux));
+        assert_eq!(
+            BotType::from_str("Rust"),
+            Ok(BotType::Custom("rust".to_string()))
+        );
+        assert!(BotType::from_str("").is_err());
+        assert!(BotType::from_str("rust bot").is_err());
+    }
+
+    #[test]
+    fn test_bot_type_serde() {
+        let json = serde_json::to_string(&BotType::NodeJs).unwrap();
+        assert_eq!(json, r#""nodejs""#);
+        let bot_type: BotType = serde_json::from_str(r#""go""#).unwrap();
+        assert_eq!(bot_type, BotType::Custom("go".to_string()));
+        assert!(serde_json::from_str::<BotType>(r#""../go""#).is_err());
+    }
+
+    #[test]
+    fn test_data_usage_violation() {
+        let mut usage = DataUsage {
+            used_bytes: 10,
+            peak_bytes: 30,
+            quota_bytes: Some(20),
+        };
+        assert_eq!(
+            usage.violation(),
+            Some(LimitViolation::DataQuota {
+                quota_bytes: 20,
+                used_bytes: 30
+            })
+        );
+        assert!(!usage.over_quota());
+        usage.quota_bytes = None;
+        assert_eq!(usage.violation(), None);
+    }
+}
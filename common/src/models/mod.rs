@@ -1,4 +1,5 @@
 use crate::api::process::ProcStatus;
+use crate::procs::limits::LimitViolation;
 use crate::utilities::portpicker::Port;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "swagger")]
@@ -20,14 +21,21 @@ pub enum Status {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TerminateResponse {
     pub status: Status,
+    /// Processes that were killed by a limit or the sandbox before they were terminated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<LimitViolation>,
 }
 #[cfg_attr(feature = "swagger", derive(ToSchema))]
 #[derive(Debug, Deserialize, Serialize)]
 pub struct StartResponse {
     pub status: Status,
     pub status_reason: String,
+    /// Port of the SC2 websocket. Bots have no port of their own, so it is their process key.
     pub port: Port,
     pub process_key: Port,
+    /// Limits or sandbox features that could not be applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<LimitViolation>,
 }
 #[cfg_attr(feature = "swagger", derive(ToSchema))]
 #[derive(Serialize, Deserialize, Copy, Clone)]
@@ -1 +1,31 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "swagger")]
+use utoipa::ToSchema;
 
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StartSc2 {
+    /// Base build of the SC2 version to start. Defaults to the latest installed version.
+    #[serde(default)]
+    pub sc2_version: Option<u32>,
+}
+
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Sc2Version {
+    pub base_build: u32,
+    pub path: String,
+}
+
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstalledVersions {
+    /// Sorted from oldest to newest
+    pub versions: Vec<Sc2Version>,
+}
+
+impl InstalledVersions {
+    pub fn latest(&self) -> Option<u32> {
+        self.versions.last().map(|v| v.base_build)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::procs::limits::LimitViolation;
 use serde::{Deserialize, Serialize};
 use sysinfo::{CpuExt, PidExt, Process, ProcessExt, System, SystemExt};
 #[cfg(feature = "swagger")]
@@ -15,6 +16,9 @@ pub struct ProcessStats {
     group_id: Option<String>,
     start_time: u64,
     run_time: u64,
+    /// Resource limits the process ran into
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    limit_violations: Vec<LimitViolation>,
 }
 impl ProcessStats {
     pub fn new(process: &Process) -> Self {
@@ -29,8 +33,15 @@ impl ProcessStats {
             group_id: process.group_id().map(|x| x.to_string()),
             start_time: process.start_time(),
             run_time: process.run_time(),
+            limit_violations: Vec::new(),
         }
     }
+    pub fn set_limit_violations(&mut self, limit_violations: Vec<LimitViolation>) {
+        self.limit_violations = limit_violations;
+    }
+    pub fn limit_violations(&self) -> &[LimitViolation] {
+        &self.limit_violations
+    }
 }
 impl Default for ProcessStats {
     fn default() -> Self {
@@ -45,6 +56,7 @@ impl Default for ProcessStats {
             group_id: None,
             start_time: 0,
             run_time: 0,
+            limit_violations: Vec::new(),
         }
     }
 }
@@ -73,3 +85,24 @@ impl HostStats {
         }
     }
 }
+
+/// Summary of the time a bot took to respond to each game step, in seconds
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Default, Debug, Copy, Clone, Deserialize, Serialize, PartialEq)]
+pub struct StepTimeStats {
+    pub steps: u32,
+    pub p50: f32,
+    pub p90: f32,
+    pub p99: f32,
+    pub max: f32,
+    pub over_max_frame_time: u32,
+}
+
+/// How far a bot fell behind the game clock in a realtime game
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Default, Debug, Copy, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RealtimeStats {
+    pub missed_game_loops: u32,
+    pub max_lag_loops: f32,
+    pub avg_lag_loops: f32,
+}
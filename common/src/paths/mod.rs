@@ -6,6 +6,9 @@ use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 
+/// Oldest supported base build, 3.16.1
+const MIN_BASE_BUILD: u32 = 55958;
+
 pub fn default_base() -> PathBuf {
     if let Some(base_dir) = var_os("SC2_PROXY_BASE") {
         Path::new(&base_dir).to_path_buf()
@@ -48,31 +51,57 @@ pub fn cwd() -> Option<PathBuf> {
     }
 }
 
-pub fn latest_executable_path(versions_dir: PathBuf) -> io::Result<PathBuf> {
-    let (max_version, path) = fs::read_dir(versions_dir)?
-        .filter_map(|entry| -> Option<(u64, PathBuf)> {
-            let path = entry.unwrap().path();
+/// Installed `Base<build>` folders in `versions_dir`, sorted by base build
+pub fn installed_versions(versions_dir: &Path) -> io::Result<Vec<(u32, PathBuf)>> {
+    let mut versions: Vec<(u32, PathBuf)> = fs::read_dir(versions_dir)?
+        .filter_map(|entry| -> Option<(u32, PathBuf)> {
+            let path = entry.ok()?.path();
             let name = path.file_name()?.to_str()?;
 
-            if path.metadata().unwrap().is_dir() && name.starts_with("Base") {
+            if path.is_dir() && name.starts_with("Base") {
                 let version: &str = name.split_at(4).1;
-                version.parse::<u64>().ok().map(|v| (v, path.to_path_buf()))
+                version.parse::<u32>().ok().map(|v| (v, path.to_path_buf()))
             } else {
                 None
             }
         })
-        .max_by_key(|(v, _)| *v)
-        .ok_or_else(|| {
-            std::io::Error::new(ErrorKind::NotFound, "No downloaded SC2 binaries found")
-        })?;
+        .collect();
+    versions.sort_by_key(|(v, _)| *v);
+    Ok(versions)
+}
+
+pub fn latest_executable_path(versions_dir: PathBuf) -> io::Result<PathBuf> {
+    let (max_version, path) = installed_versions(&versions_dir)?.pop().ok_or_else(|| {
+        std::io::Error::new(ErrorKind::NotFound, "No downloaded SC2 binaries found")
+    })?;
 
-    if max_version < 55958 {
+    if max_version < MIN_BASE_BUILD {
         panic!("Your SC2 binary is too old. Upgrade to 3.16.1 or newer.");
     }
 
     Ok(path.join(bin_path()))
 }
 
+/// SC2 binary of a specific base build
+pub fn versioned_executable_path(versions_dir: PathBuf, base_build: u32) -> io::Result<PathBuf> {
+    if base_build < MIN_BASE_BUILD {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("SC2 build {base_build} is too old. 3.16.1 (55958) or newer is required."),
+        ));
+    }
+    installed_versions(&versions_dir)?
+        .into_iter()
+        .find(|(v, _)| *v == base_build)
+        .map(|(_, path)| path.join(bin_path()))
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                format!("SC2 build {base_build} is not installed"),
+            )
+        })
+}
+
 pub fn execute_info_path() -> Option<PathBuf> {
     let env_skip_os_str = var_os("SC2_PROXY_SKIP_EXECUTE_INFO").unwrap_or_default();
     let env_skip_str = env_skip_os_str.to_str()?;
@@ -133,7 +162,20 @@ pub fn base_dir() -> PathBuf {
 
 /// PathBuf to SC2 binary executable
 pub fn executable() -> io::Result<PathBuf> {
-    latest_executable_path(base_dir().join(Path::new("Versions")))
+    latest_executable_path(versions_dir())
+}
+
+/// PathBuf to the SC2 binary of `base_build`, or the latest one if no build is requested
+pub fn executable_for_version(base_build: Option<u32>) -> io::Result<PathBuf> {
+    match base_build {
+        Some(base_build) => versioned_executable_path(versions_dir(), base_build),
+        None => executable(),
+    }
+}
+
+/// Directory containing the `Base<build>` folders
+pub fn versions_dir() -> PathBuf {
+    base_dir().join(Path::new("Versions"))
 }
 
 /// Directory to switch to before starting SC2
@@ -155,3 +197,42 @@ pub fn replay_dir() -> PathBuf {
 pub fn map_dir() -> PathBuf {
     base_dir().join(Path::new("Maps"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions_dir(builds: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for build in builds {
+            fs::create_dir(dir.path().join(build)).unwrap();
+        }
+        fs::write(dir.path().join("Base99999"), b"").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_installed_versions() {
+        let dir = versions_dir(&["Base75689", "Base69232", "Other", "BaseX"]);
+        let versions = installed_versions(dir.path()).unwrap();
+        let builds: Vec<u32> = versions.iter().map(|(v, _)| *v).collect();
+        assert_eq!(builds, vec![69232, 75689]);
+        assert_eq!(
+            latest_executable_path(dir.path().to_path_buf()).unwrap(),
+            dir.path().join("Base75689").join(bin_path())
+        );
+    }
+
+    #[test]
+    fn test_versioned_executable_path() {
+        let dir = versions_dir(&["Base75689", "Base69232"]);
+        assert_eq!(
+            versioned_executable_path(dir.path().to_path_buf(), 69232).unwrap(),
+            dir.path().join("Base69232").join(bin_path())
+        );
+        let missing = versioned_executable_path(dir.path().to_path_buf(), 80000).unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+        let too_old = versioned_executable_path(dir.path().to_path_buf(), 50000).unwrap_err();
+        assert_eq!(too_old.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,254 @@
+//! Environment self-checks that controllers run on request, so misconfiguration is reported
+//! before a match starts instead of failing halfway through one
+
+use serde::{Deserialize, Serialize};
+use std::env::consts::EXE_EXTENSION;
+use std::env::var_os;
+use std::fmt;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "swagger")]
+use utoipa::ToSchema;
+
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    /// Only needed for some matches, e.g. a runtime for one bot type
+    Warning,
+    Failed,
+}
+
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What an operator should do to fix a failed check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl PreflightCheck {
+    pub fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+    pub fn failed(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Failed,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+    /// Downgrades a failed check to a warning
+    pub fn optional(mut self) -> Self {
+        if self.status == CheckStatus::Failed {
+            self.status = CheckStatus::Warning;
+        }
+        self
+    }
+}
+
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightReport {
+    pub controller: String,
+    pub passed: bool,
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    pub fn new(controller: &str, checks: Vec<PreflightCheck>) -> Self {
+        let passed = checks.iter().all(|c| c.status != CheckStatus::Failed);
+        Self {
+            controller: controller.to_string(),
+            passed,
+            checks,
+        }
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Preflight check for {}", self.controller)?;
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Ok => "OK",
+                CheckStatus::Warning => "WARN",
+                CheckStatus::Failed => "FAIL",
+            };
+            writeln!(f, "[{status:>4}] {}: {}", check.name, check.detail)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "       fix: {fix}")?;
+            }
+        }
+        let summary = if self.passed { "passed" } else { "failed" };
+        write!(f, "Preflight check {summary}")
+    }
+}
+
+/// Prints `report` and exits, with a non-zero code if any check failed
+pub fn exit_with_report(report: &PreflightReport) -> ! {
+    println!("{report}");
+    std::process::exit(if report.passed { 0 } else { 1 })
+}
+
+/// Report for controllers that could not load their configuration, so nothing else can be checked
+pub fn configuration_failed(controller: &str, error: impl fmt::Display) -> PreflightReport {
+    PreflightReport::new(
+        controller,
+        vec![PreflightCheck::failed(
+            "Configuration",
+            format!("Could not load the configuration: {error}"),
+            "Make sure the proxy controller is running and reachable",
+        )],
+    )
+}
+
+/// Resolves `program` the way a process spawn would: paths are checked directly, bare names are
+/// looked up in `PATH`
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let paths = var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            let exe =
+                (!EXE_EXTENSION.is_empty()).then(|| dir.join(format!("{program}.{EXE_EXTENSION}")));
+            std::iter::once(dir.join(program)).chain(exe)
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn check_program(name: &str, program: &str) -> PreflightCheck {
+    match find_program(program) {
+        Some(path) => PreflightCheck::ok(name, format!("Found {}", path.display())),
+        None => PreflightCheck::failed(
+            name,
+            format!("{program:?} was not found"),
+            format!("Install {program} or add it to PATH"),
+        ),
+    }
+}
+
+pub fn check_directory(name: &str, path: &Path) -> PreflightCheck {
+    if path.is_dir() {
+        PreflightCheck::ok(name, format!("{} exists", path.display()))
+    } else {
+        PreflightCheck::failed(
+            name,
+            format!("{} does not exist", path.display()),
+            "Create the directory or fix the path in the configuration",
+        )
+    }
+}
+
+/// Checks that `path` exists or can be created, and that files can be written to it
+pub fn check_writable_directory(name: &str, path: &Path) -> PreflightCheck {
+    if let Err(e) = std::fs::create_dir_all(path) {
+        return PreflightCheck::failed(
+            name,
+            format!("Could not create {}: {e}", path.display()),
+            "Create the directory or fix the path in the configuration",
+        );
+    }
+    match tempfile::tempfile_in(path) {
+        Ok(_) => PreflightCheck::ok(name, format!("{} is writable", path.display())),
+        Err(e) => PreflightCheck::failed(
+            name,
+            format!("Could not write to {}: {e}", path.display()),
+            "Make the directory writable for the user running the controller",
+        ),
+    }
+}
+
+pub fn check_file(name: &str, path: &Path) -> PreflightCheck {
+    if path.is_file() {
+        PreflightCheck::ok(name, format!("{} exists", path.display()))
+    } else {
+        PreflightCheck::failed(
+            name,
+            format!("{} does not exist", path.display()),
+            "Create the file or fix the path in the configuration",
+        )
+    }
+}
+
+/// Checks that every controller needs: the log and temp directories, and 7z for archives
+pub fn common_checks(settings: &crate::configuration::ac_config::ACConfig) -> Vec<PreflightCheck> {
+    vec![
+        check_writable_directory("Log root", Path::new(&settings.log_root)),
+        check_writable_directory(
+            "Temp directory",
+            &Path::new(&settings.temp_root).join(&settings.temp_path),
+        ),
+        check_program("7z", "7z"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_passes_with_warnings() {
+        let report = PreflightReport::new(
+            "test",
+            vec![
+                PreflightCheck::ok("a", "fine"),
+                PreflightCheck::failed("b", "missing", "install it").optional(),
+            ],
+        );
+        assert!(report.passed);
+        assert_eq!(report.checks[1].status, CheckStatus::Warning);
+
+        let report = PreflightReport::new(
+            "test",
+            vec![PreflightCheck::failed("b", "missing", "install it")],
+        );
+        assert!(!report.passed);
+        assert!(report.to_string().contains("fix: install it"));
+    }
+
+    #[test]
+    fn test_writable_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("logs").join("nested");
+        let check = check_writable_directory("logs", &nested);
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert!(nested.is_dir());
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, b"").unwrap();
+        assert_eq!(
+            check_writable_directory("file", &file).status,
+            CheckStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_find_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("my_program");
+        std::fs::write(&program, b"").unwrap();
+        assert_eq!(find_program(program.to_str().unwrap()), Some(program));
+        assert!(find_program("definitely-not-an-installed-program").is_none());
+        assert_eq!(
+            check_program("missing", "definitely-not-an-installed-program").status,
+            CheckStatus::Failed
+        );
+    }
+}
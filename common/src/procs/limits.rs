@@ -0,0 +1,452 @@
+//! Resource limits for controller child processes. CPU affinity, the address space rlimit and the
+//! nice level are applied between fork and exec. Memory and CPU quotas can additionally be
+//! enforced through a cgroup v2 per process. The process is moved into its cgroup right after it
+//! is spawned, so it runs outside of it for a moment at start-up.
+
+use crate::utilities::portpicker::Port;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "swagger")]
+use utoipa::ToSchema;
+
+/// cgroup v2 default `cpu.max` period
+const CPU_PERIOD_USEC: u64 = 100_000;
+/// Report the address space limit as reached above this fraction of it
+const MEMORY_LIMIT_THRESHOLD: f64 = 0.95;
+
+pub type LimitMap = Arc<RwLock<HashMap<Port, LimitedProcess>>>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// CPUs the process may run on. Empty means no restriction.
+    pub cpu_affinity: Vec<usize>,
+    /// Address space limit, and `memory.max` when a cgroup is used
+    pub memory_limit_mb: Option<u64>,
+    pub nice: Option<i32>,
+    /// Maximum number of processes of the user (RLIMIT_NPROC)
+    pub max_processes: Option<u64>,
+    /// Maximum number of open file descriptors (RLIMIT_NOFILE)
+    pub max_open_files: Option<u64>,
+    /// Parent cgroup v2 directory. Each process gets its own child cgroup in it.
+    pub cgroup: Option<PathBuf>,
+    /// Number of CPUs worth of time per period, enforced through the cgroup
+    pub cpu_quota: Option<f32>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    fn memory_limit_bytes(&self) -> Option<u64> {
+        self.memory_limit_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Checks that the limits can be applied at all, before a process is spawned with them
+    pub fn validate(&self) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(cpu) = self
+            .cpu_affinity
+            .iter()
+            .find(|cpu| **cpu >= libc::CPU_SETSIZE as usize)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "CPU {cpu} is beyond the {} CPUs that affinity can be set for",
+                    libc::CPU_SETSIZE
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Applies the limits that have to be set from inside the child process. If they can't be
+    /// applied, spawning the command fails.
+    pub fn apply_to_command(&self, command: &mut async_process::Command) -> io::Result<()> {
+        self.validate()?;
+        #[cfg(unix)]
+        if !self.is_empty() {
+            use async_process::unix::CommandExt;
+            let limits = self.clone();
+            // Safety: the closure does not allocate and only calls async-signal-safe functions
+            unsafe {
+                command.pre_exec(move || limits.apply_in_child());
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = command;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn apply_in_child(&self) -> io::Result<()> {
+        unsafe {
+            #[cfg(target_os = "linux")]
+            if !self.cpu_affinity.is_empty() {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                for cpu in &self.cpu_affinity {
+                    libc::CPU_SET(*cpu, &mut set);
+                }
+                if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            let rlimits = [
+                (libc::RLIMIT_AS, self.memory_limit_bytes()),
+                (libc::RLIMIT_NPROC, self.max_processes),
+                (libc::RLIMIT_NOFILE, self.max_open_files),
+            ];
+            for (resource, value) in rlimits {
+                if let Some(value) = value {
+                    let limit = libc::rlimit {
+                        rlim_cur: value as libc::rlim_t,
+                        rlim_max: value as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+            }
+            if let Some(nice) = self.nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates and configures a child cgroup called `name`, if a parent cgroup is configured
+    fn create_cgroup(&self, name: &str) -> io::Result<Option<PathBuf>> {
+        let parent = match &self.cgroup {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let cgroup = parent.join(name);
+        std::fs::create_dir_all(&cgroup)?;
+        if let Some(bytes) = self.memory_limit_bytes() {
+            std::fs::write(cgroup.join("memory.max"), bytes.to_string())?;
+        }
+        if let Some(cpu_quota) = self.cpu_quota {
+            let quota = (cpu_quota as f64 * CPU_PERIOD_USEC as f64) as u64;
+            std::fs::write(cgroup.join("cpu.max"), format!("{quota} {CPU_PERIOD_USEC}"))?;
+        }
+        Ok(Some(cgroup))
+    }
+}
+
+#[cfg_attr(feature = "swagger", derive(ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LimitViolation {
+    /// A limit could not be applied, so the process runs without it
+    Setup { reason: String },
+    /// The process is about to hit its address space limit
+    MemoryLimit { limit_bytes: u64, used_bytes: u64 },
+    /// The cgroup reached `memory.max` and had to reclaim memory
+    CgroupMemoryMax { events: u64 },
+    /// Processes in the cgroup were killed for running out of memory
+    OomKill { count: u64 },
+    /// The cgroup used up its CPU quota
+    CpuThrottled { periods: u64, throttled_usec: u64 },
+    /// The process was killed by a signal that points at a limit or the sandbox
+    Killed { signal: i32, reason: String },
+    /// The data directory of a bot grew beyond its quota
+    DataQuota { quota_bytes: u64, used_bytes: u64 },
+}
+
+/// Describes why a process that exited by itself was killed, if a limit or the sandbox did it
+pub fn exit_violation(status: std::process::ExitStatus) -> Option<LimitViolation> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        let signal = status.signal()?;
+        let reason = match signal {
+            libc::SIGSYS => "Made a system call that the sandbox blocks",
+            libc::SIGKILL => "Killed, usually for running out of memory",
+            libc::SIGXCPU => "Exceeded the CPU time limit",
+            libc::SIGXFSZ => "Exceeded the file size limit",
+            _ => return None,
+        };
+        Some(LimitViolation::Killed {
+            signal,
+            reason: reason.to_string(),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// A started process and the limits it runs under
+#[derive(Debug, Clone)]
+pub struct LimitedProcess {
+    limits: ResourceLimits,
+    cgroup: Option<PathBuf>,
+    setup_errors: Vec<String>,
+    /// Violations noticed outside of the process while it runs, like its data quota
+    recorded: Vec<LimitViolation>,
+}
+
+impl LimitedProcess {
+    /// Moves the process `pid` into its own cgroup. Failures are kept and reported as violations,
+    /// since the process is already running at this point. Until then, the process is only held
+    /// to the limits applied by [`ResourceLimits::apply_to_command`].
+    pub fn new(limits: &ResourceLimits, name: &str, pid: u32) -> Self {
+        let mut setup_errors = Vec::new();
+        let cgroup = match limits.create_cgroup(name) {
+            Ok(Some(cgroup)) => {
+                match std::fs::write(cgroup.join("cgroup.procs"), pid.to_string()) {
+                    Ok(()) => Some(cgroup),
+                    Err(e) => {
+                        setup_errors.push(format!("Could not move process into cgroup: {e}"));
+                        remove_cgroup(&cgroup);
+                        None
+                    }
+                }
+            }
+            Ok(None) => None,
+            Err(e) => {
+                setup_errors.push(format!("Could not create cgroup {name}: {e}"));
+                None
+            }
+        };
+        Self {
+            limits: limits.clone(),
+            cgroup,
+            setup_errors,
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Keeps a violation noticed outside of the process. It replaces an earlier violation of the
+    /// same kind, so repeated measurements only keep the latest one.
+    pub fn record(&mut self, violation: LimitViolation) {
+        let kind = std::mem::discriminant(&violation);
+        self.recorded.retain(|v| std::mem::discriminant(v) != kind);
+        self.recorded.push(violation);
+    }
+
+    /// Violations kept with [`LimitedProcess::record`]
+    pub fn recorded(&self) -> &[LimitViolation] {
+        &self.recorded
+    }
+
+    /// Limits the process ran into so far. `virtual_memory` is its current address space size.
+    pub fn violations(&self, virtual_memory: u64) -> Vec<LimitViolation> {
+        let mut violations: Vec<LimitViolation> = self
+            .setup_errors
+            .iter()
+            .map(|reason| LimitViolation::Setup {
+                reason: reason.clone(),
+            })
+            .collect();
+        if let Some(limit_bytes) = self.limits.memory_limit_bytes() {
+            if virtual_memory as f64 >= limit_bytes as f64 * MEMORY_LIMIT_THRESHOLD {
+                violations.push(LimitViolation::MemoryLimit {
+                    limit_bytes,
+                    used_bytes: virtual_memory,
+                });
+            }
+        }
+        if let Some(cgroup) = &self.cgroup {
+            violations.extend(cgroup_violations(cgroup));
+        }
+        violations.extend(self.recorded.iter().cloned());
+        violations
+    }
+
+    /// Removes the cgroup of the process. Only succeeds once the process has exited.
+    pub fn cleanup(&self) {
+        if let Some(cgroup) = &self.cgroup {
+            remove_cgroup(cgroup);
+        }
+    }
+}
+
+fn remove_cgroup(cgroup: &Path) {
+    if let Err(e) = std::fs::remove_dir(cgroup) {
+        tracing::debug!("Could not remove cgroup {:?}: {}", cgroup, e);
+    }
+}
+
+fn cgroup_violations(cgroup: &Path) -> Vec<LimitViolation> {
+    let mut violations = Vec::new();
+    if let Ok(events) = std::fs::read_to_string(cgroup.join("memory.events")) {
+        let events = parse_flat_keyed(&events);
+        match events.get("max") {
+            Some(&events) if events > 0 => {
+                violations.push(LimitViolation::CgroupMemoryMax { events });
+            }
+            _ => {}
+        }
+        match events.get("oom_kill") {
+            Some(&count) if count > 0 => violations.push(LimitViolation::OomKill { count }),
+            _ => {}
+        }
+    }
+    if let Ok(stat) = std::fs::read_to_string(cgroup.join("cpu.stat")) {
+        let stat = parse_flat_keyed(&stat);
+        match stat.get("nr_throttled") {
+            Some(&periods) if periods > 0 => violations.push(LimitViolation::CpuThrottled {
+                periods,
+                throttled_usec: stat.get("throttled_usec").copied().unwrap_or_default(),
+            }),
+            _ => {}
+        }
+    }
+    violations
+}
+
+/// Parses cgroup files in the `key value` per line format
+fn parse_flat_keyed(contents: &str) -> HashMap<&str, u64> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            Some((key, value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_keeps_latest_violation_of_a_kind() {
+        let mut limited = LimitedProcess::new(&ResourceLimits::default(), "record", 0);
+        let quota = |used_bytes| LimitViolation::DataQuota {
+            quota_bytes: 10,
+            used_bytes,
+        };
+        limited.record(quota(11));
+        limited.record(quota(12));
+        assert_eq!(limited.recorded(), &[quota(12)]);
+        assert_eq!(limited.violations(0), vec![quota(12)]);
+    }
+
+    fn limits(cgroup: &Path) -> ResourceLimits {
+        ResourceLimits {
+            memory_limit_mb: Some(100),
+            cgroup: Some(cgroup.to_path_buf()),
+            cpu_quota: Some(1.5),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_violation() {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(
+            exit_violation(std::process::ExitStatus::from_raw(libc::SIGSYS)),
+            Some(LimitViolation::Killed {
+                signal: libc::SIGSYS,
+                reason: "Made a system call that the sandbox blocks".to_string()
+            })
+        );
+        assert_eq!(
+            exit_violation(std::process::ExitStatus::from_raw(libc::SIGSEGV)),
+            None
+        );
+        // Exit code 1
+        assert_eq!(
+            exit_violation(std::process::ExitStatus::from_raw(1 << 8)),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_validate_cpu_affinity() {
+        let mut limits = ResourceLimits {
+            cpu_affinity: vec![0, 3],
+            ..Default::default()
+        };
+        assert!(limits.validate().is_ok());
+        limits.cpu_affinity.push(libc::CPU_SETSIZE as usize);
+        assert_eq!(
+            limits.validate().unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_parse_flat_keyed() {
+        let parsed = parse_flat_keyed("low 0\nhigh 2\nmax 3\noom 1\noom_kill 1\n");
+        assert_eq!(parsed["high"], 2);
+        assert_eq!(parsed["oom_kill"], 1);
+        assert_eq!(parsed.len(), 5);
+    }
+
+    #[test]
+    fn test_create_cgroup() {
+        let parent = tempfile::tempdir().unwrap();
+        let cgroup = limits(parent.path())
+            .create_cgroup("sc2_9000")
+            .unwrap()
+            .unwrap();
+        assert_eq!(cgroup, parent.path().join("sc2_9000"));
+        assert_eq!(
+            std::fs::read_to_string(cgroup.join("memory.max")).unwrap(),
+            (100 * 1024 * 1024).to_string()
+        );
+        assert_eq!(
+            std::fs::read_to_string(cgroup.join("cpu.max")).unwrap(),
+            "150000 100000"
+        );
+        assert_eq!(ResourceLimits::default().create_cgroup("x").unwrap(), None);
+    }
+
+    #[test]
+    fn test_violations() {
+        let parent = tempfile::tempdir().unwrap();
+        let process = LimitedProcess::new(&limits(parent.path()), "sc2_9000", 1234);
+        assert!(process.setup_errors.is_empty());
+        assert!(process.violations(0).is_empty());
+
+        let cgroup = parent.path().join("sc2_9000");
+        std::fs::write(cgroup.join("memory.events"), "max 4\noom 1\noom_kill 1\n").unwrap();
+        std::fs::write(
+            cgroup.join("cpu.stat"),
+            "nr_throttled 7\nthrottled_usec 900\n",
+        )
+        .unwrap();
+        let limit_bytes = 100 * 1024 * 1024;
+        assert_eq!(
+            process.violations(limit_bytes),
+            vec![
+                LimitViolation::MemoryLimit {
+                    limit_bytes,
+                    used_bytes: limit_bytes
+                },
+                LimitViolation::CgroupMemoryMax { events: 4 },
+                LimitViolation::OomKill { count: 1 },
+                LimitViolation::CpuThrottled {
+                    periods: 7,
+                    throttled_usec: 900
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_setup_errors_are_reported() {
+        let parent = tempfile::tempdir().unwrap();
+        let file = parent.path().join("not_a_dir");
+        std::fs::write(&file, b"").unwrap();
+        let process = LimitedProcess::new(&limits(&file), "sc2_9000", 1234);
+        assert!(matches!(
+            process.violations(0).as_slice(),
+            [LimitViolation::Setup { .. }]
+        ));
+    }
+}
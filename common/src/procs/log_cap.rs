@@ -0,0 +1,218 @@
+use crate::configuration::ac_config::ACConfig;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const MARKER_PREFIX: &[u8] = b"\n[arenaclient: log truncated, ";
+const MARKER_SUFFIX: &[u8] = b" bytes omitted]\n";
+/// Longest possible marker, with a 20 digit byte count
+const MAX_MARKER_LEN: u64 = (MARKER_PREFIX.len() + 20 + MARKER_SUFFIX.len()) as u64;
+/// Least a [`CappedLog`] may go over its cap before it is shrunk
+const MIN_SLACK_BYTES: u64 = 64 * 1024;
+
+/// Size cap of a log file. Output beyond the cap is dropped from the middle, so the start and the
+/// end of the log are kept.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LogCap {
+    pub head_bytes: u64,
+    pub tail_bytes: u64,
+}
+
+impl LogCap {
+    /// Cap for bot logs, or `None` if they are unbounded
+    pub fn for_bots(settings: &ACConfig) -> Option<Self> {
+        settings.bot_log_max_size_kb.map(|max_kb| {
+            let max_bytes = max_kb.saturating_mul(1024);
+            let head_bytes = settings.bot_log_head_kb.saturating_mul(1024).min(max_bytes);
+            Self {
+                head_bytes,
+                tail_bytes: max_bytes - head_bytes,
+            }
+        })
+    }
+
+    /// How far a [`CappedLog`] may go over the cap. Shrinking rewrites the tail, so it is
+    /// batched up instead of happening on every write.
+    fn slack_bytes(&self) -> u64 {
+        (self.tail_bytes / 4).max(MIN_SLACK_BYTES)
+    }
+}
+
+/// A log file that is kept within its cap as it is written
+pub struct CappedLog {
+    file: File,
+    path: PathBuf,
+    cap: Option<LogCap>,
+    len: u64,
+}
+
+impl CappedLog {
+    /// Writes to `file` at `path`, which has to be opened in append mode. Without a cap the log
+    /// grows without bounds.
+    pub fn new(file: File, path: &Path, cap: Option<LogCap>) -> std::io::Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            cap,
+            len,
+        })
+    }
+
+    /// Appends `bytes` and shrinks the log once it is over its cap by more than the slack
+    pub fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.file.write_all(bytes)?;
+        self.len += bytes.len() as u64;
+        if let Some(cap) = &self.cap {
+            let limit = cap.head_bytes + MAX_MARKER_LEN + cap.tail_bytes;
+            if self.len > limit.saturating_add(cap.slack_bytes()) {
+                enforce_log_cap(&self.path, cap)?;
+                self.len = self.file.metadata()?.len();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn marker(omitted: u64) -> Vec<u8> {
+    [MARKER_PREFIX, omitted.to_string().as_bytes(), MARKER_SUFFIX].concat()
+}
+
+/// Parses a marker at the start of `bytes` into the number of omitted bytes and the marker length
+fn parse_marker(bytes: &[u8]) -> Option<(u64, u64)> {
+    let rest = bytes.strip_prefix(MARKER_PREFIX)?;
+    let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+    if !rest[digits..].starts_with(MARKER_SUFFIX) {
+        return None;
+    }
+    let omitted = std::str::from_utf8(&rest[..digits]).ok()?.parse().ok()?;
+    Some((
+        omitted,
+        (MARKER_PREFIX.len() + digits + MARKER_SUFFIX.len()) as u64,
+    ))
+}
+
+/// Shrinks the log at `path` to `cap` by replacing everything between the head and the tail with
+/// a truncation marker. Can be called repeatedly while the log is written to, as long as the
+/// writer opened it in append mode. Output written while the log is being shrunk may be lost,
+/// unless the writer is the one shrinking it like [`CappedLog`] does.
+///
+/// Returns the total number of bytes omitted from the log so far.
+pub fn enforce_log_cap(path: &Path, cap: &LogCap) -> std::io::Result<u64> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let len = file.metadata()?.len();
+    if len <= cap.head_bytes {
+        return Ok(0);
+    }
+    file.seek(SeekFrom::Start(cap.head_bytes))?;
+    let mut existing_marker = Vec::new();
+    (&mut file)
+        .take(MAX_MARKER_LEN)
+        .read_to_end(&mut existing_marker)?;
+    let (previously_omitted, marker_len) = parse_marker(&existing_marker).unwrap_or((0, 0));
+    if len <= cap.head_bytes + MAX_MARKER_LEN + cap.tail_bytes {
+        return Ok(previously_omitted);
+    }
+
+    let kept_start = cap.head_bytes + marker_len;
+    let tail_start = len.saturating_sub(cap.tail_bytes).max(kept_start);
+    file.seek(SeekFrom::Start(tail_start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let omitted = previously_omitted + (tail_start - kept_start);
+
+    file.set_len(cap.head_bytes)?;
+    file.seek(SeekFrom::Start(cap.head_bytes))?;
+    file.write_all(&marker(omitted))?;
+    file.write_all(&tail)?;
+    Ok(omitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_round_trip() {
+        let bytes = [marker(1234).as_slice(), b"rest"].concat();
+        assert_eq!(
+            parse_marker(&bytes),
+            Some((1234, marker(1234).len() as u64))
+        );
+        assert_eq!(parse_marker(b"normal output"), None);
+    }
+
+    #[test]
+    fn test_enforce_log_cap_keeps_head_and_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stderr.log");
+        let cap = LogCap {
+            head_bytes: 10,
+            tail_bytes: 10,
+        };
+        std::fs::write(&path, b"0123456789").unwrap();
+        assert_eq!(enforce_log_cap(&path, &cap).unwrap(), 0);
+
+        let content = [b"0123456789".as_slice(), &[b'x'; 100], b"abcdefghij"].concat();
+        std::fs::write(&path, content).unwrap();
+        assert_eq!(enforce_log_cap(&path, &cap).unwrap(), 100);
+        let capped = std::fs::read(&path).unwrap();
+        assert_eq!(
+            capped,
+            [b"0123456789".as_slice(), &marker(100), b"abcdefghij"].concat()
+        );
+
+        // Within the cap, so nothing changes
+        assert_eq!(enforce_log_cap(&path, &cap).unwrap(), 100);
+        assert_eq!(std::fs::read(&path).unwrap(), capped);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[b'y'; 100]).unwrap();
+        file.write_all(b"klmnopqrst").unwrap();
+        assert_eq!(enforce_log_cap(&path, &cap).unwrap(), 210);
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            [b"0123456789".as_slice(), &marker(210), b"klmnopqrst"].concat()
+        );
+    }
+
+    #[test]
+    fn test_capped_log_shrinks_while_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stdout.log");
+        let cap = LogCap {
+            head_bytes: 10,
+            tail_bytes: 10,
+        };
+        let file = crate::procs::create_log_file(&path).unwrap();
+        let mut log = CappedLog::new(file, &path, Some(cap)).unwrap();
+        log.write_all(b"0123456789").unwrap();
+        let line = [b'x'; 1023];
+        let mut written = 10;
+        while written <= 10 + MAX_MARKER_LEN + 10 + MIN_SLACK_BYTES {
+            log.write_all(&line).unwrap();
+            log.write_all(b"\n").unwrap();
+            written += 1024;
+        }
+        let content = std::fs::read(&path).unwrap();
+        assert!((content.len() as u64) < 10 + MAX_MARKER_LEN + 10);
+        assert!(content.starts_with(b"0123456789\n[arenaclient: log truncated, "));
+        assert!(content.ends_with(b"xxxxxxxxx\n"));
+
+        // Writes go on at the new end of the log
+        log.write_all(b"end").unwrap();
+        assert!(std::fs::read(&path).unwrap().ends_with(b"x\nend"));
+    }
+
+    #[test]
+    fn test_bot_log_cap_saturates() {
+        let settings = ACConfig {
+            bot_log_max_size_kb: Some(u64::MAX),
+            bot_log_head_kb: u64::MAX,
+            ..Default::default()
+        };
+        let cap = LogCap::for_bots(&settings).unwrap();
+        assert_eq!(cap.head_bytes, u64::MAX);
+        assert_eq!(cap.tail_bytes, 0);
+    }
+}
@@ -1,15 +1,22 @@
-pub mod tcp_port;
+pub mod limits;
+pub mod log_cap;
 
+use crate::models::bot_controller::BotLog;
 use std::fs::{File, OpenOptions};
-use std::path::PathBuf;
+use std::path::Path;
 
-pub fn create_stdout_and_stderr_files(log_file_path: &PathBuf) -> std::io::Result<(File, File)> {
-    let stdout_file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(log_file_path)?;
-
-    let stderr_file = stdout_file.try_clone()?;
+/// Creates empty `stdout.log` and `stderr.log` files in `log_dir`.
+///
+/// The files are opened in append mode, so the process keeps writing at the end when a log is
+/// shrunk by [`log_cap::enforce_log_cap`].
+pub fn create_stdout_and_stderr_files(log_dir: &Path) -> std::io::Result<(File, File)> {
+    let stdout_file = create_log_file(&log_dir.join(BotLog::Stdout.file_name()))?;
+    let stderr_file = create_log_file(&log_dir.join(BotLog::Stderr.file_name()))?;
     Ok((stdout_file, stderr_file))
 }
+
+/// Creates an empty log file, opened in append mode
+pub fn create_log_file(path: &Path) -> std::io::Result<File> {
+    File::create(path)?;
+    OpenOptions::new().append(true).open(path)
+}
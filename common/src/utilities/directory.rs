@@ -1,3 +1,11 @@
+use std::io;
+use std::path::Path;
+
+/// Mode for copied directories and executables
+const DIR_MODE: u32 = 0o755;
+/// Mode for copied files without any execute bit
+const FILE_MODE: u32 = 0o644;
+
 pub async fn ensure_directory_structure(base: &str, folder: &str) -> std::io::Result<()> {
     let base_dir = std::path::Path::new(base);
     let folder_dir = std::path::Path::new(folder);
@@ -12,3 +20,382 @@ pub async fn ensure_directory_structure(base: &str, folder: &str) -> std::io::Re
     }
     Ok(())
 }
+
+/// What to do with symlinks when syncing a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Recreate them with the same target
+    Keep,
+    /// Leave them out, so the copy can't point anywhere outside of it. The source itself must
+    /// not be a symlink either.
+    Skip,
+}
+
+/// Removes everything inside `dir`, but keeps `dir` itself along with its owner and permissions.
+/// Symlinks are removed, not followed.
+pub fn clear_directory(dir: &Path) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(entry.path())?;
+        } else {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the contents of `dest` with a copy of `src`, creating `dest` if needed.
+///
+/// Files are copied rather than hard-linked, so whoever owns `dest` can't change the originals.
+/// Copies are owned by the owner of `dest` and get fresh permissions: 0o755 for directories and
+/// executables, 0o644 for everything else.
+pub fn sync_directory(src: &Path, dest: &Path, symlinks: Symlinks) -> io::Result<()> {
+    if symlinks == Symlinks::Skip {
+        ensure_real_directory(src)?;
+    }
+    std::fs::create_dir_all(dest)?;
+    clear_directory(dest)?;
+    let owner = Owner::of(dest)?;
+    copy_contents(src, dest, symlinks, &owner)
+}
+
+/// Fails unless `dir` is a directory itself, rather than a symlink to one
+fn ensure_real_directory(dir: &Path) -> io::Result<()> {
+    if std::fs::symlink_metadata(dir)?.is_dir() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a directory", dir.display()),
+        ))
+    }
+}
+
+/// Total size of the files in `dir` and its subdirectories. Symlinks are not followed, and `dir`
+/// itself must not be one.
+pub fn directory_size(dir: &Path) -> io::Result<u64> {
+    ensure_real_directory(dir)?;
+    contents_size(dir)
+}
+
+fn contents_size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += contents_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Copies the files in `src` to `dest` in path order, leaving out each file that would take the
+/// copy beyond `max_bytes`. Symlinks are left out as well, and `src` itself must not be one.
+///
+/// Returns the size of the files that were left out.
+pub fn copy_directory_within(src: &Path, dest: &Path, max_bytes: u64) -> io::Result<u64> {
+    ensure_real_directory(src)?;
+    std::fs::create_dir_all(dest)?;
+    let mut remaining = max_bytes;
+    copy_contents_within(src, dest, &mut remaining)
+}
+
+fn copy_contents_within(src: &Path, dest: &Path, remaining: &mut u64) -> io::Result<u64> {
+    let mut entries = std::fs::read_dir(src)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut omitted = 0;
+    for entry in entries {
+        let file_type = entry.file_type()?;
+        let target = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            omitted += copy_contents_within(&entry.path(), &target, remaining)?;
+        } else if file_type.is_file() {
+            let len = entry.metadata()?.len();
+            if len <= *remaining {
+                std::fs::copy(entry.path(), &target)?;
+                *remaining -= len;
+            } else {
+                omitted += len;
+            }
+        }
+    }
+    Ok(omitted)
+}
+
+fn copy_contents(src: &Path, dest: &Path, symlinks: Symlinks, owner: &Owner) -> io::Result<()> {
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            std::fs::create_dir(&target)?;
+            copy_contents(&entry.path(), &target, symlinks, owner)?;
+            owner.apply(&target)?;
+            set_mode(&target, DIR_MODE)?;
+        } else if file_type.is_symlink() {
+            if symlinks == Symlinks::Keep {
+                copy_symlink(&entry.path(), &target)?;
+                owner.apply(&target)?;
+            }
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+            owner.apply(&target)?;
+            set_mode(&target, copied_file_mode(&entry.metadata()?))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(src)?, dest)
+}
+
+#[cfg(windows)]
+fn copy_symlink(src: &Path, dest: &Path) -> io::Result<()> {
+    let link = std::fs::read_link(src)?;
+    if std::fs::metadata(src)?.is_dir() {
+        std::os::windows::fs::symlink_dir(link, dest)
+    } else {
+        std::os::windows::fs::symlink_file(link, dest)
+    }
+}
+
+#[cfg(unix)]
+fn copied_file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    if metadata.permissions().mode() & 0o111 != 0 {
+        DIR_MODE
+    } else {
+        FILE_MODE
+    }
+}
+
+#[cfg(not(unix))]
+fn copied_file_mode(_metadata: &std::fs::Metadata) -> u32 {
+    FILE_MODE
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Owner that copied entries are handed over to
+struct Owner {
+    #[cfg(unix)]
+    uid: u32,
+    #[cfg(unix)]
+    gid: u32,
+}
+
+impl Owner {
+    #[cfg(unix)]
+    fn of(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn of(_path: &Path) -> io::Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Changes the owner of `path` without following symlinks. Entries that already have the right
+    /// owner are left alone, so this only needs privileges when the owner actually differs.
+    #[cfg(unix)]
+    fn apply(&self, path: &Path) -> io::Result<()> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = std::fs::symlink_metadata(path)?;
+        if metadata.uid() != self.uid || metadata.gid() != self.gid {
+            std::os::unix::fs::lchown(path, Some(self.uid), Some(self.gid))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn apply(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_sync_directory_copies_recursively() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        write(&src.path().join("run.py"), "print()");
+        write(&src.path().join("lib").join("nested").join("a.txt"), "a");
+        std::fs::create_dir(src.path().join("data")).unwrap();
+
+        sync_directory(src.path(), dest.path(), Symlinks::Keep).unwrap();
+
+        let copied = std::fs::read_to_string(dest.path().join("lib/nested/a.txt")).unwrap();
+        assert_eq!(copied, "a");
+        assert!(dest.path().join("run.py").is_file());
+        assert!(dest.path().join("data").is_dir());
+    }
+
+    #[test]
+    fn test_sync_directory_wipes_leftovers() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        write(&src.path().join("bot"), "new");
+        write(&dest.path().join("bot"), "old");
+        write(&dest.path().join("old_dir").join("leftover"), "old");
+        write(&dest.path().join("leftover"), "old");
+
+        sync_directory(src.path(), dest.path(), Symlinks::Keep).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("bot")).unwrap(),
+            "new"
+        );
+        assert!(!dest.path().join("old_dir").exists());
+        assert!(!dest.path().join("leftover").exists());
+    }
+
+    #[test]
+    fn test_sync_directory_creates_missing_destination() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let dest = dest.path().join("missing").join("data");
+        write(&src.path().join("file"), "contents");
+
+        sync_directory(src.path(), &dest, Symlinks::Skip).unwrap();
+
+        assert!(dest.join("file").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sync_directory_resets_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        write(&src.path().join("bot"), "");
+        write(&src.path().join("secret"), "");
+        set_mode(&src.path().join("bot"), 0o700).unwrap();
+        set_mode(&src.path().join("secret"), 0o600).unwrap();
+
+        sync_directory(src.path(), dest.path(), Symlinks::Keep).unwrap();
+
+        let mode = |name: &str| {
+            let metadata = std::fs::metadata(dest.path().join(name)).unwrap();
+            metadata.permissions().mode() & 0o777
+        };
+        assert_eq!(mode("bot"), 0o755);
+        assert_eq!(mode("secret"), 0o644);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sync_directory_symlinks() {
+        let src = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        write(&outside.path().join("secret"), "secret");
+        std::os::unix::fs::symlink(outside.path().join("secret"), src.path().join("link")).unwrap();
+
+        let kept = tempfile::tempdir().unwrap();
+        sync_directory(src.path(), kept.path(), Symlinks::Keep).unwrap();
+        let link = std::fs::read_link(kept.path().join("link")).unwrap();
+        assert_eq!(link, outside.path().join("secret"));
+
+        let skipped = tempfile::tempdir().unwrap();
+        sync_directory(src.path(), skipped.path(), Symlinks::Skip).unwrap();
+        assert!(std::fs::symlink_metadata(skipped.path().join("link")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sync_directory_refuses_symlinked_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        write(&outside.path().join("secret"), "secret");
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(outside.path(), &link).unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        write(&dest.path().join("previous"), "previous");
+        assert!(sync_directory(&link, dest.path(), Symlinks::Skip).is_err());
+        assert!(!dest.path().join("secret").exists());
+        assert!(dest.path().join("previous").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clear_directory_does_not_follow_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        write(&outside.path().join("keep"), "keep");
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+        clear_directory(dir.path()).unwrap();
+
+        assert!(dir.path().read_dir().unwrap().next().is_none());
+        assert!(outside.path().join("keep").is_file());
+    }
+
+    #[test]
+    fn test_directory_size() {
+        let dir = tempfile::tempdir().unwrap();
+        write(&dir.path().join("a"), "12345");
+        write(&dir.path().join("nested").join("b"), "123");
+        assert_eq!(directory_size(dir.path()).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_copy_directory_within() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        write(&src.path().join("a"), "12345");
+        write(&src.path().join("b").join("big"), "1234567890");
+        write(&src.path().join("c"), "123");
+
+        let omitted = copy_directory_within(src.path(), dest.path(), 9).unwrap();
+
+        assert_eq!(omitted, 10);
+        assert!(dest.path().join("a").is_file());
+        assert!(dest.path().join("b").is_dir());
+        assert!(!dest.path().join("b").join("big").exists());
+        assert!(dest.path().join("c").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_size_and_copy_refuse_symlinked_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        write(&outside.path().join("secret"), "secret");
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(outside.path(), &link).unwrap();
+
+        assert!(directory_size(&link).is_err());
+        let dest = dir.path().join("dest");
+        assert!(copy_directory_within(&link, &dest, 1024).is_err());
+        assert!(!dest.join("secret").exists());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod directory;
 pub mod portpicker;
+pub mod scrub;
 pub mod zip_utils;
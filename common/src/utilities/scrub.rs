@@ -0,0 +1,79 @@
+use std::path::Path;
+
+/// What secrets are replaced with
+const REDACTED: &[u8] = b"[REDACTED]";
+
+/// Replaces every occurrence of each secret in `content`. Empty secrets are ignored.
+pub fn scrub_secrets(content: &[u8], secrets: &[String]) -> Vec<u8> {
+    let mut scrubbed = content.to_vec();
+    for secret in secrets.iter().map(String::as_bytes) {
+        if secret.is_empty() {
+            continue;
+        }
+        let mut result = Vec::with_capacity(scrubbed.len());
+        let mut rest = scrubbed.as_slice();
+        while let Some(position) = rest.windows(secret.len()).position(|w| w == secret) {
+            result.extend_from_slice(&rest[..position]);
+            result.extend_from_slice(REDACTED);
+            rest = &rest[position + secret.len()..];
+        }
+        result.extend_from_slice(rest);
+        scrubbed = result;
+    }
+    scrubbed
+}
+
+/// Scrubs the secrets from every file directly in `dir`
+pub fn scrub_directory(dir: &Path, secrets: &[String]) -> std::io::Result<()> {
+    if secrets.iter().all(String::is_empty) {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let content = std::fs::read(&path)?;
+        let scrubbed = scrub_secrets(&content, secrets);
+        if scrubbed != content {
+            std::fs::write(&path, scrubbed)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_secrets() {
+        let secrets = vec![
+            "token123".to_string(),
+            String::new(),
+            "/home/bot1".to_string(),
+        ];
+        let content = b"Auth token123 at /home/bot1/data, token123\n";
+        assert_eq!(
+            scrub_secrets(content, &secrets),
+            b"Auth [REDACTED] at [REDACTED]/data, [REDACTED]\n".to_vec()
+        );
+        assert_eq!(scrub_secrets(content, &[]), content.to_vec());
+    }
+
+    #[test]
+    fn test_scrub_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("stdout.log"), "token123\n").unwrap();
+        std::fs::write(dir.path().join("stderr.log"), "nothing secret\n").unwrap();
+        scrub_directory(dir.path(), &["token123".to_string()]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("stdout.log")).unwrap(),
+            "[REDACTED]\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("stderr.log")).unwrap(),
+            "nothing secret\n"
+        );
+    }
+}
@@ -188,7 +188,7 @@ async fn delete_old_jobs(
     }
     Ok(())
 }
-fn load_arenaclient_details(path: &str) -> anyhow::Result<Vec<Arenaclient>> {
+pub(crate) fn load_arenaclient_details(path: &str) -> anyhow::Result<Vec<Arenaclient>> {
     let file = std::fs::File::open(path)?;
     let reader = BufReader::new(file);
     Ok(serde_json::from_reader(reader)?)
@@ -2,6 +2,7 @@ mod arenaclient;
 // mod old;
 mod k8s_config;
 mod k8s_processor;
+mod preflight;
 mod profile;
 mod state;
 // #[cfg(feature = "swagger")]
@@ -10,14 +11,17 @@ mod state;
 // #[cfg(feature = "swagger")]
 // use crate::docs::ApiDoc;
 use crate::k8s_processor::process;
+use crate::preflight::{configuration_failed, preflight};
 use axum::http::Request;
 use axum::response::Response;
 use axum::routing::get;
 use axum::Router;
 use axum::{error_handling::HandleErrorLayer, http::StatusCode};
+use clap::{arg, command};
 use common::api::health;
 use common::configuration::get_host_url;
 use common::logging::init_logging;
+use common::preflight::exit_with_report;
 use config::{Config, FileFormat};
 use parking_lot::RwLock;
 use state::AppState;
@@ -34,6 +38,11 @@ static PREFIX: &str = "ACK8S";
 
 #[tokio::main]
 async fn main() {
+    let matches = command!()
+        .arg(arg!(--check "Check the environment, print a report and exit"))
+        .get_matches();
+    let check = matches.get_flag("check");
+
     let host_url = get_host_url(PREFIX, 8085);
 
     let env_log = std::env::var("RUST_LOG")
@@ -44,10 +53,17 @@ async fn main() {
     if full_path.exists() {
         tokio::fs::remove_file(full_path).await.unwrap();
     }
-    let mut settings = setup_k8s_config();
+    let mut settings = match setup_k8s_config() {
+        Ok(settings) => settings,
+        Err(e) if check => exit_with_report(&configuration_failed(e)),
+        Err(e) => panic!("Could not load config: {e}"),
+    };
     if settings.version.is_none() {
         settings.version = Some(format!("v{VERSION}"));
     }
+    if check {
+        exit_with_report(&preflight::run_checks(&settings).await);
+    }
 
     let (non_blocking_stdout, _guard) = tracing_appender::non_blocking(std::io::stdout());
     let non_blocking_file = tracing_appender::rolling::never(&log_path, log_file);
@@ -55,7 +71,9 @@ async fn main() {
 
     info!("Running version: {:?}", VERSION);
 
-    let state = AppState {};
+    let state = AppState {
+        settings: settings.clone(),
+    };
     let app_state = Arc::new(RwLock::new(state));
 
     #[allow(unused_mut)]
@@ -81,6 +99,7 @@ async fn main() {
                 }),
         )
         .route("/health", get(health))
+        .route("/preflight", get(preflight))
         .layer(
             ServiceBuilder::new()
                 .layer(HandleErrorLayer::new(|error: BoxError| async move {
@@ -146,15 +165,13 @@ async fn shutdown_signal() {
     debug!("signal received, starting graceful shutdown");
 }
 
-fn setup_k8s_config() -> k8s_config::K8sConfig {
+fn setup_k8s_config() -> Result<k8s_config::K8sConfig, config::ConfigError> {
     let default_config = include_str!("../configs/default_config.toml");
     Config::builder()
         .add_source(config::File::from_str(default_config, FileFormat::Toml).required(true))
         .add_source(config::File::new("config.toml", FileFormat::Toml).required(false))
         .add_source(config::File::new("config.json", FileFormat::Json).required(false))
         .add_source(config::Environment::default().prefix(PREFIX))
-        .build()
-        .expect("Could not load config")
+        .build()?
         .try_deserialize::<k8s_config::K8sConfig>()
-        .expect("Could not deserialize config")
 }
@@ -0,0 +1,98 @@
+use crate::k8s_config::K8sConfig;
+use crate::k8s_processor::load_arenaclient_details;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::Json;
+use common::preflight::{PreflightCheck, PreflightReport};
+use k8s_openapi::api::batch::v1::Job;
+use kube::api::{Api, ListParams};
+use kube::Client;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+pub async fn run_checks(settings: &K8sConfig) -> PreflightReport {
+    let checks = vec![
+        check_arenaclients(settings),
+        check_website_url(settings),
+        check_cluster(settings).await,
+    ];
+    PreflightReport::new("k8s_controller", checks)
+}
+
+/// Report for a configuration that could not be loaded, so nothing else can be checked
+pub fn configuration_failed(error: impl std::fmt::Display) -> PreflightReport {
+    PreflightReport::new(
+        "k8s_controller",
+        vec![PreflightCheck::failed(
+            "Configuration",
+            format!("Could not load the configuration: {error}"),
+            "Fix config.toml, config.json or the ACK8S_ environment variables",
+        )],
+    )
+}
+
+pub async fn preflight(State(state): State<Arc<RwLock<AppState>>>) -> Json<PreflightReport> {
+    let settings = state.read().settings.clone();
+    Json(run_checks(&settings).await)
+}
+
+fn check_arenaclients(settings: &K8sConfig) -> PreflightCheck {
+    let path = &settings.arenaclients_json_path;
+    match load_arenaclient_details(path) {
+        Ok(arenaclients) if arenaclients.is_empty() => PreflightCheck::failed(
+            "Arenaclients",
+            format!("No arenaclients in {path}"),
+            "Add the name and API token of each arenaclient to the file",
+        ),
+        Ok(arenaclients) => PreflightCheck::ok(
+            "Arenaclients",
+            format!("{} arenaclients in {path}", arenaclients.len()),
+        ),
+        Err(e) => PreflightCheck::failed(
+            "Arenaclients",
+            format!("Could not load {path}: {e}"),
+            "Point ARENACLIENTS_JSON_PATH at a JSON list of arenaclients",
+        ),
+    }
+}
+
+fn check_website_url(settings: &K8sConfig) -> PreflightCheck {
+    if settings.website_url.is_empty() {
+        PreflightCheck::failed(
+            "Website URL",
+            "WEBSITE_URL is empty",
+            "Set WEBSITE_URL to the AI Arena website matches are fetched from",
+        )
+    } else {
+        PreflightCheck::ok("Website URL", settings.website_url.clone())
+    }
+}
+
+/// The cluster is reachable and jobs can be listed in the configured namespace
+async fn check_cluster(settings: &K8sConfig) -> PreflightCheck {
+    let client = match Client::try_default().await {
+        Ok(client) => client,
+        Err(e) => {
+            return PreflightCheck::failed(
+                "Kubernetes cluster",
+                format!("Could not create a client: {e}"),
+                "Run inside the cluster or provide a kubeconfig",
+            )
+        }
+    };
+    let jobs: Api<Job> = Api::namespaced(client, &settings.namespace);
+    match jobs.list(&ListParams::default().limit(1)).await {
+        Ok(_) => PreflightCheck::ok(
+            "Kubernetes cluster",
+            format!("Jobs can be listed in namespace {:?}", settings.namespace),
+        ),
+        Err(e) => PreflightCheck::failed(
+            "Kubernetes cluster",
+            format!(
+                "Could not list jobs in namespace {:?}: {e}",
+                settings.namespace
+            ),
+            "Check NAMESPACE and the RBAC permissions of the service account",
+        ),
+    }
+}
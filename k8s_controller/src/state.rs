@@ -1,2 +1,6 @@
+use crate::k8s_config::K8sConfig;
+
 #[derive(Debug, Clone)]
-pub struct AppState {}
+pub struct AppState {
+    pub settings: K8sConfig,
+}
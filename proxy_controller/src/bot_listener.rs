@@ -0,0 +1,79 @@
+//! Per-bot proxy listeners. Each bot gets a port of its own for a match, so the proxy knows
+//! which player a connection belongs to from the port it came in on.
+
+use crate::state::ProxyState;
+use crate::ws_routes::bot_websocket_handler;
+use axum::routing::get;
+use axum::Router;
+use common::utilities::portpicker::Port;
+use common::PlayerNum;
+use parking_lot::RwLock;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+#[derive(Clone)]
+pub struct BotListenerState {
+    pub proxy_state: Arc<RwLock<ProxyState>>,
+    pub player_num: PlayerNum,
+}
+
+/// A listener serving `/sc2api` for one bot. It stops accepting connections when dropped.
+#[derive(Debug)]
+pub struct BotListener {
+    port: Port,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl BotListener {
+    /// Starts listening on `port`, or on a free port if `port` is 0
+    pub fn start(
+        proxy_state: Arc<RwLock<ProxyState>>,
+        player_num: PlayerNum,
+        ip: IpAddr,
+        port: Port,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::new(ip, port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let server = axum::Server::from_tcp(listener)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        let app = Router::new()
+            .route("/sc2api", get(bot_websocket_handler))
+            .with_state(BotListenerState {
+                proxy_state,
+                player_num,
+            });
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        let server = server
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async {
+                let _ = shutdown_signal.await;
+            });
+        debug!("Listening for bot {:?} on port {}", player_num, port);
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!("Bot {:?} listener error: {}", player_num, e);
+            }
+        });
+        Ok(Self {
+            port,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub const fn port(&self) -> Port {
+        self.port
+    }
+}
+
+impl Drop for BotListener {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
@@ -1 +1,17 @@
+#[cfg(feature = "swagger")]
+use utoipa::OpenApi;
 
+#[cfg(feature = "swagger")]
+#[cfg_attr(feature = "swagger", derive(OpenApi))]
+#[cfg_attr(
+    feature = "swagger",
+    openapi(
+        paths(crate::routes::preflight, common::api::health),
+        components(schemas(
+            common::preflight::PreflightReport,
+            common::preflight::PreflightCheck,
+            common::preflight::CheckStatus
+        ))
+    )
+)]
+pub struct ApiDoc;
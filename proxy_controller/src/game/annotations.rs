@@ -0,0 +1,242 @@
+use crate::game::game_config::GameConfig;
+use crate::game::game_result::GameResult;
+use common::PlayerNum;
+use indexmap::IndexSet;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const TAG_PREFIX: &str = "Tag:";
+const METRIC_PREFIX: &str = "Metric:";
+const EVENT_PREFIX: &str = "Event:";
+const BUILD_PREFIX: &str = "Build:";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnnotationKind {
+    /// `Metric:<name>=<value>`
+    Metric { name: String, value: f64 },
+    /// `Event:<message>`
+    Event { message: String },
+    /// `Build:<item>`
+    Build { item: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub game_loop: u32,
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerAnnotations {
+    pub annotations: Vec<Annotation>,
+    /// Annotations and tags that were rejected because they were malformed or over the limits
+    pub dropped: u32,
+}
+
+/// Collects tags and annotations that bots send as chat messages
+#[derive(Debug)]
+pub struct AnnotationCollector {
+    max_count: usize,
+    max_length: usize,
+    tags: IndexSet<String>,
+    annotations: PlayerAnnotations,
+}
+
+impl AnnotationCollector {
+    pub fn new(max_count: usize, max_length: usize) -> Self {
+        Self {
+            max_count,
+            max_length,
+            tags: IndexSet::with_capacity(10),
+            annotations: PlayerAnnotations::default(),
+        }
+    }
+
+    /// Records a chat message sent at `game_loop`. Messages without a known prefix are ignored.
+    pub fn add_message(&mut self, message: &str, game_loop: u32) {
+        if let Some(tag) = message.strip_prefix(TAG_PREFIX) {
+            if self.tags.contains(tag) {
+                return;
+            }
+            if tag.len() > self.max_length || self.tags.len() >= self.max_count {
+                self.annotations.dropped += 1;
+            } else {
+                self.tags.insert(tag.to_string());
+            }
+            return;
+        }
+        let kind = match parse_annotation(message) {
+            Some(kind) => kind,
+            None => {
+                if [METRIC_PREFIX, EVENT_PREFIX, BUILD_PREFIX]
+                    .iter()
+                    .any(|prefix| message.starts_with(prefix))
+                {
+                    self.annotations.dropped += 1;
+                }
+                return;
+            }
+        };
+        if message.len() > self.max_length || self.annotations.annotations.len() >= self.max_count {
+            self.annotations.dropped += 1;
+            return;
+        }
+        self.annotations
+            .annotations
+            .push(Annotation { game_loop, kind });
+    }
+
+    pub fn finish(self) -> (IndexSet<String>, PlayerAnnotations) {
+        (self.tags, self.annotations)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerMatchAnnotations {
+    pub player_num: PlayerNum,
+    pub name: String,
+    #[serde(flatten)]
+    pub annotations: PlayerAnnotations,
+}
+
+/// Contents of the `annotations.json` file included with the match results
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchAnnotations {
+    pub match_id: u32,
+    pub players: Vec<PlayerMatchAnnotations>,
+}
+
+impl MatchAnnotations {
+    pub fn new(config: &GameConfig, game_result: &GameResult) -> Self {
+        let players = [PlayerNum::One, PlayerNum::Two]
+            .into_iter()
+            .map(|player_num| PlayerMatchAnnotations {
+                player_num,
+                name: config.players[&player_num].name.clone(),
+                annotations: game_result
+                    .player_result(player_num)
+                    .and_then(|r| r.annotations.clone())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            match_id: config.match_id,
+            players,
+        }
+    }
+
+    /// Writes `annotations.json` to `directory`
+    pub async fn write_to(&self, directory: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        tokio::fs::write(directory.join("annotations.json"), json).await
+    }
+}
+
+fn parse_annotation(message: &str) -> Option<AnnotationKind> {
+    if let Some(metric) = message.strip_prefix(METRIC_PREFIX) {
+        let (name, value) = metric.split_once('=')?;
+        let name = name.trim();
+        let value = value.trim().parse::<f64>().ok().filter(|v| v.is_finite())?;
+        (!name.is_empty()).then(|| AnnotationKind::Metric {
+            name: name.to_string(),
+            value,
+        })
+    } else if let Some(event) = message.strip_prefix(EVENT_PREFIX) {
+        let message = event.trim();
+        (!message.is_empty()).then(|| AnnotationKind::Event {
+            message: message.to_string(),
+        })
+    } else if let Some(build) = message.strip_prefix(BUILD_PREFIX) {
+        let item = build.trim();
+        (!item.is_empty()).then(|| AnnotationKind::Build {
+            item: item.to_string(),
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotations() {
+        let mut collector = AnnotationCollector::new(10, 100);
+        collector.add_message("Metric:apm=123.5", 10);
+        collector.add_message("Event:expanded to natural", 20);
+        collector.add_message("Build: Pylon", 30);
+        collector.add_message("gl hf", 40);
+        let (tags, annotations) = collector.finish();
+        assert!(tags.is_empty());
+        assert_eq!(annotations.dropped, 0);
+        assert_eq!(
+            annotations.annotations,
+            vec![
+                Annotation {
+                    game_loop: 10,
+                    kind: AnnotationKind::Metric {
+                        name: "apm".to_string(),
+                        value: 123.5
+                    }
+                },
+                Annotation {
+                    game_loop: 20,
+                    kind: AnnotationKind::Event {
+                        message: "expanded to natural".to_string()
+                    }
+                },
+                Annotation {
+                    game_loop: 30,
+                    kind: AnnotationKind::Build {
+                        item: "Pylon".to_string()
+                    }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tags_are_deduplicated() {
+        let mut collector = AnnotationCollector::new(10, 100);
+        collector.add_message("Tag:proxy", 1);
+        collector.add_message("Tag:proxy", 2);
+        collector.add_message("Tag:cannon_rush", 3);
+        let (tags, annotations) = collector.finish();
+        assert_eq!(
+            tags.into_iter().collect::<Vec<_>>(),
+            vec!["proxy".to_string(), "cannon_rush".to_string()]
+        );
+        assert_eq!(annotations.dropped, 0);
+    }
+
+    #[test]
+    fn test_malformed_annotations_are_dropped() {
+        let mut collector = AnnotationCollector::new(10, 100);
+        collector.add_message("Metric:apm", 1);
+        collector.add_message("Metric:=5", 1);
+        collector.add_message("Metric:apm=NaN", 1);
+        collector.add_message("Event:", 1);
+        let (_, annotations) = collector.finish();
+        assert!(annotations.annotations.is_empty());
+        assert_eq!(annotations.dropped, 4);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut collector = AnnotationCollector::new(2, 20);
+        collector.add_message("Event:one", 1);
+        collector.add_message("Event:this one is far too long", 2);
+        collector.add_message("Event:two", 3);
+        collector.add_message("Event:three", 4);
+        collector.add_message("Tag:a", 5);
+        collector.add_message("Tag:b", 6);
+        collector.add_message("Tag:c", 7);
+        let (tags, annotations) = collector.finish();
+        assert_eq!(annotations.annotations.len(), 2);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(annotations.dropped, 3);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::matches::{Match, MatchPlayer};
-use common::configuration::ac_config::ACConfig;
+use common::configuration::ac_config::{ACConfig, OpponentRacePolicy};
 use common::PlayerNum;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GameConfig {
@@ -12,11 +13,18 @@ pub struct GameConfig {
     pub strikes: i32,
     pub replay_path: String,
     pub match_id: u32,
-    pub replay_name: String,
+    pub replay_name_template: String,
     pub disable_debug: bool,
     pub real_time: bool,
+    pub realtime_step_emulation: bool,
+    pub disable_fog: bool,
+    pub random_seed: Option<u32>,
+    pub sc2_version: Option<u32>,
     pub visualize: bool,
     pub validate_race: bool,
+    pub opponent_race_policy: OpponentRacePolicy,
+    pub max_annotations: usize,
+    pub max_annotation_length: usize,
     pub players: HashMap<PlayerNum, MatchPlayer>,
 }
 
@@ -31,16 +39,20 @@ impl GameConfig {
             strikes: ac_config.strikes,
             replay_path: ac_config.replays_directory.clone(),
             match_id: m.match_id,
-            replay_name: format!(
-                "{}_{}_vs_{}.SC2Replay",
-                m.match_id,
-                &m.players[&PlayerNum::One].name,
-                &m.players[&PlayerNum::Two].name
-            ),
+            replay_name_template: ac_config.replay_name_template.clone(),
             disable_debug: ac_config.disable_debug,
-            real_time: ac_config.realtime,
+            // Humans can only play in realtime
+            real_time: m.human_player().is_some()
+                || m.options.realtime.unwrap_or(ac_config.realtime),
+            realtime_step_emulation: ac_config.realtime_step_emulation,
+            disable_fog: m.options.disable_fog.unwrap_or_default(),
+            random_seed: m.options.random_seed,
+            sc2_version: m.options.sc2_version.or(ac_config.sc2_version),
             visualize: ac_config.visualize,
             validate_race: ac_config.validate_race,
+            opponent_race_policy: ac_config.opponent_race_policy,
+            max_annotations: ac_config.max_annotations,
+            max_annotation_length: ac_config.max_annotation_length,
             players: m.players.clone(),
         }
     }
@@ -60,13 +72,47 @@ impl GameConfig {
     pub const fn realtime(&self) -> bool {
         self.real_time
     }
+    pub const fn realtime_step_emulation(&self) -> bool {
+        self.real_time && self.realtime_step_emulation
+    }
+    /// Number of players that join the game through the proxy
+    pub fn participant_count(&self) -> usize {
+        self.players
+            .values()
+            .filter(|player| !player.kind.is_computer())
+            .count()
+    }
+    pub const fn disable_fog(&self) -> bool {
+        self.disable_fog
+    }
+    pub const fn random_seed(&self) -> Option<u32> {
+        self.random_seed
+    }
+    /// Base build to start SC2 with. `None` uses the latest installed version.
+    pub const fn sc2_version(&self) -> Option<u32> {
+        self.sc2_version
+    }
     pub const fn max_game_time(&self) -> u32 {
         self.max_game_time
     }
     pub fn replay_path(&self) -> &str {
         &self.replay_path
     }
+    pub fn replay_name_template(&self) -> &str {
+        &self.replay_name_template
+    }
+    /// Where the given player saves its replay, before post-processing picks the final replay
+    pub fn player_replay_path(&self, player_num: PlayerNum) -> PathBuf {
+        Path::new(&self.replay_path).join(format!(
+            "{}_player{}.SC2Replay",
+            self.match_id,
+            player_num.index() + 1
+        ))
+    }
     pub const fn validate_race(&self) -> bool {
         self.validate_race
     }
+    pub const fn opponent_race_policy(&self) -> OpponentRacePolicy {
+        self.opponent_race_policy
+    }
 }
@@ -1,4 +1,6 @@
 use crate::game::player_result::PlayerResult;
+use crate::game::sc2_result::Sc2Result;
+use common::models::aiarena::aiarena_end_reason::EndReason;
 use common::models::aiarena::aiarena_result::AiArenaResult;
 use common::PlayerNum;
 
@@ -8,6 +10,8 @@ pub struct GameResult {
     pub player1_result: Option<PlayerResult>,
     pub player2_result: Option<PlayerResult>,
     pub result: Option<AiArenaResult>,
+    /// SC2 could not be started or could not find the map
+    pub sc2_start_failed: bool,
 }
 
 impl GameResult {
@@ -17,17 +21,56 @@ impl GameResult {
             player1_result: None,
             player2_result: None,
             result: None,
+            sc2_start_failed: false,
         }
     }
     pub fn has_any_result(&self) -> bool {
         self.player1_result.is_some() || self.player2_result.is_some()
     }
+    pub const fn player_result(&self, player_num: PlayerNum) -> Option<&PlayerResult> {
+        match player_num {
+            PlayerNum::One => self.player1_result.as_ref(),
+            PlayerNum::Two => self.player2_result.as_ref(),
+        }
+    }
+    pub fn end_reason(&self, player_num: PlayerNum) -> Option<EndReason> {
+        self.player_result(player_num).map(|r| r.end_reason)
+    }
+    /// The player that surrendered, if any
+    pub fn surrendered_player(&self) -> Option<PlayerNum> {
+        [PlayerNum::One, PlayerNum::Two]
+            .into_iter()
+            .find(|player_num| self.end_reason(*player_num) == Some(EndReason::Surrender))
+    }
+    /// Whether the match failed because SC2 crashed, rather than because of a bot or a decided
+    /// game. Such matches can be replayed.
+    pub fn is_infrastructure_failure(&self) -> bool {
+        if self.sc2_start_failed {
+            return true;
+        }
+        if self.result == Some(AiArenaResult::InitializationError) {
+            return false;
+        }
+        let player_results = [&self.player1_result, &self.player2_result];
+        let bot_fault_or_decided = player_results.iter().flatten().any(|r| {
+            matches!(r.result, Sc2Result::Crash | Sc2Result::Timeout)
+                || r.end_reason.concludes_game()
+        });
+        !bot_fault_or_decided
+            && player_results.iter().flatten().any(|r| {
+                r.result == Sc2Result::SC2Crash && r.end_reason != EndReason::DisconnectAfterGameEnd
+            })
+    }
     pub fn set_error(&mut self) {
         self.result = Some(AiArenaResult::Error);
     }
     pub fn set_init_error(&mut self) {
         self.result = Some(AiArenaResult::InitializationError);
     }
+    pub fn set_sc2_start_failed(&mut self) {
+        self.sc2_start_failed = true;
+        self.result = Some(AiArenaResult::Error);
+    }
 
     pub fn add_player_result(&mut self, player_num: PlayerNum, player_result: PlayerResult) {
         match player_num {
@@ -40,3 +83,93 @@ impl GameResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player_result(result: Sc2Result, end_reason: EndReason) -> PlayerResult {
+        PlayerResult {
+            game_loops: 100,
+            frame_time: 0.0,
+            player_id: 0,
+            tags: Default::default(),
+            step_time_stats: None,
+            realtime_stats: None,
+            summary: None,
+            annotations: None,
+            actual_races: Default::default(),
+            result,
+            end_reason,
+        }
+    }
+
+    fn game_result(player1: Option<PlayerResult>, player2: Option<PlayerResult>) -> GameResult {
+        GameResult {
+            match_id: 1,
+            player1_result: player1,
+            player2_result: player2,
+            result: None,
+            sc2_start_failed: false,
+        }
+    }
+
+    #[test]
+    fn test_sc2_crash_is_infrastructure_failure() {
+        let result = game_result(
+            Some(player_result(Sc2Result::SC2Crash, EndReason::GameCrash)),
+            Some(player_result(Sc2Result::SC2Crash, EndReason::GameCrash)),
+        );
+        assert!(result.is_infrastructure_failure());
+        let result = game_result(
+            Some(player_result(Sc2Result::SC2Crash, EndReason::GameCrash)),
+            None,
+        );
+        assert!(result.is_infrastructure_failure());
+    }
+
+    #[test]
+    fn test_sc2_start_failure_is_infrastructure_failure() {
+        let mut result = game_result(None, None);
+        result.set_sc2_start_failed();
+        assert!(result.is_infrastructure_failure());
+        assert_eq!(result.result, Some(AiArenaResult::Error));
+    }
+
+    #[test]
+    fn test_bot_faults_are_not_infrastructure_failures() {
+        let result = game_result(
+            Some(player_result(Sc2Result::Crash, EndReason::GameCrash)),
+            Some(player_result(Sc2Result::SC2Crash, EndReason::GameCrash)),
+        );
+        assert!(!result.is_infrastructure_failure());
+        let result = game_result(
+            Some(player_result(Sc2Result::SC2Crash, EndReason::GameCrash)),
+            Some(player_result(Sc2Result::Timeout, EndReason::Timeout)),
+        );
+        assert!(!result.is_infrastructure_failure());
+        let mut result = game_result(
+            Some(player_result(Sc2Result::SC2Crash, EndReason::InitCrash)),
+            None,
+        );
+        result.set_init_error();
+        assert!(!result.is_infrastructure_failure());
+    }
+
+    #[test]
+    fn test_decided_games_are_not_infrastructure_failures() {
+        let result = game_result(
+            Some(player_result(Sc2Result::Victory, EndReason::Normal)),
+            Some(player_result(Sc2Result::SC2Crash, EndReason::GameCrash)),
+        );
+        assert!(!result.is_infrastructure_failure());
+        let result = game_result(
+            Some(player_result(Sc2Result::Victory, EndReason::Normal)),
+            Some(player_result(
+                Sc2Result::SC2Crash,
+                EndReason::DisconnectAfterGameEnd,
+            )),
+        );
+        assert!(!result.is_infrastructure_failure());
+    }
+}
@@ -1,6 +1,10 @@
+pub mod annotations;
 pub mod game_config;
 pub mod game_result;
+pub mod pause;
 pub mod player_data;
 pub mod player_result;
 pub mod race;
+pub mod realtime;
 pub mod sc2_result;
+pub mod step_time_histogram;
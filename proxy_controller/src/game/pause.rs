@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PauseState {
+    paused_since: Option<Instant>,
+    total_paused: Duration,
+}
+
+impl PauseState {
+    fn paused_time(&self) -> Duration {
+        self.total_paused
+            + self
+                .paused_since
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
+
+/// Shared switch that freezes the forwarding of bot requests while a match is paused
+#[derive(Debug, Clone)]
+pub struct PauseControl {
+    sender: Arc<watch::Sender<PauseState>>,
+}
+
+impl Default for PauseControl {
+    fn default() -> Self {
+        let (sender, _) = watch::channel(PauseState::default());
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+}
+
+impl PauseControl {
+    /// Returns false if the match was already paused
+    pub fn pause(&self) -> bool {
+        self.sender.send_if_modified(|state| {
+            if state.paused_since.is_none() {
+                state.paused_since = Some(Instant::now());
+                true
+            } else {
+                false
+            }
+        })
+    }
+    /// Returns false if the match was not paused
+    pub fn resume(&self) -> bool {
+        self.sender
+            .send_if_modified(|state| match state.paused_since.take() {
+                Some(since) => {
+                    state.total_paused += since.elapsed();
+                    true
+                }
+                None => false,
+            })
+    }
+    pub fn is_paused(&self) -> bool {
+        self.sender.borrow().paused_since.is_some()
+    }
+    /// Total time spent paused so far, including the current pause
+    pub fn paused_time(&self) -> Duration {
+        self.sender.borrow().paused_time()
+    }
+    pub async fn wait_until_resumed(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as self, so this can't fail
+        let _ = receiver
+            .wait_for(|state| state.paused_since.is_none())
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume() {
+        let pause = PauseControl::default();
+        assert!(!pause.is_paused());
+        assert!(pause.pause());
+        assert!(!pause.pause());
+        assert!(pause.is_paused());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(pause.resume());
+        assert!(!pause.resume());
+        assert!(!pause.is_paused());
+        assert!(pause.paused_time() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let pause = PauseControl::default();
+        let other = pause.clone();
+        pause.pause();
+        assert!(other.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_resumed() {
+        let pause = PauseControl::default();
+        pause.pause();
+        let waiter = {
+            let pause = pause.clone();
+            tokio::spawn(async move { pause.wait_until_resumed().await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+        pause.resume();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("Waiter was not woken up")
+            .unwrap();
+    }
+}
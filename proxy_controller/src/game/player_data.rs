@@ -10,7 +10,20 @@ pub struct PlayerData {
 }
 
 impl PlayerData {
+    /// Raw data is always enabled for bots, so the proxy can tell when the opponent is scouted
+    /// whatever interface the bot uses. It is removed again from the observations of bots that did
+    /// not ask for it.
     pub fn from_join_request(req: &RequestJoinGame) -> Self {
+        let mut player_data = Self::from_human_join_request(req);
+        player_data
+            .interface_options
+            .set_raw_affects_selection(true);
+        player_data.interface_options.set_raw(true);
+        player_data
+    }
+
+    /// Keeps the interface options of the client as requested, including render settings
+    pub fn from_human_join_request(req: &RequestJoinGame) -> Self {
         Self {
             race: req.race(),
             name: if req.has_player_name() {
@@ -19,12 +32,7 @@ impl PlayerData {
                 None
             },
 
-            interface_options: {
-                let mut if_opts = req.options.clone().unwrap();
-
-                if_opts.set_raw_affects_selection(true);
-                if_opts
-            },
+            interface_options: req.options.clone().unwrap(),
         }
     }
 }
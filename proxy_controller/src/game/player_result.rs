@@ -1,5 +1,11 @@
+use crate::game::annotations::PlayerAnnotations;
+use crate::game::race::BotRace;
 use crate::game::sc2_result::Sc2Result;
+use crate::summary::recorder::PlayerSummary;
+use common::models::aiarena::aiarena_end_reason::EndReason;
+use common::models::stats::{RealtimeStats, StepTimeStats};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlayerResult {
@@ -12,8 +18,26 @@ pub struct PlayerResult {
     /// Tags
     #[serde(skip_serializing_if = "indexmap::IndexSet::is_empty")]
     pub tags: indexmap::IndexSet<String>,
+    /// Step time percentiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_time_stats: Option<StepTimeStats>,
+    /// Lag behind the game clock in realtime games
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realtime_stats: Option<RealtimeStats>,
+    /// Time series and chat for the match summary
+    #[serde(skip)]
+    pub summary: Option<PlayerSummary>,
+    /// Metrics, events and build order markers sent by the bot
+    #[serde(skip)]
+    pub annotations: Option<PlayerAnnotations>,
+    /// Race each player id ended up with, as reported to this player in the game info
+    #[serde(skip)]
+    pub actual_races: HashMap<u32, BotRace>,
     /// Result
     pub result: Sc2Result,
+    /// How the player's game ended
+    #[serde(default)]
+    pub end_reason: EndReason,
 }
 impl PlayerResult {
     pub fn set_game_loops(&mut self, game_loops: u32) {
@@ -28,7 +52,16 @@ impl PlayerResult {
     pub fn set_tags(&mut self, tags: indexmap::IndexSet<String>) {
         self.tags = tags;
     }
+    pub fn set_step_time_stats(&mut self, step_time_stats: Option<StepTimeStats>) {
+        self.step_time_stats = step_time_stats;
+    }
+    pub fn set_realtime_stats(&mut self, realtime_stats: Option<RealtimeStats>) {
+        self.realtime_stats = realtime_stats;
+    }
     pub fn set_result(&mut self, result: Sc2Result) {
         self.result = result;
     }
+    pub fn set_end_reason(&mut self, end_reason: EndReason) {
+        self.end_reason = end_reason;
+    }
 }
@@ -20,6 +20,15 @@ impl BotRace {
             _ => Self::NoRace,
         }
     }
+    pub const fn from_race(race: Race) -> Self {
+        match race {
+            Race::Terran => Self::Terran,
+            Race::Zerg => Self::Zerg,
+            Race::Protoss => Self::Protoss,
+            Race::Random => Self::Random,
+            Race::NoRace => Self::NoRace,
+        }
+    }
     pub const fn to_race(self) -> Race {
         match self {
             Self::Terran => Race::Terran,
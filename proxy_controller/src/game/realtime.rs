@@ -0,0 +1,143 @@
+use common::models::stats::RealtimeStats;
+use std::time::{Duration, Instant};
+
+/// Game speed of a realtime game on the "faster" setting
+pub const GAME_LOOPS_PER_SECOND: f32 = 22.4;
+
+/// Converts a wall clock duration to the number of game loops that pass in a realtime game
+pub fn duration_to_loops(duration: Duration) -> f32 {
+    duration.as_secs_f32() * GAME_LOOPS_PER_SECOND
+}
+
+/// Converts a number of game loops to the wall clock duration they take in a realtime game
+pub fn loops_to_duration(loops: u32) -> Duration {
+    Duration::from_secs_f32(loops as f32 / GAME_LOOPS_PER_SECOND)
+}
+
+/// Tracks how far a bot falls behind the game clock in a realtime game.
+///
+/// The lag of a step is the number of game loops that pass between an observation and the bot's
+/// next step or observation request. Missed game loops are loops the bot never observed, beyond
+/// the ones it skipped on purpose by requesting a larger step.
+#[derive(Debug, Clone)]
+pub struct RealtimeTracker {
+    last_game_loop: Option<u32>,
+    last_observation_at: Option<Instant>,
+    expected_loop_delta: u32,
+    missed_game_loops: u32,
+    steps: u32,
+    total_lag_loops: f32,
+    max_lag_loops: f32,
+}
+
+impl Default for RealtimeTracker {
+    fn default() -> Self {
+        Self {
+            last_game_loop: None,
+            last_observation_at: None,
+            expected_loop_delta: 1,
+            missed_game_loops: 0,
+            steps: 0,
+            total_lag_loops: 0.0,
+            max_lag_loops: 0.0,
+        }
+    }
+}
+
+impl RealtimeTracker {
+    pub fn record_lag(&mut self, lag: Duration) {
+        let lag_loops = duration_to_loops(lag);
+        self.steps += 1;
+        self.total_lag_loops += lag_loops;
+        self.max_lag_loops = self.max_lag_loops.max(lag_loops);
+    }
+
+    pub fn record_observation(&mut self, game_loop: u32, observed_at: Instant) {
+        if let Some(last_game_loop) = self.last_game_loop {
+            let delta = game_loop.saturating_sub(last_game_loop);
+            self.missed_game_loops += delta.saturating_sub(self.expected_loop_delta);
+        }
+        self.last_game_loop = Some(game_loop);
+        self.last_observation_at = Some(observed_at);
+        self.expected_loop_delta = 1;
+    }
+
+    /// Registers an emulated step of `count` game loops and returns how long to wait before
+    /// answering it, along with the game loop the game is expected to be at afterwards.
+    pub fn emulate_step(&mut self, count: u32, now: Instant) -> (Duration, u32) {
+        let count = count.max(1);
+        self.expected_loop_delta = count;
+        let target_loop = self.last_game_loop.unwrap_or_default() + count;
+        let wait = self
+            .last_observation_at
+            .map_or(Duration::ZERO, |observed_at| {
+                (observed_at + loops_to_duration(count)).saturating_duration_since(now)
+            });
+        (wait, target_loop)
+    }
+
+    pub fn stats(&self) -> RealtimeStats {
+        let avg_lag_loops = if self.steps == 0 {
+            0f32
+        } else {
+            self.total_lag_loops / self.steps as f32
+        };
+        RealtimeStats {
+            missed_game_loops: self.missed_game_loops,
+            max_lag_loops: self.max_lag_loops,
+            avg_lag_loops,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(duration_to_loops(Duration::from_secs(10)), 224.0);
+        assert_eq!(loops_to_duration(224), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_missed_game_loops() {
+        let mut tracker = RealtimeTracker::default();
+        let now = Instant::now();
+        tracker.record_observation(0, now);
+        tracker.record_observation(1, now);
+        tracker.record_observation(4, now);
+        assert_eq!(tracker.stats().missed_game_loops, 2);
+    }
+
+    #[test]
+    fn test_emulated_step_is_not_missed() {
+        let mut tracker = RealtimeTracker::default();
+        let now = Instant::now();
+        tracker.record_observation(10, now);
+        let (wait, target_loop) = tracker.emulate_step(8, now);
+        assert_eq!(target_loop, 18);
+        assert!(wait > Duration::ZERO && wait <= loops_to_duration(8));
+        tracker.record_observation(19, now);
+        assert_eq!(tracker.stats().missed_game_loops, 1);
+    }
+
+    #[test]
+    fn test_emulated_step_after_slow_bot_does_not_wait() {
+        let mut tracker = RealtimeTracker::default();
+        let now = Instant::now();
+        tracker.record_observation(10, now);
+        let (wait, _) = tracker.emulate_step(1, now + Duration::from_secs(1));
+        assert_eq!(wait, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_lag() {
+        let mut tracker = RealtimeTracker::default();
+        tracker.record_lag(Duration::from_secs(1));
+        tracker.record_lag(Duration::ZERO);
+        let stats = tracker.stats();
+        assert_eq!(stats.max_lag_loops, 22.4);
+        assert_eq!(stats.avg_lag_loops, 11.2);
+    }
+}
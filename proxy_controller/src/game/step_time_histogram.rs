@@ -0,0 +1,129 @@
+use common::models::stats::StepTimeStats;
+use std::time::Duration;
+
+/// Width of a single histogram bucket
+const BUCKET_WIDTH_MS: usize = 1;
+/// Number of buckets. Step times above the last bucket are only tracked through the maximum.
+const BUCKET_COUNT: usize = 1000;
+
+/// Fixed-width histogram of step times.
+///
+/// Recording a step is constant time and the memory used does not grow with the length of the
+/// game. Percentiles are reported at the upper bound of the bucket they fall into.
+#[derive(Debug, Clone)]
+pub struct StepTimeHistogram {
+    buckets: Vec<u32>,
+    overflow: u32,
+    steps: u32,
+    max: Duration,
+    max_frame_time: Option<Duration>,
+    over_max_frame_time: u32,
+}
+
+impl StepTimeHistogram {
+    /// `max_frame_time` is in milliseconds. A value of 0 or less disables the over-limit count.
+    pub fn new(max_frame_time: i32) -> Self {
+        Self {
+            buckets: vec![0; BUCKET_COUNT],
+            overflow: 0,
+            steps: 0,
+            max: Duration::ZERO,
+            max_frame_time: u64::try_from(max_frame_time)
+                .ok()
+                .filter(|x| *x > 0)
+                .map(Duration::from_millis),
+            over_max_frame_time: 0,
+        }
+    }
+
+    pub fn record(&mut self, step_time: Duration) {
+        let bucket = step_time.as_millis() as usize / BUCKET_WIDTH_MS;
+        match self.buckets.get_mut(bucket) {
+            Some(count) => *count += 1,
+            None => self.overflow += 1,
+        }
+        self.steps += 1;
+        self.max = self.max.max(step_time);
+        if self.max_frame_time.map_or(false, |limit| step_time > limit) {
+            self.over_max_frame_time += 1;
+        }
+    }
+
+    pub const fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    fn percentile(&self, percentile: f64) -> Duration {
+        let target = ((f64::from(self.steps) * percentile).ceil() as u32).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                let upper_bound = Duration::from_millis(((bucket + 1) * BUCKET_WIDTH_MS) as u64);
+                return upper_bound.min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Summary of the recorded steps, or `None` if no steps were recorded
+    pub fn stats(&self) -> Option<StepTimeStats> {
+        if self.steps == 0 {
+            return None;
+        }
+        Some(StepTimeStats {
+            steps: self.steps,
+            p50: self.percentile(0.5).as_secs_f32(),
+            p90: self.percentile(0.9).as_secs_f32(),
+            p99: self.percentile(0.99).as_secs_f32(),
+            max: self.max.as_secs_f32(),
+            over_max_frame_time: self.over_max_frame_time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_histogram_has_no_stats() {
+        let histogram = StepTimeHistogram::new(40);
+        assert_eq!(histogram.steps(), 0);
+        assert!(histogram.stats().is_none());
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = StepTimeHistogram::new(40);
+        for ms in 0..100 {
+            histogram.record(Duration::from_micros(ms * 1000 + 500));
+        }
+        let stats = histogram.stats().unwrap();
+        assert_eq!(stats.steps, 100);
+        assert_eq!(stats.p50, 0.050);
+        assert_eq!(stats.p90, 0.090);
+        assert_eq!(stats.p99, 0.099);
+        assert_eq!(stats.max, Duration::from_micros(99_500).as_secs_f32());
+        assert_eq!(stats.over_max_frame_time, 60);
+    }
+
+    #[test]
+    fn test_overflow_is_reported_as_max() {
+        let mut histogram = StepTimeHistogram::new(40);
+        histogram.record(Duration::from_millis(2));
+        histogram.record(Duration::from_secs(5));
+        let stats = histogram.stats().unwrap();
+        assert_eq!(stats.p50, 0.003);
+        assert_eq!(stats.p99, 5.0);
+        assert_eq!(stats.max, 5.0);
+        assert_eq!(stats.over_max_frame_time, 1);
+    }
+
+    #[test]
+    fn test_max_frame_time_disabled() {
+        let mut histogram = StepTimeHistogram::new(0);
+        histogram.record(Duration::from_secs(1));
+        assert_eq!(histogram.stats().unwrap().over_max_frame_time, 0);
+    }
+}
@@ -1,14 +1,23 @@
 #![allow(dead_code)]
+mod bot_listener;
+#[cfg(feature = "swagger")]
+mod docs;
 mod game;
 mod match_scheduler;
 pub mod matches;
 #[cfg(feature = "mockserver")]
 mod mocking;
+mod preflight;
+mod replays;
 mod routes;
 mod state;
+mod summary;
 pub mod websocket;
 mod ws_routes;
 
+#[cfg(feature = "swagger")]
+use crate::docs::ApiDoc;
+use crate::game::pause::PauseControl;
 use crate::match_scheduler::match_scheduler;
 use crate::matches::sources::aiarena_api::HttpApiSource;
 use crate::matches::sources::test_source::TestSource;
@@ -16,7 +25,8 @@ use crate::matches::sources::{FileSource, MatchSource};
 #[cfg(feature = "mockserver")]
 use crate::mocking::setup_mock_server;
 use crate::routes::{
-    configuration, download_bot, download_bot_data, download_map, get_bot_data_md5, get_bot_zip_md5,
+    configuration, download_bot, download_bot_data, download_map, get_bot_data_md5,
+    get_bot_zip_md5, pause_match, preflight, resume_match, tail_bot_log,
 };
 use crate::state::ProxyState;
 use crate::ws_routes::websocket_handler;
@@ -28,8 +38,10 @@ use axum::{http::Request, response::Response};
 use clap::{arg, command, value_parser};
 use common::api::health;
 use common::configuration::ac_config::{ACConfig, RunType};
+use common::configuration::bot_runtime::validate_bot_runtimes;
 use common::configuration::get_host_url;
 use common::logging::init_logging;
+use common::preflight::exit_with_report;
 use config::{Config, FileFormat};
 use parking_lot::RwLock;
 use std::net::SocketAddr;
@@ -42,6 +54,10 @@ use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 use tower_http::BoxError;
 use tracing::{debug, Span};
+#[cfg(feature = "swagger")]
+use utoipa::OpenApi;
+#[cfg(feature = "swagger")]
+use utoipa_swagger_ui::SwaggerUi;
 
 static PREFIX: &str = "acproxy";
 
@@ -49,6 +65,7 @@ static PREFIX: &str = "acproxy";
 async fn main() {
     let matches = command!()
         .arg(arg!(--port <VALUE>).value_parser(value_parser!(u16)))
+        .arg(arg!(--check "Check the environment, print a report and exit"))
         .get_matches();
 
     let port = *matches.get_one::<u16>("port").unwrap_or(&8080);
@@ -69,6 +86,9 @@ async fn main() {
         settings.base_website_url = mock_server.base_url();
         settings.caching_server_url = mock_server.base_url();
     }
+    if matches.get_flag("check") {
+        exit_with_report(&preflight::run_checks(&settings));
+    }
     let log_level = &settings.logging_level;
     let env_log = std::env::var("RUST_LOG")
         .unwrap_or_else(|_| format!("info,common={log_level},proxy_controller={log_level}"));
@@ -88,6 +108,7 @@ async fn main() {
         RunType::Test => Box::new(TestSource::new(settings.clone())),
         RunType::Mock => Box::new(HttpApiSource::new(settings.clone()).unwrap()),
     };
+    let addr = SocketAddr::from_str(&host_url).unwrap();
     let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
     let app_state = Arc::new(RwLock::new(ProxyState {
         settings,
@@ -103,12 +124,22 @@ async fn main() {
         shutdown_sender: tx,
         bot_controllers: vec![],
         sc2_controllers: vec![],
+        pause: PauseControl::default(),
+        listen_ip: addr.ip(),
     }));
 
     tokio::spawn(match_scheduler(app_state.clone(), match_source));
 
+    #[allow(unused_mut)]
+    let mut router = Router::<Arc<RwLock<ProxyState>>>::new();
+    #[cfg(feature = "swagger")]
+    {
+        router = router
+            .merge(SwaggerUi::new("/swagger-ui/").url("/api-doc/openapi.json", ApiDoc::openapi()));
+    }
+
     // Compose the routes
-    let app = Router::<Arc<RwLock<ProxyState>>>::new()
+    let app = router
         .route("/configuration", get(configuration))
         .route("/sc2api", get(websocket_handler))
         .layer(
@@ -121,11 +152,15 @@ async fn main() {
                 }),
         )
         .route("/health", get(health))
+        .route("/preflight", get(preflight))
         .route("/download_bot", post(download_bot))
         .route("/download_map", get(download_map))
         .route("/download_bot_data", post(download_bot_data))
         .route("/download_bot_data/md5_hash", post(get_bot_data_md5))
         .route("/download_bot/md5_hash", post(get_bot_zip_md5))
+        .route("/matches/current/pause", post(pause_match))
+        .route("/matches/current/resume", post(resume_match))
+        .route("/matches/current/bots/:player/log", get(tail_bot_log))
         // Add middleware to all routes
         .layer(
             ServiceBuilder::new()
@@ -143,7 +178,6 @@ async fn main() {
                 .into_inner(),
         )
         .with_state(app_state);
-    let addr = SocketAddr::from_str(&host_url).unwrap();
 
     debug!("listening on {}", addr);
     let graceful_server = axum::Server::bind(&addr)
@@ -162,7 +196,7 @@ async fn main() {
 
 fn setup_proxy_config() -> ACConfig {
     let default_config = include_str!("../../configs/default_config.toml");
-    Config::builder()
+    let settings = Config::builder()
         .add_source(config::File::from_str(default_config, FileFormat::Toml).required(true))
         .add_source(config::File::new("config.toml", FileFormat::Toml).required(false))
         .add_source(config::File::new("config.json", FileFormat::Json).required(false))
@@ -170,7 +204,11 @@ fn setup_proxy_config() -> ACConfig {
         .build()
         .expect("Could not load config")
         .try_deserialize::<ACConfig>()
-        .expect("Could not deserialize config")
+        .expect("Could not deserialize config");
+    if let Err(e) = validate_bot_runtimes(&settings) {
+        panic!("{e}");
+    }
+    settings
 }
 
 /// Tokio signal handler that will wait for a user to press CTRL+C.
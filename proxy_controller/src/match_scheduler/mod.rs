@@ -1,32 +1,44 @@
+use crate::bot_listener::BotListener;
+use crate::game::annotations::MatchAnnotations;
 use crate::game::game_config::GameConfig;
 use crate::game::game_result::GameResult;
+use crate::match_scheduler::reports::{MatchAttempt, MatchReports};
 use crate::matches::sources::{LogsAndReplays, MatchSource};
 use crate::matches::{Match, MatchPlayer};
+use crate::replays;
 use crate::state::{ProxyState, SC2Url};
+use crate::summary::MatchSummary;
 use bytes::Bytes;
 use common::api::api_reference::bot_controller_client::BotController;
 use common::api::api_reference::sc2_controller_client::SC2Controller;
 use common::api::api_reference::{ApiError, ControllerApi};
-use common::configuration::ac_config::{ACConfig, RunType};
+use common::configuration::ac_config::{ACConfig, DataQuotaPolicy, RunType};
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_result::AiArenaResult;
-use common::models::bot_controller::StartBot;
+use common::models::bot_controller::{DataSnapshot, DataUsage, StartBot};
+use common::procs::limits::LimitViolation;
 use common::utilities::directory::ensure_directory_structure;
 use common::utilities::portpicker::Port;
+use common::utilities::scrub::scrub_directory;
+use common::utilities::zip_utils::{zip_directory_to_path, zip_extract_from_bytes};
 use common::PlayerNum;
-use futures_util::future::{join, join3, join4};
+use futures_util::future::{join, join3, join_all};
 use futures_util::TryFutureExt;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tokio::join;
 use tokio::time::sleep;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+mod reports;
+
+/// Note added to the bot logs when the bot data went over its quota
+const DATA_QUOTA_NOTE: &str = "data_quota.txt";
 
 pub async fn match_scheduler<M: MatchSource>(
     proxy_state: Arc<RwLock<ProxyState>>,
@@ -95,13 +107,6 @@ pub async fn match_scheduler<M: MatchSource>(
     'main_loop: while match_source.has_next().await
         && (match_counter < rounds_per_run || rounds_per_run == -1)
     {
-        info!("Sending start requests to SC2");
-
-        let response = tokio::spawn(join(
-            sc2_controllers[0].clone().start_owned(),
-            sc2_controllers[1].clone().start_owned(),
-        ));
-
         let new_match = match match_source.next_match().await {
             None => {
                 sleep(Duration::from_secs(30)).await;
@@ -109,151 +114,229 @@ pub async fn match_scheduler<M: MatchSource>(
             }
             Some(m) => m,
         };
-
-        let start_time = std::time::Instant::now();
-
-        {
-            let mut temp_state = proxy_state.write();
-            temp_state.current_match = Some(new_match.clone());
-            temp_state.game_result = Some(GameResult::new(new_match.match_id));
-        }
-        info!("Starting Game - Round {}", match_counter);
-        info!(
-            "{} vs {}",
-            &new_match.players[&PlayerNum::One].name,
-            &new_match.players[&PlayerNum::Two].name
-        );
-
-        tracing::trace!("Finding map");
-        match sc2_controllers[0].find_map(&new_match.map_name).await {
-            Ok(map) => {
-                proxy_state.write().map = Some(map.map_path);
+        let participants = new_match.participants();
+        let bot_players = new_match.bot_players();
+        let game_config = GameConfig::new(&new_match, &settings);
+        let mut attempts: Vec<MatchAttempt> = Vec::new();
+        // Bots whose data was snapshotted by the bot controller, so retries can restore it
+        let mut data_snapshots = HashSet::new();
+        let game_result = loop {
+            info!("Sending start requests to SC2");
+            let response = tokio::spawn(join_all(participants.iter().map(|player_num| {
+                sc2_controllers[player_num.index()]
+                    .clone()
+                    .start_owned(game_config.sc2_version())
+            })));
+
+            let start_time = std::time::Instant::now();
+
+            {
+                let mut temp_state = proxy_state.write();
+                temp_state.current_match = Some(new_match.clone());
+                temp_state.game_result = Some(GameResult::new(new_match.match_id));
             }
-            Err(e) => {
-                error!("Failed to find map: {}", e);
-                break 'main_loop;
+            info!("Starting Game - Round {}", match_counter);
+            info!(
+                "{} vs {}",
+                &new_match.players[&PlayerNum::One].name,
+                &new_match.players[&PlayerNum::Two].name
+            );
+
+            // SC2 failing to start is worth a retry like a crash during the match
+            let mut sc2_ready = true;
+            tracing::trace!("Finding map");
+            match sc2_controllers[0].find_map(&new_match.map_name).await {
+                Ok(map) => {
+                    proxy_state.write().map = Some(map.map_path);
+                }
+                Err(e) => {
+                    error!("Failed to find map: {}", e);
+                    sc2_ready = false;
+                }
             }
-        }
 
-        let (process_key1, process_key2) = match response.await.unwrap() {
-            (Ok(sc1_resp), Ok(sc2_resp)) => {
-                tracing::debug!("SC2-1 Response:\n{:?}", sc1_resp);
-                tracing::debug!("SC2-2 Response:\n{:?}", sc2_resp);
-                let urls = vec![
-                    SC2Url::new(&settings.sc2_cont_host, &sc1_resp),
-                    SC2Url::new(&settings.sc2_cont_host, &sc2_resp),
-                ];
-                tracing::trace!("Adding SC2 urls");
-                proxy_state.write().sc2_urls.extend(urls);
-
-                tracing::trace!("SC2 urls added");
-                sc2_controllers[0].set_process_key(sc1_resp.process_key);
-                proxy_state.write().sc2_controllers[0].set_process_key(sc1_resp.process_key);
-                sc2_controllers[1].set_process_key(sc2_resp.process_key);
-                proxy_state.write().sc2_controllers[1].set_process_key(sc2_resp.process_key);
-                bot_controllers[0].set_process_key(sc1_resp.process_key);
-                proxy_state.write().bot_controllers[0].set_process_key(sc1_resp.process_key);
-                bot_controllers[1].set_process_key(sc2_resp.process_key);
-                proxy_state.write().bot_controllers[1].set_process_key(sc2_resp.process_key);
-
-                (sc1_resp.process_key, sc2_resp.process_key)
-            }
-            (Err(e), _) | (_, Err(e)) => {
-                error!("Failed to start SC2: {}", e);
-                break 'main_loop;
+            let mut process_keys = HashMap::with_capacity(participants.len());
+            for (player_num, sc2_resp) in participants.iter().zip(response.await.unwrap()) {
+                match sc2_resp {
+                    Ok(sc2_resp) => {
+                        tracing::debug!("SC2 Response for {:?}:\n{:?}", player_num, sc2_resp);
+                        let index = player_num.index();
+                        tracing::trace!("Adding SC2 url");
+                        let mut state = proxy_state.write();
+                        state
+                            .sc2_urls
+                            .push(SC2Url::new(&settings.sc2_cont_host, &sc2_resp));
+                        sc2_controllers[index].set_process_key(sc2_resp.process_key);
+                        state.sc2_controllers[index].set_process_key(sc2_resp.process_key);
+                        bot_controllers[index].set_process_key(sc2_resp.process_key);
+                        state.bot_controllers[index].set_process_key(sc2_resp.process_key);
+                        process_keys.insert(*player_num, sc2_resp.process_key);
+                    }
+                    Err(e) => {
+                        error!("Failed to start SC2: {}", e);
+                        sc2_ready = false;
+                    }
+                }
             }
-        };
 
-        tracing::debug!("Starting bots");
-        let mut bots_started = false;
-        let should_download = settings.run_type == RunType::AiArena;
-        bot_controllers[0].set_start_bot(create_start_bot(
-            PlayerNum::One,
-            &new_match,
-            process_key1,
-            should_download,
-        ));
-        bot_controllers[1].set_start_bot(create_start_bot(
-            PlayerNum::Two,
-            &new_match,
-            process_key2,
-            should_download,
-        ));
-
-        match join!(bot_controllers[0].start(), bot_controllers[1].start()) {
-            (Ok(resp1), Ok(resp2)) => {
-                tracing::trace!("Bots started");
-                let mut counter = 0;
-                let max_retries = 60;
-                let (mut bot_added1, mut bot_added2) = (false, false);
-                while counter < max_retries && !(bot_added1 && bot_added2) {
-                    if counter > 0 {
-                        sleep(Duration::from_millis(500)).await;
-                    }
-                    if !bot_added1 {
-                        tracing::trace!("Adding bot1");
-                        bot_added1 = proxy_state.write().update_player(
-                            resp1.port,
-                            &new_match.players[&PlayerNum::One].name,
-                            PlayerNum::One,
-                        );
+            tracing::debug!("Starting bots");
+            let listeners = match start_bot_listeners(&proxy_state, &bot_players, &settings) {
+                Ok(listeners) => listeners,
+                Err(e) => {
+                    error!("Failed to open bot listeners: {}", e);
+                    break 'main_loop;
+                }
+            };
+            let should_download = settings.run_type == RunType::AiArena;
+            // Every attempt starts from the data the bots had before the first one
+            let data_snapshot = |player_num| {
+                if data_snapshots.contains(player_num) {
+                    DataSnapshot::Restore
+                } else if settings.max_match_retries > 0 {
+                    DataSnapshot::Take
+                } else {
+                    DataSnapshot::Skip
+                }
+            };
+            let mut players_ready = sc2_ready;
+            if players_ready {
+                for player_num in &bot_players {
+                    match create_start_bot(
+                        *player_num,
+                        &new_match,
+                        process_keys[player_num],
+                        &listeners[player_num],
+                        should_download,
+                        data_snapshot(player_num),
+                    ) {
+                        Some(start_bot) => {
+                            bot_controllers[player_num.index()].set_start_bot(start_bot)
+                        }
+                        None => {
+                            error!("Player {:?} is not a bot and can't be started", player_num);
+                            players_ready = false;
+                        }
                     }
-                    if !bot_added2 {
-                        tracing::trace!("Adding bot2");
-                        bot_added2 = proxy_state.write().update_player(
-                            resp2.port,
-                            &new_match.players[&PlayerNum::Two].name,
-                            PlayerNum::Two,
-                        );
+                }
+            }
+
+            if players_ready {
+                let start_responses = join_all(
+                    bot_players
+                        .iter()
+                        .map(|player_num| bot_controllers[player_num.index()].start()),
+                )
+                .await;
+                for (player_num, start_response) in bot_players.iter().zip(start_responses) {
+                    match start_response {
+                        Ok(_) if settings.max_match_retries > 0 => {
+                            data_snapshots.insert(*player_num);
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            error!("Failed to start bot {:?}: {}", player_num, e);
+                            players_ready = false;
+                        }
                     }
+                }
+            }
+            if players_ready {
+                let connect_timeout = Duration::from_secs(settings.bot_connect_timeout_secs);
+                let connections = join_all(bot_players.iter().map(|player_num| {
+                    wait_for_connection(&proxy_state, *player_num, connect_timeout)
+                }))
+                .await;
+                players_ready = connections.into_iter().all(|connected| connected);
+            }
 
-                    counter += 1;
+            if players_ready {
+                tracing::trace!("Bots started");
+                if let Some(human_player) = new_match.human_player() {
+                    info!("Waiting for a human to connect as {:?}", human_player);
+                    let connect_timeout = Duration::from_secs(settings.human_connect_timeout_secs);
+                    players_ready =
+                        wait_for_connection(&proxy_state, human_player, connect_timeout).await;
                 }
-                bots_started = true;
             }
-            (Err(e), _) => {
-                error!("Failed to start bot 1: {}", e);
+
+            if players_ready {
+                wait_for_results(&proxy_state, &participants).await;
+            } else if sc2_ready {
                 proxy_state.write().game_result.as_mut().unwrap().result =
                     Some(AiArenaResult::InitializationError);
+            } else {
+                proxy_state
+                    .write()
+                    .game_result
+                    .as_mut()
+                    .unwrap()
+                    .set_sc2_start_failed();
             }
-            (_, Err(e)) => {
-                error!("Failed to start bot 2: {}", e);
-                proxy_state.write().game_result.as_mut().unwrap().result =
-                    Some(AiArenaResult::InitializationError);
+            drop(listeners);
+
+            let game_result = proxy_state.read().game_result.clone().unwrap();
+
+            let aiarena_game_result = AiArenaGameResult::from(&game_result);
+            // let serialized_result = serde_json::to_value(aiarena_game_result).unwrap();
+            info!("{:?}", &aiarena_game_result);
+            info!("Match finished in {:?}", start_time.elapsed());
+
+            let infrastructure_failure = game_result.is_infrastructure_failure();
+            attempts.push(MatchAttempt::new(
+                attempts.len() + 1,
+                &aiarena_game_result,
+                infrastructure_failure,
+                start_time.elapsed(),
+            ));
+            if infrastructure_failure && attempts.len() <= settings.max_match_retries {
+                warn!(
+                    "SC2 failed, restarting the match (attempt {} of {})",
+                    attempts.len() + 1,
+                    settings.max_match_retries + 1
+                );
+                terminate_processes(&bot_controllers, &sc2_controllers, &new_match).await;
+                replays::remove_player_replays(&game_config).await;
+                clean_up_state(&mut proxy_state.write());
+                continue;
             }
-        }
-        if bots_started {
-            loop {
-                tracing::trace!("Waiting for results");
-                let (p1_result_ready, p2_result_ready) = {
-                    let game_result = { proxy_state.read().game_result.clone().unwrap() };
-                    let result_ready = game_result.result.is_some();
-                    (
-                        result_ready || game_result.player1_result.as_ref().is_some(),
-                        result_ready || game_result.player2_result.as_ref().is_some(),
-                        // game_result.result.as_ref().is_some(),
-                    )
-                };
-
-                if p1_result_ready && p2_result_ready {
-                    break;
-                }
-
-                sleep(Duration::from_secs(3)).await;
+            break game_result;
+        };
+        let mut aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let data_usages = fetch_data_usages(&bot_controllers, &new_match).await;
+        for (player_num, usage) in &data_usages {
+            if let Some(violation) = usage.violation() {
+                aiarena_game_result
+                    .limit_violations_mut(*player_num)
+                    .push(violation);
             }
         }
-
-        let game_result = proxy_state.read().game_result.clone().unwrap();
-
-        let aiarena_game_result = AiArenaGameResult::from(&game_result);
-        // let serialized_result = serde_json::to_value(aiarena_game_result).unwrap();
-        info!("{:?}", &aiarena_game_result);
-        info!("Match finished in {:?}", start_time.elapsed());
-        let game_config = GameConfig::new(&new_match, &settings);
+        // SC2 is still running and decodes the replay for its metadata
+        let sc2_url = proxy_state.read().sc2_urls.first().cloned();
+        let replay_file =
+            match replays::process_replays(&game_config, &game_result, sc2_url.as_ref()).await {
+                Ok(replay_file) => Some(replay_file),
+                Err(e) => {
+                    error!("Replay processing failed: {}", e);
+                    // Upload whichever replay a player left behind, if any
+                    participants
+                        .iter()
+                        .map(|player_num| game_config.player_replay_path(*player_num))
+                        .find(|path| path.exists())
+                }
+            };
+        let summary = MatchSummary::new(&game_config, &game_result);
+        let annotations = MatchAnnotations::new(&game_config, &game_result);
+        let reports = MatchReports {
+            summary: &summary,
+            annotations: &annotations,
+            attempts: &attempts,
+        };
         let logs_and_replays = match build_logs_and_replays_object(
             &new_match.players,
             &bot_controllers,
-            PathBuf::from(game_config.replay_path()).join(&game_config.replay_name),
+            &data_usages,
+            replay_file,
+            &reports,
             &settings,
         )
         .await
@@ -272,12 +355,7 @@ pub async fn match_scheduler<M: MatchSource>(
             error!("{:?}", e);
         }
         match_counter += 1;
-        let _cleanup_res = join3(
-            bot_controllers[0].terminate_all("graceful"),
-            bot_controllers[1].terminate_all("graceful"),
-            sc2_controllers[0].terminate_all("kill"),
-        )
-        .await;
+        terminate_processes(&bot_controllers, &sc2_controllers, &new_match).await;
         let mut state = proxy_state.write();
         clean_up_state(&mut state);
     }
@@ -303,10 +381,105 @@ pub async fn match_scheduler<M: MatchSource>(
     // todo: Clean up folders, zip files
 }
 
+/// Opens a proxy listener for each bot, on the configured port of its player if there is one
+fn start_bot_listeners(
+    proxy_state: &Arc<RwLock<ProxyState>>,
+    bot_players: &[PlayerNum],
+    settings: &ACConfig,
+) -> io::Result<HashMap<PlayerNum, BotListener>> {
+    let listen_ip = proxy_state.read().listen_ip;
+    bot_players
+        .iter()
+        .map(|player_num| {
+            let port = settings
+                .bot_listener_ports
+                .get(player_num.index())
+                .copied()
+                .unwrap_or(0);
+            BotListener::start(proxy_state.clone(), *player_num, listen_ip, port)
+                .map(|listener| (*player_num, listener))
+        })
+        .collect()
+}
+
+/// Waits until `player_num` has connected to the proxy
+async fn wait_for_connection(
+    proxy_state: &Arc<RwLock<ProxyState>>,
+    player_num: PlayerNum,
+    timeout: Duration,
+) -> bool {
+    let start = std::time::Instant::now();
+    while !proxy_state.read().is_connected(player_num) {
+        if start.elapsed() > timeout {
+            error!(
+                "Player {:?} did not connect within {}s",
+                player_num,
+                timeout.as_secs()
+            );
+            return false;
+        }
+        sleep(Duration::from_millis(250)).await;
+    }
+    true
+}
+
+/// Waits until every participant in the match has reported a result, or the match result has
+/// been set
+async fn wait_for_results(proxy_state: &Arc<RwLock<ProxyState>>, participants: &[PlayerNum]) {
+    loop {
+        tracing::trace!("Waiting for results");
+        let results_ready = {
+            let state = proxy_state.read();
+            let game_result = state.game_result.as_ref().unwrap();
+            game_result.result.is_some()
+                || participants
+                    .iter()
+                    .all(|player_num| game_result.player_result(*player_num).is_some())
+        };
+
+        if results_ready {
+            break;
+        }
+
+        sleep(Duration::from_secs(3)).await;
+    }
+}
+
+/// Sizes of the data directories of the bots in `current_match`, measured before the data is
+/// packaged
+async fn fetch_data_usages(
+    bot_controllers: &[BotController],
+    current_match: &Match,
+) -> HashMap<PlayerNum, DataUsage> {
+    let bot_players = current_match.bot_players();
+    let usages = join_all(
+        bot_players
+            .iter()
+            .map(|player_num| bot_controllers[player_num.index()].bot_data_usage()),
+    )
+    .await;
+    bot_players
+        .into_iter()
+        .zip(usages)
+        .filter_map(|(player_num, usage)| match usage {
+            Ok(usage) => Some((player_num, usage)),
+            Err(e) => {
+                error!(
+                    "Could not check the size of the data of bot {:?}: {:?}",
+                    player_num, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
 async fn build_logs_and_replays_object(
     players: &HashMap<PlayerNum, MatchPlayer>,
     bot_controllers: &[BotController],
-    replay_file: PathBuf,
+    data_usages: &HashMap<PlayerNum, DataUsage>,
+    replay_file: Option<PathBuf>,
+    reports: &MatchReports<'_>,
     settings: &ACConfig,
 ) -> io::Result<LogsAndReplays> {
     let bot1_name = players[&PlayerNum::One].name.clone();
@@ -316,12 +489,22 @@ async fn build_logs_and_replays_object(
 
     ensure_directory_structure(&settings.temp_root, &settings.temp_path).await?;
 
-    let (bot1_dir, bot2_dir) = build_bot_logs(&temp_folder, bot_controllers).await.unwrap();
+    let (bot1_dir, bot2_dir) = build_bot_logs(
+        &temp_folder,
+        bot_controllers,
+        data_usages,
+        players,
+        settings,
+    )
+    .await
+    .unwrap();
 
     let arenaclient_log_directory = build_arenaclient_logs(&temp_folder, bot_controllers)
         .await
         .unwrap(); // todo: dont unwrap
 
+    reports.write_to(&arenaclient_log_directory).await;
+
     // Copy proxy_controller logs last to pick up any potential issues
     let proxy_log_path_str = format!(
         "{}/proxy_controller/proxy_controller.log",
@@ -364,6 +547,9 @@ async fn build_logs_and_replays_object(
 async fn build_bot_logs(
     temp_folder: &Path,
     bot_controllers: &[BotController],
+    data_usages: &HashMap<PlayerNum, DataUsage>,
+    players: &HashMap<PlayerNum, MatchPlayer>,
+    settings: &ACConfig,
 ) -> io::Result<(PathBuf, PathBuf)> {
     let bot1_dir = temp_folder.join("bot1");
     tokio::fs::create_dir(&bot1_dir).await?;
@@ -372,55 +558,126 @@ async fn build_bot_logs(
     tokio::fs::create_dir(&bot1_dir.join("logs")).await?;
     tokio::fs::create_dir(&bot2_dir.join("logs")).await?;
 
-    let res = join4(
-        bot_controllers[0].download_bot_log().and_then(|x| {
-            let file_path = bot1_dir.join("logs").join("stderr.log");
-            let archive_file = bot1_dir.join("logs.zip");
-            let archive_directory = bot1_dir.join("logs");
-            async move {
-                match write_file(&file_path, &x).await.map_err(ApiError::from) {
-                    Ok(_) => common::utilities::zip_utils::zip_directory_to_path(
-                        &archive_file,
-                        &archive_directory,
-                    )
-                    .map_err(ApiError::from),
-                    e => e,
-                }
+    let downloads = [(PlayerNum::One, &bot1_dir), (PlayerNum::Two, &bot2_dir)]
+        .into_iter()
+        .filter(|(player_num, _)| players[player_num].kind.is_bot())
+        .map(|(player_num, bot_dir)| {
+            download_bot_log_and_data(
+                bot_dir,
+                &bot_controllers[player_num.index()],
+                data_usages.get(&player_num),
+                settings,
+            )
+        });
+    join_all(downloads).await;
+
+    Ok((bot1_dir, bot2_dir))
+}
+
+async fn download_bot_log_and_data(
+    bot_dir: &Path,
+    bot_controller: &BotController,
+    data_usage: Option<&DataUsage>,
+    settings: &ACConfig,
+) {
+    let reject_data = settings.bot_data_quota_policy == DataQuotaPolicy::Reject
+        && data_usage.map_or(false, |usage| usage.over_quota());
+    if let Some(usage) = data_usage {
+        if let Some(note) = data_quota_note(usage, settings.bot_data_quota_policy) {
+            warn!("Bot data in {:?} went over its quota: {:?}", bot_dir, usage);
+            // Lands in the bot logs, so the bot author sees what happened to the data
+            let note_path = bot_dir.join("logs").join(DATA_QUOTA_NOTE);
+            if let Err(e) = tokio::fs::write(note_path, note).await {
+                error!("Could not write the data quota note: {:?}", e);
             }
-        }),
-        bot_controllers[1].download_bot_log().and_then(|x| {
-            let file_path = bot2_dir.join("logs").join("stderr.log");
-            let archive_file = bot2_dir.join("logs.zip");
-            let archive_directory = bot2_dir.join("logs");
+        }
+    }
+
+    let secrets = &settings.bot_log_scrub;
+    let res = join(
+        bot_controller.download_bot_log().and_then(|x| {
+            let archive_file = bot_dir.join("logs.zip");
+            let archive_directory = bot_dir.join("logs");
             async move {
-                match write_file(&file_path, &x).await.map_err(ApiError::from) {
-                    Ok(_) => common::utilities::zip_utils::zip_directory_to_path(
-                        &archive_file,
-                        &archive_directory,
-                    )
-                    .map_err(ApiError::from),
-                    e => e,
-                }
+                // The bot controller sends a zip of all the bot logs, which is unpacked to
+                // redact secrets before it is packaged again
+                zip_extract_from_bytes(&x, &archive_directory).map_err(ApiError::from)?;
+                scrub_directory(&archive_directory, secrets).map_err(ApiError::from)?;
+                zip_directory_to_path(&archive_file, &archive_directory).map_err(ApiError::from)
             }
         }),
-        bot_controllers[0].download_bot_data().and_then(|x| {
-            let file_path = bot1_dir.join("data.zip");
-            async move { write_file(&file_path, &x).await.map_err(ApiError::from) }
-        }),
-        bot_controllers[1].download_bot_data().and_then(|x| {
-            let file_path = bot2_dir.join("data.zip");
-            async move { write_file(&file_path, &x).await.map_err(ApiError::from) }
-        }),
+        async {
+            // Without a data.zip the match source doesn't upload any data, so the bot keeps the
+            // data of its previous match
+            if reject_data {
+                return Ok(());
+            }
+            bot_controller
+                .download_bot_data()
+                .and_then(|x| {
+                    let file_path = bot_dir.join("data.zip");
+                    async move { write_file(&file_path, &x).await.map_err(ApiError::from) }
+                })
+                .await
+        },
     )
     .await;
 
-    match res {
-        (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
-            error!("{:?}", e);
+    if let (Err(e), _) | (_, Err(e)) = res {
+        error!("{:?}", e);
+    }
+}
+
+/// Explains a data quota violation to the bot author, if there was one
+fn data_quota_note(usage: &DataUsage, policy: DataQuotaPolicy) -> Option<String> {
+    let (quota_bytes, used_bytes) = match usage.violation()? {
+        LimitViolation::DataQuota {
+            quota_bytes,
+            used_bytes,
+        } => (quota_bytes, used_bytes),
+        _ => return None,
+    };
+    let outcome = match (usage.over_quota(), policy) {
+        (false, _) => "It was back within the quota at the end of the match, so all data was uploaded.",
+        (true, DataQuotaPolicy::Reject) => "It was still over the quota at the end of the match, so the data was not uploaded and the data of the previous match is kept.",
+        (true, DataQuotaPolicy::Truncate) => "It was still over the quota at the end of the match, so only the files that fit in the quota were uploaded.",
+    };
+    Some(format!(
+        "The data directory reached {used_bytes} bytes, over its quota of {quota_bytes} bytes.\n{outcome}\n"
+    ))
+}
+
+/// Stops the bots and SC2 instances of the players in `current_match`
+async fn terminate_processes(
+    bot_controllers: &[BotController],
+    sc2_controllers: &[SC2Controller],
+    current_match: &Match,
+) {
+    let bot_players = current_match.bot_players();
+    let (bot_responses, _sc2_responses) = join(
+        join_all(
+            bot_players
+                .iter()
+                .map(|player_num| bot_controllers[player_num.index()].terminate_all("graceful")),
+        ),
+        join_all(
+            current_match
+                .participants()
+                .iter()
+                .map(|player_num| sc2_controllers[player_num.index()].terminate_all("kill")),
+        ),
+    )
+    .await;
+    for (player_num, response) in bot_players.iter().zip(bot_responses) {
+        if let Ok(response) = response {
+            if !response.violations.is_empty() {
+                warn!(
+                    "Bot {:?} ran into limits: {:?}",
+                    player_num, response.violations
+                );
+            }
         }
-        _ => {}
     }
-    Ok((bot1_dir, bot2_dir))
 }
 
 fn init_bot_controllers(settings: &ACConfig) -> Result<[BotController; 2], url::ParseError> {
@@ -446,6 +703,7 @@ fn clean_up_state(state: &mut ProxyState) {
     state.port_config = None;
     state.auth_whitelist.clear();
     state.game_config = None;
+    state.pause.resume();
     state.remove_all_clients();
 }
 
@@ -486,15 +744,19 @@ fn create_start_bot(
     player_num: PlayerNum,
     new_match: &Match,
     process_key: Port,
+    listener: &BotListener,
     should_download: bool,
-) -> StartBot {
-    StartBot {
+    data_snapshot: DataSnapshot,
+) -> Option<StartBot> {
+    Some(StartBot {
         bot_name: new_match.players[&player_num].name.clone(),
-        bot_type: new_match.players[&player_num].bot_type,
+        bot_type: new_match.players[&player_num].kind.bot_type()?,
         opponent_id: new_match.players[&player_num.other_player()].id.to_string(),
         player_num,
         match_id: new_match.match_id,
         process_key,
         should_download,
-    }
+        proxy_port: Some(listener.port()),
+        data_snapshot,
+    })
 }
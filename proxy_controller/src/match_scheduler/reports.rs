@@ -0,0 +1,68 @@
+use crate::game::annotations::MatchAnnotations;
+use crate::summary::MatchSummary;
+use common::models::aiarena::aiarena_end_reason::EndReason;
+use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
+use common::models::aiarena::aiarena_result::AiArenaResult;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tracing::error;
+
+/// One run of a match. Matches are replayed when SC2 fails, so a match can have several.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchAttempt {
+    pub attempt: usize,
+    pub result: AiArenaResult,
+    pub game_steps: u32,
+    pub bot1_end_reason: Option<EndReason>,
+    pub bot2_end_reason: Option<EndReason>,
+    pub infrastructure_failure: bool,
+    pub duration_secs: f32,
+}
+
+impl MatchAttempt {
+    pub fn new(
+        attempt: usize,
+        game_result: &AiArenaGameResult,
+        infrastructure_failure: bool,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            attempt,
+            result: game_result.result,
+            game_steps: game_result.game_steps,
+            bot1_end_reason: game_result.bot1_end_reason,
+            bot2_end_reason: game_result.bot2_end_reason,
+            infrastructure_failure,
+            duration_secs: duration.as_secs_f32(),
+        }
+    }
+}
+
+/// Reports that are bundled with the arenaclient logs of a match
+pub struct MatchReports<'a> {
+    pub summary: &'a MatchSummary,
+    pub annotations: &'a MatchAnnotations,
+    pub attempts: &'a [MatchAttempt],
+}
+
+impl MatchReports<'_> {
+    /// Writes all reports to `directory`. Failures are logged, since a missing report should not
+    /// hold back the match result.
+    pub async fn write_to(&self, directory: &Path) {
+        if let Err(e) = self.summary.write_to(directory).await {
+            error!("Failed to write match summary: {:?}", e);
+        }
+        if let Err(e) = self.annotations.write_to(directory).await {
+            error!("Failed to write annotations: {:?}", e);
+        }
+        if let Err(e) = self.write_attempts(directory).await {
+            error!("Failed to write match attempts: {:?}", e);
+        }
+    }
+
+    async fn write_attempts(&self, directory: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(self.attempts)?;
+        tokio::fs::write(directory.join("attempts.json"), json).await
+    }
+}
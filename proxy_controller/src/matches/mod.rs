@@ -1,11 +1,16 @@
 use crate::game::race::BotRace;
+use crate::matches::options::MatchOptions;
+use crate::matches::player_kind::PlayerKind;
 use crate::matches::sources::file_source::errors::FileMatchExtractError;
+use common::configuration::ac_config::ACConfig;
+use common::configuration::bot_runtime::BotRuntime;
 use common::models::aiarena::aiarena_match::AiArenaMatch;
-use common::models::bot_controller::BotType;
 use common::PlayerNum;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+pub mod options;
+pub mod player_kind;
 pub mod sources;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -13,25 +18,25 @@ pub struct MatchPlayer {
     pub id: String,
     pub name: String,
     pub race: BotRace,
-    pub bot_type: BotType,
+    pub kind: PlayerKind,
 }
 
 impl MatchPlayer {
-    pub fn from_aiarena_match(player_num: PlayerNum, ai_match: &AiArenaMatch) -> Self {
-        match player_num {
-            PlayerNum::One => Self {
-                id: ai_match.bot1.game_display_id.clone(),
-                name: ai_match.bot1.name.clone(),
-                race: BotRace::from_str(&ai_match.bot1.plays_race),
-                bot_type: BotType::from_str(&ai_match.bot1._type).unwrap(),
-            },
-            PlayerNum::Two => Self {
-                id: ai_match.bot2.game_display_id.clone(),
-                name: ai_match.bot2.name.clone(),
-                race: BotRace::from_str(&ai_match.bot2.plays_race),
-                bot_type: BotType::from_str(&ai_match.bot2._type).unwrap(),
-            },
-        }
+    pub fn from_aiarena_match(
+        player_num: PlayerNum,
+        ai_match: &AiArenaMatch,
+    ) -> Result<Self, String> {
+        let bot = match player_num {
+            PlayerNum::One => &ai_match.bot1,
+            PlayerNum::Two => &ai_match.bot2,
+        };
+        Ok(Self {
+            id: bot.game_display_id.clone(),
+            name: bot.name.clone(),
+            race: BotRace::from_str(&bot.plays_race),
+            kind: PlayerKind::from_str(&bot._type)
+                .map_err(|_| format!("Invalid type {:?} of bot {}", bot._type, bot.name))?,
+        })
     }
 
     pub fn from_file_source(bot_line: &[String]) -> Result<Self, FileMatchExtractError> {
@@ -49,7 +54,7 @@ impl MatchPlayer {
                     .get(2)
                     .ok_or_else(|| FileMatchExtractError::PlayerRace(bot_line.to_vec()))?,
             ),
-            bot_type: BotType::from_str(
+            kind: PlayerKind::from_str(
                 bot_line
                     .get(3)
                     .ok_or_else(|| FileMatchExtractError::PlayerType(bot_line.to_vec()))?,
@@ -64,27 +69,68 @@ pub struct Match {
     pub match_id: u32,
     pub players: HashMap<PlayerNum, MatchPlayer>,
     pub map_name: String,
+    pub options: MatchOptions,
     pub aiarena_match: Option<AiArenaMatch>,
 }
 
-impl From<AiArenaMatch> for Match {
-    fn from(ai_match: AiArenaMatch) -> Self {
+impl Match {
+    /// Fails if a bot has a type that is neither built in nor configured in `BOT_RUNTIMES`, as
+    /// it could not be started
+    pub fn check_bot_types(&self, settings: &ACConfig) -> Result<(), String> {
+        for player in self.players.values() {
+            if let Some(bot_type) = player.kind.bot_type() {
+                if BotRuntime::resolve(settings, &bot_type).is_none() {
+                    return Err(format!(
+                        "Bot {} has the type {bot_type}, which is not configured in BOT_RUNTIMES",
+                        player.name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn players_where(&self, predicate: impl Fn(&PlayerKind) -> bool) -> Vec<PlayerNum> {
+        [PlayerNum::One, PlayerNum::Two]
+            .into_iter()
+            .filter(|player_num| predicate(&self.players[player_num].kind))
+            .collect()
+    }
+    /// Players that are controlled by a bot, in player order
+    pub fn bot_players(&self) -> Vec<PlayerNum> {
+        self.players_where(PlayerKind::is_bot)
+    }
+    /// Players that join the game through the proxy, in player order
+    pub fn participants(&self) -> Vec<PlayerNum> {
+        self.players_where(|kind| !kind.is_computer())
+    }
+    /// The player controlled by a human, if any
+    pub fn human_player(&self) -> Option<PlayerNum> {
+        self.players_where(PlayerKind::is_human).first().copied()
+    }
+}
+
+impl TryFrom<AiArenaMatch> for Match {
+    type Error = String;
+
+    fn try_from(ai_match: AiArenaMatch) -> Result<Self, Self::Error> {
         let mut players = HashMap::with_capacity(2);
         players.insert(
             PlayerNum::One,
-            MatchPlayer::from_aiarena_match(PlayerNum::One, &ai_match),
+            MatchPlayer::from_aiarena_match(PlayerNum::One, &ai_match)?,
         );
 
         players.insert(
             PlayerNum::Two,
-            MatchPlayer::from_aiarena_match(PlayerNum::Two, &ai_match),
+            MatchPlayer::from_aiarena_match(PlayerNum::Two, &ai_match)?,
         );
 
-        Self {
+        Ok(Self {
             match_id: ai_match.id,
             players,
             map_name: ai_match.map.name.clone(),
+            options: MatchOptions::from_aiarena_match(&ai_match),
             aiarena_match: Some(ai_match),
-        }
+        })
     }
 }
@@ -0,0 +1,113 @@
+use crate::matches::sources::file_source::errors::FileMatchExtractError;
+use common::models::aiarena::aiarena_match::AiArenaMatch;
+use std::str::FromStr;
+
+/// Match-level options passed to `RequestCreateGame`.
+///
+/// Options that are not set fall back to the arena client configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    pub realtime: Option<bool>,
+    pub disable_fog: Option<bool>,
+    pub random_seed: Option<u32>,
+    /// Base build of the SC2 version to play on
+    pub sc2_version: Option<u32>,
+}
+
+impl MatchOptions {
+    pub fn from_aiarena_match(ai_match: &AiArenaMatch) -> Self {
+        Self {
+            realtime: ai_match.realtime,
+            disable_fog: ai_match.disable_fog,
+            random_seed: ai_match.random_seed,
+            sc2_version: None,
+        }
+    }
+
+    /// Parses the optional `key=value` fields at the end of a match line.
+    ///
+    /// Fields that are not in `key=value` form are reported as [`FileMatchExtractError::TooManyFields`]
+    pub fn from_file_source(fields: &[String]) -> Result<Self, FileMatchExtractError> {
+        let mut options = Self::default();
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| FileMatchExtractError::TooManyFields(fields.to_vec()))?;
+            let invalid = || FileMatchExtractError::MatchOption(vec![field.clone()]);
+            match key.trim() {
+                "realtime" => {
+                    options.realtime = Some(bool::from_str(value.trim()).map_err(|_| invalid())?);
+                }
+                "disable_fog" => {
+                    options.disable_fog =
+                        Some(bool::from_str(value.trim()).map_err(|_| invalid())?);
+                }
+                "random_seed" => {
+                    options.random_seed = Some(u32::from_str(value.trim()).map_err(|_| invalid())?);
+                }
+                "sc2_version" => {
+                    options.sc2_version = Some(u32::from_str(value.trim()).map_err(|_| invalid())?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_no_options() {
+        let options = MatchOptions::from_file_source(&[]).unwrap();
+        assert_eq!(options, MatchOptions::default());
+    }
+
+    #[test]
+    fn test_all_options() {
+        let options = MatchOptions::from_file_source(&fields(&[
+            "realtime=true",
+            "disable_fog=true",
+            "random_seed=42",
+            "sc2_version=75689",
+        ]))
+        .unwrap();
+        assert_eq!(options.realtime, Some(true));
+        assert_eq!(options.disable_fog, Some(true));
+        assert_eq!(options.random_seed, Some(42));
+        assert_eq!(options.sc2_version, Some(75689));
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        let options = MatchOptions::from_file_source(&fields(&["speed=fast"]));
+        assert!(matches!(
+            options,
+            Err(FileMatchExtractError::MatchOption { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_option_value() {
+        let options = MatchOptions::from_file_source(&fields(&["random_seed=-1"]));
+        assert!(matches!(
+            options,
+            Err(FileMatchExtractError::MatchOption { .. })
+        ));
+    }
+
+    #[test]
+    fn test_extra_field() {
+        let options = MatchOptions::from_file_source(&fields(&["AutomatonLE"]));
+        assert!(matches!(
+            options,
+            Err(FileMatchExtractError::TooManyFields { .. })
+        ));
+    }
+}
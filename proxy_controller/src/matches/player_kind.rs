@@ -0,0 +1,160 @@
+use common::models::bot_controller::BotType;
+use sc2_proto::sc2api::{AIBuild, Difficulty};
+use std::str::FromStr;
+
+/// Settings for the built-in SC2 computer opponent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComputerSettings {
+    pub difficulty: Difficulty,
+    pub build: AIBuild,
+}
+
+impl FromStr for ComputerSettings {
+    type Err = ();
+
+    /// Parses `<difficulty>[:<build>]`, e.g. `VeryHard:Macro`. The build defaults to a random build.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (difficulty, build) = s.split_once(':').unwrap_or((s, "RandomBuild"));
+        Ok(Self {
+            difficulty: difficulty_from_str(difficulty).ok_or(())?,
+            build: build_from_str(build).ok_or(())?,
+        })
+    }
+}
+
+/// What controls a player in a match
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlayerKind {
+    Bot(BotType),
+    Computer(ComputerSettings),
+    /// A human playing through their own SC2 client connected to the proxy
+    Human,
+}
+
+impl PlayerKind {
+    pub const fn is_bot(&self) -> bool {
+        matches!(self, Self::Bot(_))
+    }
+    pub const fn is_computer(&self) -> bool {
+        matches!(self, Self::Computer(_))
+    }
+    pub const fn is_human(&self) -> bool {
+        matches!(self, Self::Human)
+    }
+    pub fn bot_type(&self) -> Option<BotType> {
+        match self {
+            Self::Bot(bot_type) => Some(bot_type.clone()),
+            Self::Computer(_) | Self::Human => None,
+        }
+    }
+}
+
+impl FromStr for PlayerKind {
+    type Err = ();
+
+    /// Parses a bot type (e.g. `python`, or a type from `BOT_RUNTIMES`), a computer opponent
+    /// (e.g. `computer:VeryHard:Macro`) or `human`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("human") {
+            return Ok(Self::Human);
+        }
+        match s.split_once(':') {
+            Some((kind, settings)) if kind.eq_ignore_ascii_case("computer") => {
+                ComputerSettings::from_str(settings).map(Self::Computer)
+            }
+            // A computer opponent without settings, not a bot type
+            _ if s.eq_ignore_ascii_case("computer") => Err(()),
+            _ => BotType::from_str(s).map(Self::Bot),
+        }
+    }
+}
+
+fn difficulty_from_str(difficulty: &str) -> Option<Difficulty> {
+    match &difficulty.to_lowercase()[..] {
+        "veryeasy" => Some(Difficulty::VeryEasy),
+        "easy" => Some(Difficulty::Easy),
+        "medium" => Some(Difficulty::Medium),
+        "mediumhard" => Some(Difficulty::MediumHard),
+        "hard" => Some(Difficulty::Hard),
+        "harder" => Some(Difficulty::Harder),
+        "veryhard" => Some(Difficulty::VeryHard),
+        "cheatvision" => Some(Difficulty::CheatVision),
+        "cheatmoney" => Some(Difficulty::CheatMoney),
+        "cheatinsane" | "elite" => Some(Difficulty::CheatInsane),
+        _ => None,
+    }
+}
+
+fn build_from_str(build: &str) -> Option<AIBuild> {
+    match &build.to_lowercase()[..] {
+        "randombuild" | "random" => Some(AIBuild::RandomBuild),
+        "rush" => Some(AIBuild::Rush),
+        "timing" => Some(AIBuild::Timing),
+        "power" => Some(AIBuild::Power),
+        "macro" => Some(AIBuild::Macro),
+        "air" => Some(AIBuild::Air),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_kind() {
+        assert_eq!(
+            PlayerKind::from_str("python"),
+            Ok(PlayerKind::Bot(BotType::Python))
+        );
+        assert!(PlayerKind::from_str("python").unwrap().is_bot());
+    }
+
+    #[test]
+    fn test_computer_kind() {
+        let kind = PlayerKind::from_str("computer:VeryHard:Macro").unwrap();
+        assert_eq!(
+            kind,
+            PlayerKind::Computer(ComputerSettings {
+                difficulty: Difficulty::VeryHard,
+                build: AIBuild::Macro,
+            })
+        );
+        assert!(!kind.is_bot());
+        assert_eq!(kind.bot_type(), None);
+    }
+
+    #[test]
+    fn test_computer_kind_default_build() {
+        let kind = PlayerKind::from_str("Computer:CheatInsane").unwrap();
+        assert_eq!(
+            kind,
+            PlayerKind::Computer(ComputerSettings {
+                difficulty: Difficulty::CheatInsane,
+                build: AIBuild::RandomBuild,
+            })
+        );
+    }
+
+    #[test]
+    fn test_human_kind() {
+        let kind = PlayerKind::from_str("Human").unwrap();
+        assert!(kind.is_human());
+        assert!(!kind.is_bot());
+        assert!(!kind.is_computer());
+    }
+
+    #[test]
+    fn test_invalid_kind() {
+        assert!(PlayerKind::from_str("computer").is_err());
+        assert!(PlayerKind::from_str("computer:Impossible").is_err());
+        assert!(PlayerKind::from_str("computer:Easy:Turtle").is_err());
+        assert!(PlayerKind::from_str("co bol").is_err());
+    }
+
+    #[test]
+    fn test_custom_bot_kind() {
+        let kind = PlayerKind::from_str("Rust").unwrap();
+        assert_eq!(kind.bot_type(), Some(BotType::Custom("rust".to_string())));
+    }
+}
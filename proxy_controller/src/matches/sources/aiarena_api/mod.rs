@@ -20,6 +20,7 @@ use tracing::{debug, info};
 
 pub struct HttpApiSource {
     api: AiArenaApiClient,
+    settings: ACConfig,
 }
 
 impl HttpApiSource {
@@ -34,7 +35,7 @@ impl HttpApiSource {
                 &settings.base_website_url, e
             )
         })?;
-        Ok(Self { api })
+        Ok(Self { api, settings })
     }
     async fn download_map(
         &self,
@@ -58,10 +59,17 @@ impl MatchSource for HttpApiSource {
     }
 
     async fn next_match(&self) -> Option<Match> {
-        match self.api.get_match().await {
-            Ok(m) => Some(Match::from(m)),
+        let ai_match = match self.api.get_match().await {
+            Ok(m) => m,
             Err(err) => {
                 error!("{:?}", err);
+                return None;
+            }
+        };
+        match Match::try_from(ai_match).and_then(|m| m.check_bot_types(&self.settings).map(|_| m)) {
+            Ok(m) => Some(m),
+            Err(e) => {
+                error!("Could not start match: {}", e);
                 None
             }
         }
@@ -93,7 +101,10 @@ impl MatchSource for HttpApiSource {
         let bot1_log = get_file_and_filename(&bot1_dir.join("logs.zip")).await;
         let bot2_log = get_file_and_filename(&bot2_dir.join("logs.zip")).await;
 
-        let replay = get_file_and_filename(&replay_file).await;
+        let replay = match &replay_file {
+            Some(replay_file) => get_file_and_filename(replay_file).await,
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        };
         let arenaclient_logs = get_file_and_filename(&arenaclient_log).await;
 
         if let Ok(ref x) = bot1_data {
@@ -9,6 +9,7 @@ pub enum FileMatchExtractError {
     MapName(Vec<String>),
     TooManyFields(Vec<String>),
     MissingFields(Vec<String>),
+    MatchOption(Vec<String>),
 }
 
 impl Display for FileMatchExtractError {
@@ -21,6 +22,7 @@ impl Display for FileMatchExtractError {
             Self::TooManyFields(vec) => ("Too many fields in line", vec),
             Self::MissingFields(vec) => ("Not enough fields in line", vec),
             Self::PlayerType(vec) => ("Could not extract player 1 type", vec),
+            Self::MatchOption(vec) => ("Invalid match option", vec),
         };
         write!(f, "{e} in {vec:?}")
     }
@@ -1,5 +1,6 @@
 pub mod errors;
 
+use crate::matches::options::MatchOptions;
 use crate::matches::sources::file_source::errors::{FileMatchExtractError, SubmissionError};
 use crate::matches::sources::{LogsAndReplays, MatchSource};
 use crate::matches::{Match, MatchPlayer};
@@ -8,7 +9,6 @@ use common::configuration::ac_config::ACConfig;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::PlayerNum;
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, BufWriter, Lines, Read, Seek, Write};
@@ -109,7 +109,10 @@ impl MatchSource for FileSource {
         if let Ok(lines) = self.read_matches_file() {
             for line in lines.flatten() {
                 if !line.is_empty() && !line.starts_with('#') {
-                    return match extract_match(&line) {
+                    let extracted = extract_match(&line)
+                        .map_err(|e| e.to_string())
+                        .and_then(|m| m.check_bot_types(&self.settings).map(|_| m));
+                    return match extracted {
                         Ok(mut m) => {
                             m.match_id =
                                 Self::get_current_match_id(&self.settings.results_file) + 1;
@@ -163,15 +166,10 @@ fn extract_match(line: &str) -> Result<Match, FileMatchExtractError> {
         .map(std::string::ToString::to_string)
         .collect();
 
-    match vec_line.len().cmp(&9) {
-        Ordering::Greater => {
-            return Err(FileMatchExtractError::TooManyFields(vec_line));
-        }
-        Ordering::Less => {
-            return Err(FileMatchExtractError::MissingFields(vec_line));
-        }
-        Ordering::Equal => {}
+    if vec_line.len() < 9 {
+        return Err(FileMatchExtractError::MissingFields(vec_line));
     }
+    let options = MatchOptions::from_file_source(&vec_line.split_off(9))?;
 
     let bot1: Vec<String> = vec_line.drain(0..4).collect();
     let bot2: Vec<String> = vec_line.drain(0..4).collect();
@@ -188,6 +186,7 @@ fn extract_match(line: &str) -> Result<Match, FileMatchExtractError> {
         match_id: 0,
         players,
         map_name,
+        options,
         aiarena_match: None,
     })
 }
@@ -196,6 +195,8 @@ fn extract_match(line: &str) -> Result<Match, FileMatchExtractError> {
 mod tests {
     use crate::game::race::BotRace;
     use crate::matches::sources::file_source::{extract_match, FileMatchExtractError};
+    use common::configuration::ac_config::ACConfig;
+    use common::configuration::bot_runtime::BotRuntime;
     use common::PlayerNum;
 
     #[test]
@@ -213,6 +214,62 @@ mod tests {
         assert_eq!(m.map_name, "AutomatonLE");
     }
 
+    #[test]
+    pub fn test_match_extracts_options() {
+        let m = extract_match(
+            "bot-id-1,basic_bot,T,python,bot-id-2,loser_bot,P,python,AutomatonLE,disable_fog=true,random_seed=7",
+        )
+        .unwrap();
+        assert_eq!(m.map_name, "AutomatonLE");
+        assert_eq!(m.options.disable_fog, Some(true));
+        assert_eq!(m.options.random_seed, Some(7));
+        assert_eq!(m.options.realtime, None);
+    }
+
+    #[test]
+    pub fn test_match_extracts_computer_opponent() {
+        let m = extract_match(
+            "bot-id-1,basic_bot,T,python,computer,Elite AI,Z,computer:CheatInsane:Macro,AutomatonLE",
+        )
+        .unwrap();
+        assert!(m.players[&PlayerNum::One].kind.is_bot());
+        assert!(m.players[&PlayerNum::Two].kind.is_computer());
+        assert_eq!(m.players[&PlayerNum::Two].race, BotRace::Zerg);
+        assert_eq!(m.bot_players(), vec![PlayerNum::One]);
+    }
+
+    #[test]
+    pub fn test_match_extracts_human_player() {
+        let m =
+            extract_match("human,Player,R,human,bot-id-2,loser_bot,P,python,AutomatonLE").unwrap();
+        assert_eq!(m.human_player(), Some(PlayerNum::One));
+        assert_eq!(m.bot_players(), vec![PlayerNum::Two]);
+        assert_eq!(m.participants(), vec![PlayerNum::One, PlayerNum::Two]);
+    }
+
+    #[test]
+    pub fn test_match_checks_custom_bot_types() {
+        let m = extract_match("bot-id-1,basic_bot,T,rust,bot-id-2,loser_bot,P,python,AutomatonLE")
+            .unwrap();
+        let mut settings = ACConfig::default();
+        assert!(m.check_bot_types(&settings).is_err());
+        settings
+            .bot_runtimes
+            .insert("rust".to_string(), BotRuntime::default());
+        assert!(m.check_bot_types(&settings).is_ok());
+    }
+
+    #[test]
+    pub fn test_match_extracts_invalid_option() {
+        let m = extract_match(
+            "bot-id-1,basic_bot,T,python,bot-id-2,loser_bot,P,python,AutomatonLE,random_seed=abc",
+        );
+        assert!(matches!(
+            m.err().unwrap(),
+            FileMatchExtractError::MatchOption { .. }
+        ));
+    }
+
     #[test]
     pub fn test_match_extracts_invalid_missing_field() {
         let m = extract_match("basic_bot,T,python,bot-id-2,loser_bot,P,python,AutomatonLE");
@@ -1,5 +1,6 @@
 use crate::matches::Match;
 use async_trait::async_trait;
+use common::models::aiarena::aiarena_end_reason::EndReason;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_result::AiArenaResult;
 use std::path::PathBuf;
@@ -49,8 +50,14 @@ impl From<&GameResult> for AiArenaGameResult {
         let mut game_steps = 0;
         let mut bot1_avg_step_time = None;
         let mut bot1_tags = None;
+        let mut bot1_step_time_stats = None;
+        let mut bot1_realtime_stats = None;
+        let mut bot1_end_reason = None;
         let mut bot2_avg_step_time = None;
         let mut bot2_tags = None;
+        let mut bot2_step_time_stats = None;
+        let mut bot2_realtime_stats = None;
+        let mut bot2_end_reason = None;
         let mut p1_result = None;
         let mut p2_result = None;
 
@@ -60,6 +67,9 @@ impl From<&GameResult> for AiArenaGameResult {
                 debug!("Player1Result: {:?}", player1_result);
                 bot1_avg_step_time = Some(player1_result.frame_time);
                 bot1_tags = Some(player1_result.tags.iter().cloned().collect());
+                bot1_step_time_stats = player1_result.step_time_stats;
+                bot1_realtime_stats = player1_result.realtime_stats;
+                bot1_end_reason = Some(player1_result.end_reason);
                 game_steps = player1_result.game_loops;
                 p1_result = Some(player1_result.result);
             }
@@ -70,10 +80,19 @@ impl From<&GameResult> for AiArenaGameResult {
                 debug!("Player2Result: {:?}", player2_result);
                 bot2_avg_step_time = Some(player2_result.frame_time);
                 bot2_tags = Some(player2_result.tags.iter().cloned().collect());
+                bot2_step_time_stats = player2_result.step_time_stats;
+                bot2_realtime_stats = player2_result.realtime_stats;
+                bot2_end_reason = Some(player2_result.end_reason);
                 game_steps = player2_result.game_loops;
                 p2_result = Some(player2_result.result);
             }
         }
+        // A disconnect after the game ended says nothing about the outcome
+        let outcome = |result: Option<Sc2Result>, end_reason: Option<EndReason>| {
+            result.filter(|_| end_reason != Some(EndReason::DisconnectAfterGameEnd))
+        };
+        let p1_result = outcome(p1_result, bot1_end_reason);
+        let p2_result = outcome(p2_result, bot2_end_reason);
         let result = game_result
             .result
             .unwrap_or_else(|| match (p1_result, p2_result) {
@@ -91,6 +110,7 @@ impl From<&GameResult> for AiArenaGameResult {
                 (_, Some(Sc2Result::Victory)) | (Some(Sc2Result::Defeat), _) => {
                     AiArenaResult::Player2Win
                 }
+                (None, None) => AiArenaResult::Error,
                 #[cfg(test)]
                 (Some(Sc2Result::Placeholder), Some(Sc2Result::Placeholder)) => unreachable!(),
                 (_, _) => unreachable!(),
@@ -99,8 +119,16 @@ impl From<&GameResult> for AiArenaGameResult {
             match_id: game_result.match_id,
             bot1_avg_step_time,
             bot1_tags,
+            bot1_step_time_stats,
+            bot1_realtime_stats,
+            bot1_end_reason,
+            bot1_limit_violations: Vec::new(),
             bot2_avg_step_time,
             bot2_tags,
+            bot2_step_time_stats,
+            bot2_realtime_stats,
+            bot2_end_reason,
+            bot2_limit_violations: Vec::new(),
             result,
             game_steps,
         }
@@ -115,7 +143,7 @@ pub struct LogsAndReplays {
     pub bot1_dir: PathBuf,
     pub bot2_dir: PathBuf,
     pub arenaclient_log: PathBuf,
-    pub replay_file: PathBuf,
+    pub replay_file: Option<PathBuf>,
 }
 
 #[cfg(test)]
@@ -124,7 +152,10 @@ mod tests {
     use crate::game::player_result::PlayerResult;
     use crate::game::sc2_result::Sc2Result;
     use crate::matches::sources::AiArenaGameResult;
+    use common::models::aiarena::aiarena_end_reason::EndReason;
     use common::models::aiarena::aiarena_result::AiArenaResult;
+    use common::models::stats::{RealtimeStats, StepTimeStats};
+    use common::PlayerNum;
 
     fn game_result() -> GameResult {
         GameResult {
@@ -134,16 +165,29 @@ mod tests {
                 frame_time: 0.0,
                 player_id: 0,
                 tags: Default::default(),
+                step_time_stats: None,
+                realtime_stats: None,
+                annotations: None,
+                actual_races: Default::default(),
+                summary: None,
                 result: Sc2Result::Placeholder,
+                end_reason: EndReason::Normal,
             }),
             player2_result: Some(PlayerResult {
                 game_loops: 0,
                 frame_time: 0.0,
                 player_id: 0,
                 tags: Default::default(),
+                step_time_stats: None,
+                realtime_stats: None,
+                annotations: None,
+                actual_races: Default::default(),
+                summary: None,
                 result: Sc2Result::Placeholder,
+                end_reason: EndReason::Normal,
             }),
             result: Some(AiArenaResult::Placeholder),
+            sc2_start_failed: false,
         }
     }
 
@@ -205,6 +249,19 @@ mod tests {
         assert_eq!(serialized["type"], "Player2TimeOut");
     }
 
+    #[test]
+    fn test_result_serialization_computer_opponent() {
+        let mut game_result = game_result();
+        game_result.player1_result.as_mut().unwrap().result = Sc2Result::Defeat;
+        game_result.player2_result = None;
+        game_result.result = None;
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["type"], "Player2Win");
+        assert!(serialized.get("bot2_avg_step_time").is_none());
+    }
+
     #[test]
     fn test_result_serialization_tie() {
         let mut game_result = game_result();
@@ -253,6 +310,53 @@ mod tests {
         assert_eq!(serialized["type"], "Player2Crash");
     }
 
+    #[test]
+    fn test_result_serialization_p1_surrender() {
+        let mut game_result = game_result();
+        let player1_result = game_result.player1_result.as_mut().unwrap();
+        player1_result.result = Sc2Result::Defeat;
+        player1_result.end_reason = EndReason::Surrender;
+        game_result.player2_result.as_mut().unwrap().result = Sc2Result::Victory;
+        game_result.result = None;
+        assert_eq!(game_result.surrendered_player(), Some(PlayerNum::One));
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["type"], "Player2Win");
+        assert_eq!(serialized["bot1_end_reason"], "Surrender");
+        assert_eq!(serialized["bot2_end_reason"], "Normal");
+    }
+
+    #[test]
+    fn test_result_serialization_disconnect_after_game_end() {
+        let mut game_result = game_result();
+        game_result.player1_result.as_mut().unwrap().result = Sc2Result::Victory;
+        let player2_result = game_result.player2_result.as_mut().unwrap();
+        player2_result.result = Sc2Result::Crash;
+        player2_result.end_reason = EndReason::DisconnectAfterGameEnd;
+        game_result.result = None;
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["type"], "Player1Win");
+        assert_eq!(serialized["bot2_end_reason"], "DisconnectAfterGameEnd");
+    }
+
+    #[test]
+    fn test_result_serialization_init_crash() {
+        let mut game_result = game_result();
+        let player1_result = game_result.player1_result.as_mut().unwrap();
+        player1_result.result = Sc2Result::Crash;
+        player1_result.end_reason = EndReason::InitCrash;
+        game_result.player2_result = None;
+        game_result.result = None;
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["type"], "Player1Crash");
+        assert_eq!(serialized["bot1_end_reason"], "InitCrash");
+    }
+
     #[test]
     fn test_result_serialization_match_id() {
         let mut game_result = game_result();
@@ -264,6 +368,40 @@ mod tests {
         assert_eq!(serialized["match"], match_id);
     }
 
+    #[test]
+    fn test_result_serialization_step_time_stats() {
+        let mut game_result = game_result();
+        game_result.player1_result.as_mut().unwrap().step_time_stats = Some(StepTimeStats {
+            steps: 100,
+            p50: 0.01,
+            p90: 0.02,
+            p99: 0.05,
+            max: 0.1,
+            over_max_frame_time: 3,
+        });
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["bot1_step_time_stats"]["steps"], 100);
+        assert_eq!(serialized["bot1_step_time_stats"]["over_max_frame_time"], 3);
+        assert!(serialized.get("bot2_step_time_stats").is_none());
+    }
+
+    #[test]
+    fn test_result_serialization_realtime_stats() {
+        let mut game_result = game_result();
+        game_result.player2_result.as_mut().unwrap().realtime_stats = Some(RealtimeStats {
+            missed_game_loops: 12,
+            max_lag_loops: 4.5,
+            avg_lag_loops: 1.5,
+        });
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["bot2_realtime_stats"]["missed_game_loops"], 12);
+        assert!(serialized.get("bot1_realtime_stats").is_none());
+    }
+
     // #[test]
     // fn test_game_result_serialization() {
     //     let match_id = 9999;
@@ -1,3 +1,4 @@
+use crate::matches::options::MatchOptions;
 use crate::matches::sources::file_source::errors::{FileMatchExtractError, SubmissionError};
 use crate::matches::sources::file_source::open_results_file;
 use crate::matches::sources::{LogsAndReplays, MatchSource};
@@ -9,7 +10,6 @@ use common::models::aiarena::aiarena_result::AiArenaResult;
 use common::PlayerNum;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Lines, Read, Seek, Write};
@@ -129,7 +129,13 @@ impl MatchSource for TestSource {
         if let Ok(lines) = self.read_matches_file() {
             for line in lines.flatten() {
                 if !line.is_empty() && !line.starts_with('#') {
-                    return match extract_match(&line) {
+                    let extracted = extract_match(&line).map_err(|e| e.to_string()).and_then(
+                        |(m, expected_result)| {
+                            m.check_bot_types(&self.settings)
+                                .map(|_| (m, expected_result))
+                        },
+                    );
+                    return match extracted {
                         Ok((mut m, expected_result)) => {
                             m.match_id =
                                 Self::get_current_match_id(&self.settings.results_file) + 1;
@@ -180,15 +186,10 @@ fn extract_match(line: &str) -> Result<(Match, AiArenaResult), FileMatchExtractE
         .map(std::string::ToString::to_string)
         .collect();
 
-    match vec_line.len().cmp(&10) {
-        Ordering::Greater => {
-            return Err(FileMatchExtractError::TooManyFields(vec_line));
-        }
-        Ordering::Less => {
-            return Err(FileMatchExtractError::MissingFields(vec_line));
-        }
-        Ordering::Equal => {}
+    if vec_line.len() < 10 {
+        return Err(FileMatchExtractError::MissingFields(vec_line));
     }
+    let options = MatchOptions::from_file_source(&vec_line.split_off(10))?;
 
     let bot1: Vec<String> = vec_line.drain(0..4).collect();
     let bot2: Vec<String> = vec_line.drain(0..4).collect();
@@ -210,6 +211,7 @@ fn extract_match(line: &str) -> Result<(Match, AiArenaResult), FileMatchExtractE
             match_id: 0,
             players,
             map_name,
+            options,
             aiarena_match: None,
         },
         expected_result,
@@ -0,0 +1,43 @@
+use common::configuration::ac_config::{ACConfig, RunType};
+use common::preflight::{
+    check_file, check_writable_directory, common_checks, PreflightCheck, PreflightReport,
+};
+use std::path::Path;
+
+pub fn run_checks(settings: &ACConfig) -> PreflightReport {
+    let mut checks = common_checks(settings);
+    checks.push(check_writable_directory(
+        "Replays directory",
+        Path::new(&settings.replays_directory),
+    ));
+    match settings.run_type {
+        RunType::Local => {
+            checks.push(check_file(
+                "Matches file",
+                Path::new(&settings.matches_file),
+            ));
+        }
+        RunType::AiArena => {
+            checks.push(check_setting(
+                "API token",
+                settings.api_token.as_deref().unwrap_or_default(),
+                "Set API_TOKEN to the token of this arena client",
+            ));
+            checks.push(check_setting(
+                "Website URL",
+                &settings.base_website_url,
+                "Set BASE_WEBSITE_URL to the AI Arena website",
+            ));
+        }
+        RunType::Test | RunType::Mock => {}
+    }
+    PreflightReport::new("proxy_controller", checks)
+}
+
+fn check_setting(name: &str, value: &str, fix: &str) -> PreflightCheck {
+    if value.trim().is_empty() {
+        PreflightCheck::failed(name, "Not set", fix)
+    } else {
+        PreflightCheck::ok(name, "Set")
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    NotMpq,
+    Truncated,
+    InvalidHeader(&'static str),
+    Serialization(serde_json::Error),
+    NoValidReplay,
+    ReplayInfo(String),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Error while accessing replay file: {e}"),
+            Self::NotMpq => write!(f, "Replay is not an MPQ archive"),
+            Self::Truncated => write!(f, "Replay is truncated"),
+            Self::InvalidHeader(reason) => write!(f, "Invalid replay header: {reason}"),
+            Self::Serialization(e) => write!(f, "Error while serializing replay metadata: {e}"),
+            Self::NoValidReplay => write!(f, "No valid replay was saved by any player"),
+            Self::ReplayInfo(reason) => write!(f, "Could not decode the replay info: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
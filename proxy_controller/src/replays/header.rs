@@ -0,0 +1,343 @@
+//! Decoding of the replay header stored in the MPQ user data of an SC2 replay
+
+use crate::replays::errors::ReplayError;
+use std::collections::BTreeMap;
+
+const MPQ_USER_DATA_MAGIC: &[u8; 4] = b"MPQ\x1b";
+const MPQ_HEADER_MAGIC: &[u8; 4] = b"MPQ\x1a";
+const REPLAY_SIGNATURE: &[u8] = b"StarCraft II replay";
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub revision: u32,
+    pub build: u32,
+    pub base_build: u32,
+}
+
+impl std::fmt::Display for ReplayVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.revision, self.build
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayHeader {
+    pub version: ReplayVersion,
+    pub elapsed_game_loops: u32,
+}
+
+/// Validates the MPQ structure of a replay and decodes its header
+pub fn parse_replay_header(bytes: &[u8]) -> Result<ReplayHeader, ReplayError> {
+    if bytes.get(0..4) != Some(MPQ_USER_DATA_MAGIC) {
+        return Err(ReplayError::NotMpq);
+    }
+    let header_offset = read_u32_le(bytes, 8)? as usize;
+    let user_data_header_size = read_u32_le(bytes, 12)? as usize;
+    if bytes.get(header_offset..header_offset + 4) != Some(MPQ_HEADER_MAGIC) {
+        return Err(ReplayError::NotMpq);
+    }
+    let content = bytes
+        .get(16..16 + user_data_header_size)
+        .ok_or(ReplayError::Truncated)?;
+
+    let header = VersionedDecoder::new(content).decode(0)?;
+    let signature = header.field(0).and_then(Value::as_blob);
+    if !signature.map_or(false, |s| s.starts_with(REPLAY_SIGNATURE)) {
+        return Err(ReplayError::InvalidHeader("missing signature"));
+    }
+    let version = header
+        .field(1)
+        .ok_or(ReplayError::InvalidHeader("missing version"))?;
+    let version_field = |tag| {
+        version
+            .field(tag)
+            .and_then(Value::as_u32)
+            .ok_or(ReplayError::InvalidHeader("invalid version"))
+    };
+    Ok(ReplayHeader {
+        version: ReplayVersion {
+            major: version_field(1)?,
+            minor: version_field(2)?,
+            revision: version_field(3)?,
+            build: version_field(4)?,
+            base_build: version_field(5)?,
+        },
+        elapsed_game_loops: header
+            .field(3)
+            .and_then(Value::as_u32)
+            .ok_or(ReplayError::InvalidHeader("missing elapsed game loops"))?,
+    })
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Result<u32, ReplayError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(ReplayError::Truncated)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int(i64),
+    Blob(Vec<u8>),
+    Array(Vec<Value>),
+    Struct(BTreeMap<i64, Value>),
+    Choice(i64, Box<Value>),
+    Optional(Option<Box<Value>>),
+}
+
+impl Value {
+    fn field(&self, tag: i64) -> Option<&Self> {
+        match self {
+            Self::Struct(fields) => fields.get(&tag),
+            _ => None,
+        }
+    }
+    fn as_u32(&self) -> Option<u32> {
+        match self {
+            Self::Int(value) => u32::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+    fn as_blob(&self) -> Option<&[u8]> {
+        match self {
+            Self::Blob(blob) => Some(blob),
+            _ => None,
+        }
+    }
+}
+
+/// Decoder for the self-describing "versioned" serialization format used by SC2
+struct VersionedDecoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> VersionedDecoder<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ReplayError> {
+        let end = self
+            .position
+            .checked_add(count)
+            .ok_or(ReplayError::Truncated)?;
+        let slice = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(ReplayError::Truncated)?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_vint(&mut self) -> Result<i64, ReplayError> {
+        let mut byte = i64::from(self.read_u8()?);
+        let negative = byte & 1 != 0;
+        let mut result = (byte >> 1) & 0x3f;
+        let mut bits = 6;
+        while byte & 0x80 != 0 {
+            if bits > 62 {
+                return Err(ReplayError::InvalidHeader("integer overflow"));
+            }
+            byte = i64::from(self.read_u8()?);
+            result |= (byte & 0x7f) << bits;
+            bits += 7;
+        }
+        Ok(if negative { -result } else { result })
+    }
+
+    fn read_len(&mut self) -> Result<usize, ReplayError> {
+        usize::try_from(self.read_vint()?)
+            .map_err(|_| ReplayError::InvalidHeader("negative length"))
+    }
+
+    fn decode(&mut self, depth: usize) -> Result<Value, ReplayError> {
+        if depth > MAX_DEPTH {
+            return Err(ReplayError::InvalidHeader("nesting too deep"));
+        }
+        match self.read_u8()? {
+            0x00 => {
+                let len = self.read_len()?;
+                let items = (0..len)
+                    .map(|_| self.decode(depth + 1))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::Array(items))
+            }
+            0x01 => {
+                let bits = self.read_len()?;
+                Ok(Value::Blob(self.take((bits + 7) / 8)?.to_vec()))
+            }
+            0x02 => {
+                let len = self.read_len()?;
+                Ok(Value::Blob(self.take(len)?.to_vec()))
+            }
+            0x03 => {
+                let tag = self.read_vint()?;
+                Ok(Value::Choice(tag, Box::new(self.decode(depth + 1)?)))
+            }
+            0x04 => match self.read_u8()? {
+                0 => Ok(Value::Optional(None)),
+                _ => Ok(Value::Optional(Some(Box::new(self.decode(depth + 1)?)))),
+            },
+            0x05 => {
+                let len = self.read_len()?;
+                let mut fields = BTreeMap::new();
+                for _ in 0..len {
+                    let tag = self.read_vint()?;
+                    fields.insert(tag, self.decode(depth + 1)?);
+                }
+                Ok(Value::Struct(fields))
+            }
+            0x06 => Ok(Value::Int(i64::from(self.read_u8()?))),
+            0x07 => {
+                let b = self.take(4)?;
+                Ok(Value::Int(i64::from(u32::from_le_bytes([
+                    b[0], b[1], b[2], b[3],
+                ]))))
+            }
+            0x08 => {
+                let b = self.take(8)?;
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(b);
+                Ok(Value::Int(i64::from_le_bytes(buf)))
+            }
+            0x09 => Ok(Value::Int(self.read_vint()?)),
+            _ => Err(ReplayError::InvalidHeader("unknown value type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vint(value: i64) -> Vec<u8> {
+        let mut magnitude = value.unsigned_abs();
+        let mut first = ((magnitude & 0x3f) << 1) as u8 | u8::from(value < 0);
+        magnitude >>= 6;
+        let mut out = vec![];
+        if magnitude > 0 {
+            first |= 0x80;
+        }
+        out.push(first);
+        while magnitude > 0 {
+            let mut byte = (magnitude & 0x7f) as u8;
+            magnitude >>= 7;
+            if magnitude > 0 {
+                byte |= 0x80;
+            }
+            out.push(byte);
+        }
+        out
+    }
+
+    fn int(value: i64) -> Vec<u8> {
+        let mut out = vec![0x09];
+        out.extend(vint(value));
+        out
+    }
+
+    fn blob(bytes: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x02];
+        out.extend(vint(bytes.len() as i64));
+        out.extend(bytes);
+        out
+    }
+
+    fn structure(fields: Vec<(i64, Vec<u8>)>) -> Vec<u8> {
+        let mut out = vec![0x05];
+        out.extend(vint(fields.len() as i64));
+        for (tag, value) in fields {
+            out.extend(vint(tag));
+            out.extend(value);
+        }
+        out
+    }
+
+    /// Builds the start of a replay file with the given build and game length
+    fn replay_bytes(build: i64, elapsed_game_loops: i64) -> Vec<u8> {
+        let content = structure(vec![
+            (0, blob(b"StarCraft II replay\x1b11")),
+            (
+                1,
+                structure(vec![
+                    (0, int(1)),
+                    (1, int(5)),
+                    (2, int(0)),
+                    (3, int(11)),
+                    (4, int(build)),
+                    (5, int(build)),
+                ]),
+            ),
+            (2, int(2)),
+            (3, int(elapsed_game_loops)),
+            (4, vec![0x06, 1]),
+        ]);
+        let header_offset = 1024u32;
+        let mut out = Vec::with_capacity(header_offset as usize + 32);
+        out.extend(MPQ_USER_DATA_MAGIC);
+        out.extend(512u32.to_le_bytes());
+        out.extend(header_offset.to_le_bytes());
+        out.extend((content.len() as u32).to_le_bytes());
+        out.extend(&content);
+        out.resize(header_offset as usize, 0);
+        out.extend(MPQ_HEADER_MAGIC);
+        out.extend([0u8; 28]);
+        out
+    }
+
+    #[test]
+    fn test_vint_roundtrip() {
+        for value in [0, 1, -1, 63, 64, -64, 1_000_000, -987_654_321] {
+            let bytes = vint(value);
+            assert_eq!(VersionedDecoder::new(&bytes).read_vint().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_parse_replay_header() {
+        let header = parse_replay_header(&replay_bytes(81102, 13440)).unwrap();
+        assert_eq!(header.elapsed_game_loops, 13440);
+        assert_eq!(header.version.build, 81102);
+        assert_eq!(header.version.base_build, 81102);
+        assert_eq!(header.version.to_string(), "5.0.11.81102");
+    }
+
+    #[test]
+    fn test_not_mpq() {
+        assert!(matches!(
+            parse_replay_header(b"not a replay at all"),
+            Err(ReplayError::NotMpq)
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = replay_bytes(81102, 13440);
+        assert!(parse_replay_header(&bytes[..40]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let mut bytes = replay_bytes(81102, 13440);
+        // Corrupt the "StarCraft" signature
+        let position = bytes.windows(9).position(|w| w == b"StarCraft").unwrap();
+        bytes[position] = b'X';
+        assert!(matches!(
+            parse_replay_header(&bytes),
+            Err(ReplayError::InvalidHeader(_))
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+//! Players and map of a replay, as decoded by SC2 itself
+
+use crate::game::race::BotRace;
+use crate::replays::errors::ReplayError;
+use crate::state::SC2Url;
+use crate::ws_routes::connect;
+use futures_util::{SinkExt, StreamExt};
+use protobuf::Message;
+use sc2_proto::sc2api::{Request, RequestReplayInfo, Response, ResponseReplayInfo};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message as TMessage;
+
+const REPLAY_INFO_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayInfoPlayer {
+    pub name: String,
+    pub race: BotRace,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayInfo {
+    pub map: String,
+    /// Keyed by the in-game player id
+    pub players: HashMap<u32, ReplayInfoPlayer>,
+}
+
+impl ReplayInfo {
+    pub fn from_response(response: &ResponseReplayInfo) -> Self {
+        Self {
+            map: response.map_name().to_string(),
+            players: response
+                .player_info
+                .iter()
+                .map(|extra| {
+                    let info = &extra.player_info;
+                    let player = ReplayInfoPlayer {
+                        name: info.player_name().to_string(),
+                        race: BotRace::from_race(info.race_actual()),
+                    };
+                    (info.player_id(), player)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Asks the SC2 instance behind `sc2_url` to decode `replay`
+pub async fn query_replay_info(
+    sc2_url: &SC2Url,
+    replay: Vec<u8>,
+) -> Result<ReplayInfo, ReplayError> {
+    timeout(REPLAY_INFO_TIMEOUT, request_replay_info(sc2_url, replay))
+        .await
+        .map_err(|_| ReplayError::ReplayInfo("SC2 did not answer in time".to_string()))?
+}
+
+async fn request_replay_info(sc2_url: &SC2Url, replay: Vec<u8>) -> Result<ReplayInfo, ReplayError> {
+    let mut sc2_ws = connect(sc2_url)
+        .await
+        .ok_or_else(|| ReplayError::ReplayInfo("could not connect to SC2".to_string()))?;
+
+    let mut replay_info = RequestReplayInfo::new();
+    replay_info.set_replay_data(replay);
+    let mut request = Request::new();
+    request.set_replay_info(replay_info);
+    let bytes = request.write_to_bytes().expect("Invalid protobuf message");
+    sc2_ws
+        .send(TMessage::binary(bytes))
+        .await
+        .map_err(|e| ReplayError::ReplayInfo(e.to_string()))?;
+
+    let response = match sc2_ws.next().await {
+        Some(Ok(TMessage::Binary(bytes))) => Response::parse_from_bytes(&bytes)
+            .map_err(|e| ReplayError::ReplayInfo(e.to_string()))?,
+        Some(Ok(other)) => {
+            return Err(ReplayError::ReplayInfo(format!(
+                "unexpected message {other:?}"
+            )))
+        }
+        Some(Err(e)) => return Err(ReplayError::ReplayInfo(e.to_string())),
+        None => return Err(ReplayError::ReplayInfo("connection closed".to_string())),
+    };
+    let _ = sc2_ws.close(None).await;
+
+    if !response.has_replay_info() {
+        return Err(ReplayError::ReplayInfo(format!(
+            "no replay info in response: {:?}",
+            response.error
+        )));
+    }
+    let replay_info = response.replay_info();
+    if replay_info.has_error() {
+        return Err(ReplayError::ReplayInfo(format!(
+            "{:?}: {}",
+            replay_info.error(),
+            replay_info.error_details()
+        )));
+    }
+    Ok(ReplayInfo::from_response(replay_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::MessageField;
+    use sc2_proto::common::Race;
+    use sc2_proto::sc2api::{PlayerInfo, PlayerInfoExtra};
+
+    #[test]
+    fn test_replay_info_from_response() {
+        let player = |player_id, name: &str, race| {
+            let mut info = PlayerInfo::new();
+            info.set_player_id(player_id);
+            info.set_player_name(name.to_string());
+            info.set_race_requested(Race::Random);
+            info.set_race_actual(race);
+            let mut extra = PlayerInfoExtra::new();
+            extra.player_info = MessageField::some(info);
+            extra
+        };
+        let mut response = ResponseReplayInfo::new();
+        response.set_map_name("Automaton LE".to_string());
+        response.player_info = vec![
+            player(1, "basic_bot", Race::Protoss),
+            player(2, "loser_bot", Race::Terran),
+        ];
+
+        let info = ReplayInfo::from_response(&response);
+        assert_eq!(info.map, "Automaton LE");
+        assert_eq!(
+            info.players[&1],
+            ReplayInfoPlayer {
+                name: "basic_bot".to_string(),
+                race: BotRace::Protoss
+            }
+        );
+        assert_eq!(info.players[&2].race, BotRace::Terran);
+    }
+}
@@ -0,0 +1,491 @@
+//! Post-match processing of the replays saved by the players
+
+pub mod errors;
+pub mod header;
+pub mod info;
+
+use crate::game::game_config::GameConfig;
+use crate::game::game_result::GameResult;
+use crate::game::race::BotRace;
+use crate::game::realtime::GAME_LOOPS_PER_SECOND;
+use crate::game::sc2_result::Sc2Result;
+use crate::replays::errors::ReplayError;
+use crate::replays::header::{parse_replay_header, ReplayHeader};
+use crate::replays::info::{query_replay_info, ReplayInfo};
+use crate::state::SC2Url;
+use common::PlayerNum;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+const REPLAY_EXTENSION: &str = "SC2Replay";
+const METADATA_EXTENSION: &str = "json";
+const DEFAULT_NAME_TEMPLATE: &str = "{match_id}_{player1}_vs_{player2}";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayPlayer {
+    pub player_num: PlayerNum,
+    pub name: String,
+    pub race: BotRace,
+    pub result: Option<Sc2Result>,
+}
+
+/// Written next to the replay as a JSON sidecar
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayMetadata {
+    pub match_id: u32,
+    pub map: String,
+    pub game_loops: u32,
+    pub duration_secs: f32,
+    pub version: String,
+    pub build: u32,
+    pub base_build: u32,
+    pub md5: String,
+    pub players: Vec<ReplayPlayer>,
+}
+
+#[derive(Debug, Clone)]
+struct ReplayCandidate {
+    player_num: PlayerNum,
+    path: PathBuf,
+    header: ReplayHeader,
+    md5: String,
+}
+
+/// Validates the replays saved by the players, keeps the most complete one under the configured
+/// name and writes its metadata next to it. Players, races and the map are taken from the replay
+/// as decoded by the SC2 instance behind `sc2_url`, if any. Returns the path of the final replay.
+pub async fn process_replays(
+    config: &GameConfig,
+    game_result: &GameResult,
+    sc2_url: Option<&SC2Url>,
+) -> Result<PathBuf, ReplayError> {
+    let mut candidates = Vec::with_capacity(2);
+    for player_num in [PlayerNum::One, PlayerNum::Two] {
+        let path = config.player_replay_path(player_num);
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => {
+                error!("Could not read replay {:?}: {}", &path, e);
+                continue;
+            }
+        };
+        match parse_replay_header(&bytes) {
+            Ok(header) => candidates.push(ReplayCandidate {
+                player_num,
+                path,
+                header,
+                md5: format!("{:x}", md5::compute(&bytes)),
+            }),
+            Err(e) => {
+                warn!("Discarding replay {:?}: {}", &path, e);
+                remove_replay(&path).await;
+            }
+        }
+    }
+
+    let chosen = select_replay(&candidates)
+        .cloned()
+        .ok_or(ReplayError::NoValidReplay)?;
+    for candidate in candidates
+        .iter()
+        .filter(|c| c.player_num != chosen.player_num)
+    {
+        if candidate.md5 != chosen.md5 {
+            info!(
+                "Replay of {:?} differs from the one of {:?}, keeping the latter",
+                candidate.player_num, chosen.player_num
+            );
+        }
+        remove_replay(&candidate.path).await;
+    }
+
+    let replay_info = match sc2_url {
+        Some(sc2_url) => {
+            let bytes = tokio::fs::read(&chosen.path).await?;
+            match query_replay_info(sc2_url, bytes).await {
+                Ok(replay_info) => Some(replay_info),
+                Err(e) => {
+                    warn!(
+                        "Falling back to the match config for the replay metadata: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    let metadata = build_metadata(config, game_result, &chosen, replay_info.as_ref());
+
+    let mut name = render_replay_name(config.replay_name_template(), &metadata);
+    if is_blank_name(&name) {
+        warn!(
+            "Replay name template {:?} rendered an empty name, using the default",
+            config.replay_name_template()
+        );
+        name = render_replay_name(DEFAULT_NAME_TEMPLATE, &metadata);
+    }
+    let replay_dir = Path::new(config.replay_path());
+    let (replay_file, metadata_file) = unused_replay_paths(replay_dir, &name);
+    tokio::fs::rename(&chosen.path, &replay_file).await?;
+
+    let serialized = serde_json::to_vec_pretty(&metadata).map_err(ReplayError::Serialization)?;
+    tokio::fs::write(&metadata_file, serialized).await?;
+
+    info!("Replay stored as {:?}", &replay_file);
+    Ok(replay_file)
+}
+
+/// Removes the replays the players saved, so a replayed match starts from a clean slate
+pub async fn remove_player_replays(config: &GameConfig) {
+    for player_num in [PlayerNum::One, PlayerNum::Two] {
+        let path = config.player_replay_path(player_num);
+        if path.exists() {
+            remove_replay(&path).await;
+        }
+    }
+}
+
+async fn remove_replay(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        error!("Could not remove replay {:?}: {}", path, e);
+    }
+}
+
+/// Picks the replay that covers the most game loops. Player one wins ties.
+fn select_replay(candidates: &[ReplayCandidate]) -> Option<&ReplayCandidate> {
+    candidates
+        .iter()
+        .rev()
+        .max_by_key(|c| c.header.elapsed_game_loops)
+}
+
+/// Paths for the replay and its metadata that don't clash with a stored replay. A numbered
+/// suffix is added to `name` if needed.
+fn unused_replay_paths(replay_dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    let paths = |name: &str| {
+        (
+            replay_dir.join(format!("{name}.{REPLAY_EXTENSION}")),
+            replay_dir.join(format!("{name}.{METADATA_EXTENSION}")),
+        )
+    };
+    let mut candidate = paths(name);
+    let mut suffix = 1;
+    while candidate.0.exists() || candidate.1.exists() {
+        suffix += 1;
+        candidate = paths(&format!("{name}_{suffix}"));
+    }
+    candidate
+}
+
+/// Whether a rendered name is unusable as a file name on its own
+fn is_blank_name(name: &str) -> bool {
+    name.chars().all(|c| matches!(c, '_' | '.'))
+}
+
+/// Metadata of the chosen replay. Values decoded from the replay take precedence over the ones
+/// from the match.
+fn build_metadata(
+    config: &GameConfig,
+    game_result: &GameResult,
+    replay: &ReplayCandidate,
+    replay_info: Option<&ReplayInfo>,
+) -> ReplayMetadata {
+    let decoded_player = |player_num: PlayerNum| {
+        let player_id = game_result
+            .player_result(player_num)
+            .map_or(player_num.index() as u32 + 1, |r| r.player_id);
+        replay_info?.players.get(&player_id)
+    };
+    ReplayMetadata {
+        match_id: config.match_id,
+        map: replay_info
+            .map(|info| info.map.clone())
+            .filter(|map| !map.is_empty())
+            .unwrap_or_else(|| config.map().clone()),
+        game_loops: replay.header.elapsed_game_loops,
+        duration_secs: replay.header.elapsed_game_loops as f32 / GAME_LOOPS_PER_SECOND,
+        version: replay.header.version.to_string(),
+        build: replay.header.version.build,
+        base_build: replay.header.version.base_build,
+        md5: replay.md5.clone(),
+        players: [PlayerNum::One, PlayerNum::Two]
+            .into_iter()
+            .map(|player_num| {
+                let decoded = decoded_player(player_num);
+                ReplayPlayer {
+                    player_num,
+                    name: decoded
+                        .map(|p| p.name.clone())
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| config.players[&player_num].name.clone()),
+                    race: decoded
+                        .map(|p| p.race)
+                        .filter(|race| !matches!(race, BotRace::NoRace | BotRace::Random))
+                        .unwrap_or_else(|| player_race(config, game_result, player_num)),
+                    result: game_result.player_result(player_num).map(|r| r.result),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Race `player_num` played as. Random picks are resolved from the game info the players
+/// received, falling back to the race from the match.
+fn player_race(config: &GameConfig, game_result: &GameResult, player_num: PlayerNum) -> BotRace {
+    let own_view = game_result
+        .player_result(player_num)
+        .and_then(|own| own.actual_races.get(&own.player_id));
+    // A computer opponent has no result of its own, but shows up in the game info of the other
+    // player
+    let opponent_view = || {
+        let other = game_result.player_result(player_num.other_player())?;
+        other
+            .actual_races
+            .iter()
+            .find(|(player_id, _)| **player_id != other.player_id)
+            .map(|(_, race)| race)
+    };
+    own_view
+        .or_else(opponent_view)
+        .copied()
+        .unwrap_or(config.players[&player_num].race)
+}
+
+/// Renders the replay name template. Supported placeholders are `{match_id}`, `{player1}`,
+/// `{player2}`, `{race1}`, `{race2}`, `{map}` and `{build}`.
+fn render_replay_name(template: &str, metadata: &ReplayMetadata) -> String {
+    let player1 = &metadata.players[PlayerNum::One.index()];
+    let player2 = &metadata.players[PlayerNum::Two.index()];
+    [
+        ("{match_id}", metadata.match_id.to_string()),
+        ("{player1}", player1.name.clone()),
+        ("{player2}", player2.name.clone()),
+        ("{race1}", format!("{:?}", player1.race)),
+        ("{race2}", format!("{:?}", player2.race)),
+        ("{map}", metadata.map.clone()),
+        ("{build}", metadata.build.to_string()),
+    ]
+    .iter()
+    .fold(template.to_string(), |name, (placeholder, value)| {
+        name.replace(placeholder, &sanitize_file_name(value))
+    })
+}
+
+fn sanitize_file_name(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player_result::PlayerResult;
+    use crate::matches::options::MatchOptions;
+    use crate::matches::player_kind::PlayerKind;
+    use crate::matches::{Match, MatchPlayer};
+    use crate::replays::header::ReplayVersion;
+    use crate::replays::info::ReplayInfoPlayer;
+    use common::configuration::ac_config::ACConfig;
+    use common::models::bot_controller::BotType;
+    use std::collections::HashMap;
+
+    fn game_config(template: &str) -> GameConfig {
+        let player = |name: &str, race| MatchPlayer {
+            id: name.to_string(),
+            name: name.to_string(),
+            race,
+            kind: PlayerKind::Bot(BotType::Python),
+        };
+        let mut players = HashMap::new();
+        players.insert(PlayerNum::One, player("basic bot", BotRace::Zerg));
+        players.insert(PlayerNum::Two, player("loser/bot", BotRace::Terran));
+        let m = Match {
+            match_id: 42,
+            players,
+            map_name: "AutomatonLE".to_string(),
+            options: MatchOptions::default(),
+            aiarena_match: None,
+        };
+        let ac_config = ACConfig {
+            replay_name_template: template.to_string(),
+            ..Default::default()
+        };
+        GameConfig::new(&m, &ac_config)
+    }
+
+    fn header(build: u32, elapsed_game_loops: u32) -> ReplayHeader {
+        ReplayHeader {
+            version: ReplayVersion {
+                major: 5,
+                minor: 0,
+                revision: 11,
+                build,
+                base_build: build,
+            },
+            elapsed_game_loops,
+        }
+    }
+
+    fn candidate(player_num: PlayerNum, elapsed_game_loops: u32) -> ReplayCandidate {
+        ReplayCandidate {
+            player_num,
+            path: PathBuf::new(),
+            header: header(81102, elapsed_game_loops),
+            md5: String::new(),
+        }
+    }
+
+    fn metadata(config: &GameConfig, replay_info: Option<&ReplayInfo>) -> ReplayMetadata {
+        build_metadata(
+            config,
+            &GameResult::new(42),
+            &candidate(PlayerNum::One, 100),
+            replay_info,
+        )
+    }
+
+    #[test]
+    fn test_render_default_template() {
+        let config = game_config("{match_id}_{player1}_vs_{player2}");
+        assert_eq!(
+            render_replay_name(config.replay_name_template(), &metadata(&config, None)),
+            "42_basic_bot_vs_loser_bot"
+        );
+    }
+
+    #[test]
+    fn test_render_all_placeholders() {
+        let config = game_config("{map}-{build}-{race1}v{race2}-{unknown}");
+        assert_eq!(
+            render_replay_name(config.replay_name_template(), &metadata(&config, None)),
+            "AutomatonLE-81102-ZergvTerran-{unknown}"
+        );
+    }
+
+    #[test]
+    fn test_render_from_replay_info() {
+        let config = game_config("{map}-{player1}-{race1}v{player2}-{race2}");
+        let player = |name: &str, race| ReplayInfoPlayer {
+            name: name.to_string(),
+            race,
+        };
+        let replay_info = ReplayInfo {
+            map: "Automaton LE".to_string(),
+            players: HashMap::from([
+                (1, player("basic_bot", BotRace::Protoss)),
+                // Nothing better than the match config for this one
+                (2, player("", BotRace::Random)),
+            ]),
+        };
+        assert_eq!(
+            render_replay_name(
+                config.replay_name_template(),
+                &metadata(&config, Some(&replay_info))
+            ),
+            "Automaton_LE-basic_bot-Protossvloser_bot-Terran"
+        );
+    }
+
+    #[test]
+    fn test_blank_name() {
+        let config = game_config("");
+        let name = render_replay_name(config.replay_name_template(), &metadata(&config, None));
+        assert!(is_blank_name(&name));
+        assert!(is_blank_name("_._"));
+        assert!(!is_blank_name("42_basic_bot_vs_loser_bot"));
+    }
+
+    #[test]
+    fn test_unused_replay_paths() {
+        let replay_dir = std::env::temp_dir().join(format!("replays_{}", std::process::id()));
+        std::fs::create_dir_all(&replay_dir).unwrap();
+
+        let (replay, metadata) = unused_replay_paths(&replay_dir, "42");
+        assert_eq!(replay, replay_dir.join("42.SC2Replay"));
+        assert_eq!(metadata, replay_dir.join("42.json"));
+
+        std::fs::write(&replay, b"").unwrap();
+        let (replay, _) = unused_replay_paths(&replay_dir, "42");
+        assert_eq!(replay, replay_dir.join("42_2.SC2Replay"));
+        // A stray metadata file blocks the name as well
+        std::fs::write(replay_dir.join("42_2.json"), b"").unwrap();
+        let (replay, metadata) = unused_replay_paths(&replay_dir, "42");
+        assert_eq!(replay, replay_dir.join("42_3.SC2Replay"));
+        assert_eq!(metadata, replay_dir.join("42_3.json"));
+
+        std::fs::remove_dir_all(&replay_dir).unwrap();
+    }
+
+    #[test]
+    fn test_select_longest_replay() {
+        let candidates = [
+            candidate(PlayerNum::One, 100),
+            candidate(PlayerNum::Two, 200),
+        ];
+        assert_eq!(
+            select_replay(&candidates).unwrap().player_num,
+            PlayerNum::Two
+        );
+    }
+
+    #[test]
+    fn test_select_player_one_on_tie() {
+        let candidates = [
+            candidate(PlayerNum::One, 200),
+            candidate(PlayerNum::Two, 200),
+        ];
+        assert_eq!(
+            select_replay(&candidates).unwrap().player_num,
+            PlayerNum::One
+        );
+        assert!(select_replay(&[]).is_none());
+    }
+
+    #[test]
+    fn test_player_race_from_game_info() {
+        let config = game_config("");
+        let mut game_result = GameResult::new(42);
+        assert_eq!(
+            player_race(&config, &game_result, PlayerNum::One),
+            BotRace::Zerg
+        );
+
+        // Only player two reported, as it would against a computer opponent
+        game_result.add_player_result(
+            PlayerNum::Two,
+            PlayerResult {
+                game_loops: 0,
+                frame_time: 0.0,
+                player_id: 2,
+                tags: Default::default(),
+                step_time_stats: None,
+                realtime_stats: None,
+                summary: None,
+                annotations: None,
+                actual_races: HashMap::from([(1, BotRace::Protoss), (2, BotRace::Zerg)]),
+                result: Sc2Result::Victory,
+                end_reason: Default::default(),
+            },
+        );
+        assert_eq!(
+            player_race(&config, &game_result, PlayerNum::One),
+            BotRace::Protoss
+        );
+        assert_eq!(
+            player_race(&config, &game_result, PlayerNum::Two),
+            BotRace::Zerg
+        );
+    }
+}
@@ -1,15 +1,22 @@
 use crate::state::ProxyState;
-use axum::extract::State;
+use axum::body::StreamBody;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
 use axum::Json;
 use bytes::Bytes;
 use common::api::api_reference::aiarena::aiarena_api_client::AiArenaApiClient;
 use common::api::errors::app_error::AppError;
 use common::api::errors::download_error::DownloadError;
 use common::configuration::ac_config::ACConfig;
+use common::models::bot_controller::LogTailQuery;
+use common::preflight::PreflightReport;
 use common::PlayerNum;
+use futures_util::stream;
 use parking_lot::RwLock;
+use serde::Serialize;
 use std::sync::Arc;
-use tracing::{self, error};
+use tracing::{self, error, info};
 
 #[tracing::instrument]
 pub async fn configuration(
@@ -18,6 +25,18 @@ pub async fn configuration(
     Ok(Json(state.read().settings.clone()))
 }
 
+#[cfg_attr(feature = "swagger", utoipa::path(
+    get,
+    path = "/preflight",
+    responses(
+        (status = 200, description = "Environment check report", body = PreflightReport)
+    )
+))]
+pub async fn preflight(State(state): State<Arc<RwLock<ProxyState>>>) -> Json<PreflightReport> {
+    let settings = state.read().settings.clone();
+    Json(crate::preflight::run_checks(&settings))
+}
+
 pub async fn download_bot(
     State(state): State<Arc<RwLock<ProxyState>>>,
     //ConnectInfo(addr): ConnectInfo<SocketAddr>,
@@ -231,3 +250,106 @@ pub async fn download_map(State(state): State<Arc<RwLock<ProxyState>>>) -> Resul
             .map_err(|e| AppError::Download(DownloadError::Other(e.to_string())))
     }
 }
+
+#[derive(Debug, Serialize)]
+pub struct PauseStatus {
+    paused: bool,
+}
+
+fn check_pausable(state: &ProxyState) -> Result<(), (StatusCode, String)> {
+    if !state.settings.allow_pause() {
+        return Err((StatusCode::FORBIDDEN, "Pausing is disabled".to_string()));
+    }
+    match &state.game_config {
+        None => Err((StatusCode::CONFLICT, "No match is running".to_string())),
+        Some(game_config) if game_config.realtime() => Err((
+            StatusCode::CONFLICT,
+            "Realtime matches can't be paused".to_string(),
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
+pub async fn pause_match(
+    State(state): State<Arc<RwLock<ProxyState>>>,
+) -> Result<Json<PauseStatus>, (StatusCode, String)> {
+    let state = state.read();
+    check_pausable(&state)?;
+    if state.pause.pause() {
+        info!("Match paused");
+    }
+    Ok(Json(PauseStatus { paused: true }))
+}
+
+pub async fn resume_match(
+    State(state): State<Arc<RwLock<ProxyState>>>,
+) -> Result<Json<PauseStatus>, (StatusCode, String)> {
+    let state = state.read();
+    if state.pause.resume() {
+        info!("Match resumed");
+    }
+    Ok(Json(PauseStatus { paused: false }))
+}
+
+/// Passes the live log tail of a bot in the current match through from its bot controller.
+///
+/// `player` is 1 or 2.
+pub async fn tail_bot_log(
+    State(state): State<Arc<RwLock<ProxyState>>>,
+    Path(player): Path<u8>,
+    Query(query): Query<LogTailQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let player_num = match player {
+        1 => PlayerNum::One,
+        2 => PlayerNum::Two,
+        _ => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("There is no player {player}"),
+            ))
+        }
+    };
+    let (bot_name, bot_controller) = {
+        let state = state.read();
+        let current_match = match &state.current_match {
+            Some(current_match) => current_match,
+            None => return Err((StatusCode::CONFLICT, "No match is running".to_string())),
+        };
+        if current_match.human_player() == Some(player_num) {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Player {player} is not a bot"),
+            ));
+        }
+        match state.bot_controllers.get(player_num.index()) {
+            Some(bot_controller) => (
+                current_match.players[&player_num].name.clone(),
+                bot_controller.clone(),
+            ),
+            None => {
+                return Err((
+                    StatusCode::CONFLICT,
+                    "Bot controllers are not initialized".to_string(),
+                ))
+            }
+        }
+    };
+    let response = bot_controller
+        .tail_bot_log(&bot_name, &query)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+    let body = StreamBody::new(stream::unfold(Some(response), |response| async move {
+        let mut response = response?;
+        match response.chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(response))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    }));
+    let headers = [
+        (header::CONTENT_TYPE, "text/event-stream"),
+        (header::CACHE_CONTROL, "no-cache"),
+    ];
+    Ok((headers, body))
+}
@@ -1,5 +1,6 @@
 use crate::game::game_config::GameConfig;
 use crate::game::game_result::GameResult;
+use crate::game::pause::PauseControl;
 use crate::matches::Match;
 use crate::websocket::port_config::PortConfig;
 use common::api::api_reference::bot_controller_client::BotController;
@@ -9,7 +10,7 @@ use common::models::StartResponse;
 use common::utilities::portpicker::Port;
 use common::PlayerNum;
 use indexmap::IndexSet;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use tokio::sync::mpsc::Sender;
 
 #[derive(Debug, Clone)]
@@ -46,6 +47,9 @@ pub struct ProxyState {
     pub shutdown_sender: Sender<()>,
     pub bot_controllers: Vec<BotController>,
     pub sc2_controllers: Vec<SC2Controller>,
+    pub pause: PauseControl,
+    /// Address the proxy listens on, also used for the per-bot listeners
+    pub listen_ip: IpAddr,
 }
 
 impl ProxyState {
@@ -69,10 +73,63 @@ impl ProxyState {
         self.players.clear();
     }
 
-    pub fn update_player(&mut self, port: Port, bot_name: &str, player_num: PlayerNum) -> bool {
-        if let Some(player) = self.players.iter_mut().find(|x| x.addr.port() == port) {
+    /// Assigns a connection that came in on the listener of `player_num` to that bot.
+    ///
+    /// Returns false if there is no current match, or the bot is already connected.
+    pub fn assign_bot(&mut self, addr: SocketAddr, player_num: PlayerNum) -> bool {
+        let name = match &self.current_match {
+            Some(current_match) => current_match.players[&player_num].name.clone(),
+            None => return false,
+        };
+        if self.is_connected(player_num) {
+            return false;
+        }
+        if let Some(player) = self.players.iter_mut().find(|x| x.addr == addr) {
+            player.player_num = Some(player_num);
+            player.bot_name = Some(name);
+            true
+        } else {
+            false
+        }
+    }
+    /// Assigns a connection on the main proxy port to the first bot of the current match that is
+    /// not connected yet.
+    ///
+    /// Returns false if there is no current match, or all bots are connected.
+    pub fn assign_next_bot(&mut self, addr: SocketAddr) -> bool {
+        let player_num = match self.current_match.as_ref().and_then(|m| {
+            m.bot_players()
+                .into_iter()
+                .find(|player_num| !self.is_connected(*player_num))
+        }) {
+            Some(player_num) => player_num,
+            None => return false,
+        };
+        self.assign_bot(addr, player_num)
+    }
+    pub fn is_connected(&self, player_num: PlayerNum) -> bool {
+        self.players
+            .iter()
+            .any(|x| x.player_num == Some(player_num))
+    }
+    /// Assigns a connection to the human player of the current match.
+    ///
+    /// Returns false if the current match has no human player, or the human player is already
+    /// connected.
+    pub fn assign_human(&mut self, addr: SocketAddr) -> bool {
+        let (player_num, name) = match self.current_match.as_ref().and_then(|m| {
+            m.human_player()
+                .map(|player_num| (player_num, m.players[&player_num].name.clone()))
+        }) {
+            Some(human) => human,
+            None => return false,
+        };
+        if self.is_connected(player_num) {
+            return false;
+        }
+        if let Some(player) = self.players.iter_mut().find(|x| x.addr == addr) {
             player.player_num = Some(player_num);
-            player.bot_name = Some(bot_name.to_string());
+            player.bot_name = Some(name);
             true
         } else {
             false
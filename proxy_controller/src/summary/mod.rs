@@ -0,0 +1,164 @@
+//! Per-match summary built from the traffic the proxy forwards, exported as JSON and HTML
+
+pub mod recorder;
+
+use crate::game::game_config::GameConfig;
+use crate::game::game_result::GameResult;
+use crate::game::race::BotRace;
+use crate::game::sc2_result::Sc2Result;
+use crate::summary::recorder::PlayerSummary;
+use common::models::stats::StepTimeStats;
+use common::PlayerNum;
+use serde::Serialize;
+use std::path::Path;
+
+const HTML_TEMPLATE: &str = include_str!("template.html");
+const SUMMARY_PLACEHOLDER: &str = "{{SUMMARY_JSON}}";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerMatchSummary {
+    pub player_num: PlayerNum,
+    pub name: String,
+    pub race: BotRace,
+    pub result: Option<Sc2Result>,
+    pub game_loops: u32,
+    pub tags: Vec<String>,
+    pub step_time_stats: Option<StepTimeStats>,
+    #[serde(flatten)]
+    pub summary: PlayerSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchSummary {
+    pub match_id: u32,
+    pub map: String,
+    pub players: Vec<PlayerMatchSummary>,
+}
+
+impl MatchSummary {
+    pub fn new(config: &GameConfig, game_result: &GameResult) -> Self {
+        let players = [PlayerNum::One, PlayerNum::Two]
+            .into_iter()
+            .map(|player_num| {
+                let player = &config.players[&player_num];
+                let player_result = game_result.player_result(player_num);
+                PlayerMatchSummary {
+                    player_num,
+                    name: player.name.clone(),
+                    race: player.race,
+                    result: player_result.map(|r| r.result),
+                    game_loops: player_result.map_or(0, |r| r.game_loops),
+                    tags: player_result
+                        .map(|r| r.tags.iter().cloned().collect())
+                        .unwrap_or_default(),
+                    step_time_stats: player_result.and_then(|r| r.step_time_stats),
+                    summary: player_result
+                        .and_then(|r| r.summary.clone())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        Self {
+            match_id: config.match_id,
+            map: config.map().clone(),
+            players,
+        }
+    }
+
+    /// Renders a self-contained HTML page with the summary data embedded
+    pub fn to_html(&self) -> serde_json::Result<String> {
+        // Bots control the chat contents, so make sure they can't close the script tag
+        let json = serde_json::to_string(self)?.replace("</", "<\\/");
+        Ok(HTML_TEMPLATE.replace(SUMMARY_PLACEHOLDER, &json))
+    }
+
+    /// Writes `summary.json` and `summary.html` to `directory`
+    pub async fn write_to(&self, directory: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        tokio::fs::write(directory.join("summary.json"), json).await?;
+        tokio::fs::write(directory.join("summary.html"), self.to_html()?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player_result::PlayerResult;
+    use crate::matches::options::MatchOptions;
+    use crate::matches::player_kind::PlayerKind;
+    use crate::matches::{Match, MatchPlayer};
+    use crate::summary::recorder::ChatMessage;
+    use common::configuration::ac_config::ACConfig;
+    use common::models::aiarena::aiarena_end_reason::EndReason;
+    use common::models::bot_controller::BotType;
+    use std::collections::HashMap;
+
+    fn game_config() -> GameConfig {
+        let player = |name: &str| MatchPlayer {
+            id: name.to_string(),
+            name: name.to_string(),
+            race: BotRace::Protoss,
+            kind: PlayerKind::Bot(BotType::Python),
+        };
+        let mut players = HashMap::new();
+        players.insert(PlayerNum::One, player("bot1"));
+        players.insert(PlayerNum::Two, player("bot2"));
+        let m = Match {
+            match_id: 7,
+            players,
+            map_name: "AutomatonLE".to_string(),
+            options: MatchOptions::default(),
+            aiarena_match: None,
+        };
+        GameConfig::new(&m, &ACConfig::default())
+    }
+
+    fn game_result() -> GameResult {
+        let mut game_result = GameResult::new(7);
+        game_result.add_player_result(
+            PlayerNum::One,
+            PlayerResult {
+                game_loops: 1000,
+                frame_time: 0.0,
+                player_id: 1,
+                tags: ["tag".to_string()].into_iter().collect(),
+                step_time_stats: None,
+                realtime_stats: None,
+                annotations: None,
+                actual_races: Default::default(),
+                summary: Some(PlayerSummary {
+                    samples: vec![],
+                    chat: vec![ChatMessage {
+                        game_loop: 10,
+                        message: "</script><script>alert(1)</script>".to_string(),
+                    }],
+                }),
+                result: Sc2Result::Victory,
+                end_reason: EndReason::Normal,
+            },
+        );
+        game_result
+    }
+
+    #[test]
+    fn test_summary_from_result() {
+        let summary = MatchSummary::new(&game_config(), &game_result());
+        assert_eq!(summary.match_id, 7);
+        assert_eq!(summary.players.len(), 2);
+        assert_eq!(summary.players[0].result, Some(Sc2Result::Victory));
+        assert_eq!(summary.players[0].tags, vec!["tag".to_string()]);
+        assert_eq!(summary.players[0].summary.chat.len(), 1);
+        assert_eq!(summary.players[1].result, None);
+        assert_eq!(summary.players[1].game_loops, 0);
+    }
+
+    #[test]
+    fn test_html_embeds_escaped_json() {
+        let html = MatchSummary::new(&game_config(), &game_result())
+            .to_html()
+            .unwrap();
+        assert!(!html.contains(SUMMARY_PLACEHOLDER));
+        assert!(html.contains("\"match_id\":7"));
+        assert!(!html.contains("</script><script>alert(1)"));
+    }
+}
@@ -0,0 +1,193 @@
+use sc2_proto::sc2api::{Observation, Request};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Sample every 10 seconds of game time
+pub const SAMPLE_INTERVAL_LOOPS: u32 = 224;
+/// Stop recording chat after this many messages, to bound the summary size
+pub const MAX_CHAT_MESSAGES: usize = 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SummarySample {
+    pub game_loop: u32,
+    pub supply_used: u32,
+    pub supply_cap: u32,
+    pub army_supply: u32,
+    pub workers: u32,
+    pub army_count: u32,
+    pub minerals: u32,
+    pub vespene: u32,
+    /// Average step time since the previous sample, in milliseconds
+    pub avg_step_ms: f32,
+    /// Longest step time since the previous sample, in milliseconds
+    pub max_step_ms: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub game_loop: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSummary {
+    pub samples: Vec<SummarySample>,
+    pub chat: Vec<ChatMessage>,
+}
+
+/// Builds a [`PlayerSummary`] from the traffic the proxy forwards for one player
+#[derive(Debug, Default)]
+pub struct SummaryRecorder {
+    summary: PlayerSummary,
+    game_loop: u32,
+    next_sample_loop: u32,
+    step_count: u32,
+    step_total: Duration,
+    step_max: Duration,
+}
+
+impl SummaryRecorder {
+    pub fn record_step_time(&mut self, step_time: Duration) {
+        self.step_count += 1;
+        self.step_total += step_time;
+        self.step_max = self.step_max.max(step_time);
+    }
+
+    pub fn record_chat(&mut self, request: &Request) {
+        if !request.has_action() {
+            return;
+        }
+        let messages = request
+            .action()
+            .actions
+            .iter()
+            .filter(|a| a.action_chat.has_message())
+            .map(|a| a.action_chat.message());
+        for message in messages {
+            if self.summary.chat.len() >= MAX_CHAT_MESSAGES {
+                return;
+            }
+            self.summary.chat.push(ChatMessage {
+                game_loop: self.game_loop,
+                message: message.to_string(),
+            });
+        }
+    }
+
+    pub fn record_observation(&mut self, observation: &Observation) {
+        self.game_loop = observation.game_loop();
+        if self.game_loop < self.next_sample_loop {
+            return;
+        }
+        let player = &observation.player_common;
+        let avg_step_ms = if self.step_count == 0 {
+            0.0
+        } else {
+            self.step_total.as_secs_f32() * 1000.0 / self.step_count as f32
+        };
+        self.summary.samples.push(SummarySample {
+            game_loop: self.game_loop,
+            supply_used: player.food_used(),
+            supply_cap: player.food_cap(),
+            army_supply: player.food_army(),
+            workers: player.food_workers(),
+            army_count: player.army_count(),
+            minerals: player.minerals(),
+            vespene: player.vespene(),
+            avg_step_ms,
+            max_step_ms: self.step_max.as_secs_f32() * 1000.0,
+        });
+        self.step_count = 0;
+        self.step_total = Duration::ZERO;
+        self.step_max = Duration::ZERO;
+        self.next_sample_loop = self.game_loop + SAMPLE_INTERVAL_LOOPS;
+    }
+
+    pub fn finish(self) -> PlayerSummary {
+        self.summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sc2_proto::sc2api::{Action, ActionChat, PlayerCommon, RequestAction};
+
+    fn observation(game_loop: u32, minerals: u32) -> Observation {
+        let mut player_common = PlayerCommon::new();
+        player_common.set_minerals(minerals);
+        player_common.set_food_used(12);
+        let mut observation = Observation::new();
+        observation.set_game_loop(game_loop);
+        observation.player_common = protobuf::MessageField::some(player_common);
+        observation
+    }
+
+    fn chat_request(messages: &[&str]) -> Request {
+        let mut request_action = RequestAction::new();
+        for message in messages {
+            let mut chat = ActionChat::new();
+            chat.set_message(message.to_string());
+            let mut action = Action::new();
+            action.action_chat = protobuf::MessageField::some(chat);
+            request_action.actions.push(action);
+        }
+        let mut request = Request::new();
+        request.set_action(request_action);
+        request
+    }
+
+    #[test]
+    fn test_samples_at_interval() {
+        let mut recorder = SummaryRecorder::default();
+        recorder.record_step_time(Duration::from_millis(10));
+        recorder.record_step_time(Duration::from_millis(30));
+        recorder.record_observation(&observation(0, 50));
+        for game_loop in 1..SAMPLE_INTERVAL_LOOPS {
+            recorder.record_observation(&observation(game_loop, 60));
+        }
+        recorder.record_step_time(Duration::from_millis(5));
+        recorder.record_observation(&observation(SAMPLE_INTERVAL_LOOPS, 70));
+
+        let summary = recorder.finish();
+        assert_eq!(summary.samples.len(), 2);
+        assert_eq!(summary.samples[0].minerals, 50);
+        assert_eq!(summary.samples[0].supply_used, 12);
+        assert!((summary.samples[0].avg_step_ms - 20.0).abs() < 0.01);
+        assert!((summary.samples[0].max_step_ms - 30.0).abs() < 0.01);
+        assert_eq!(summary.samples[1].game_loop, SAMPLE_INTERVAL_LOOPS);
+        assert_eq!(summary.samples[1].minerals, 70);
+        assert!((summary.samples[1].max_step_ms - 5.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_chat_is_recorded_with_game_loop() {
+        let mut recorder = SummaryRecorder::default();
+        recorder.record_observation(&observation(100, 0));
+        recorder.record_chat(&chat_request(&["gl hf", "Tag:proxy"]));
+        recorder.record_chat(&Request::new());
+
+        let summary = recorder.finish();
+        assert_eq!(
+            summary.chat,
+            vec![
+                ChatMessage {
+                    game_loop: 100,
+                    message: "gl hf".to_string()
+                },
+                ChatMessage {
+                    game_loop: 100,
+                    message: "Tag:proxy".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chat_is_capped() {
+        let mut recorder = SummaryRecorder::default();
+        let messages = vec!["spam"; MAX_CHAT_MESSAGES + 10];
+        recorder.record_chat(&chat_request(&messages));
+        assert_eq!(recorder.finish().chat.len(), MAX_CHAT_MESSAGES);
+    }
+}
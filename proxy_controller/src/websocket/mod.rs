@@ -2,3 +2,4 @@ pub mod errors;
 pub mod player;
 pub mod port_config;
 pub mod runtime_vars;
+pub mod sanitizer;
@@ -1,21 +1,27 @@
 use crate::game::game_config::GameConfig;
+use crate::game::pause::PauseControl;
 use crate::game::player_data::PlayerData;
 use crate::game::player_result::PlayerResult;
 use crate::game::sc2_result::Sc2Result;
+use crate::matches::player_kind::{ComputerSettings, PlayerKind};
+use crate::matches::MatchPlayer;
 use crate::websocket::errors::player_error::PlayerError;
 use crate::websocket::port_config::PortConfig;
 use crate::websocket::runtime_vars::RuntimeVars;
+use crate::websocket::sanitizer::ResponseSanitizer;
 use axum::extract::ws::{Message as AMessage, WebSocket};
+use common::models::aiarena::aiarena_end_reason::EndReason;
 use common::PlayerNum;
 use futures_util::{SinkExt, StreamExt};
 use protobuf::{EnumOrUnknown, Message, MessageField};
+use sc2_proto::common::Race;
 use sc2_proto::sc2api::{
     Request, RequestJoinGame, RequestLeaveGame, RequestPing, RequestSaveReplay, Response,
-    ResponseDebug, Status,
+    ResponseDebug, ResponseStep, Status,
 };
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
@@ -30,6 +36,10 @@ pub struct Player {
     addr: SocketAddr,
     bot_ws_timeout: Duration,
     sc2_ws_timeout: Duration,
+    joined: bool,
+    /// Whether the bot asked for raw data when it joined
+    raw_requested: bool,
+    pause: PauseControl,
 }
 
 impl Player {
@@ -37,6 +47,7 @@ impl Player {
         bot_ws: WebSocket,
         sc2_ws: WebSocketStream<TcpStream>,
         addr: SocketAddr,
+        pause: PauseControl,
     ) -> Self {
         Self {
             bot_ws,
@@ -44,26 +55,41 @@ impl Player {
             addr,
             bot_ws_timeout: Duration::from_secs(30),
             sc2_ws_timeout: Duration::from_secs(60),
+            joined: false,
+            raw_requested: true,
+            pause,
         }
     }
     pub const fn addr(&self) -> SocketAddr {
         self.addr
     }
+    /// Whether the player joined the game, i.e. failures are no longer initialization errors
+    pub const fn has_joined(&self) -> bool {
+        self.joined
+    }
 
     /// Receive a message from the client
     /// Returns None if the connection is already closed
     pub async fn bot_recv_message(&mut self) -> Result<AMessage, PlayerError> {
         trace!("Waiting for a message from the client");
-        match timeout(self.bot_ws_timeout, self.bot_ws.next()).await {
-            Ok(res_msg) => match res_msg {
-                Some(Ok(msg)) => {
-                    trace!("Message received from client:\n{:?}", &msg);
-                    Ok(msg)
+        let mut next_message = self.bot_ws.next();
+        loop {
+            let paused_before = self.pause.paused_time();
+            match timeout(self.bot_ws_timeout, &mut next_message).await {
+                Ok(res_msg) => {
+                    return match res_msg {
+                        Some(Ok(msg)) => {
+                            trace!("Message received from client:\n{:?}", &msg);
+                            Ok(msg)
+                        }
+                        Some(Err(e)) => Err(PlayerError::BotWebsocket(e)),
+                        None => Err(PlayerError::NoMessageAvailable),
+                    }
                 }
-                Some(Err(e)) => Err(PlayerError::BotWebsocket(e)),
-                None => Err(PlayerError::NoMessageAvailable),
-            },
-            Err(_) => Err(PlayerError::BotTimeout(self.bot_ws_timeout)),
+                // Restart the timeout if the match was paused while waiting
+                Err(_) if self.pause.is_paused() || self.pause.paused_time() > paused_before => {}
+                Err(_) => return Err(PlayerError::BotTimeout(self.bot_ws_timeout)),
+            }
         }
     }
     /// Send message to the client
@@ -148,7 +174,7 @@ impl Player {
         self.sc2_send_message(TMessage::binary(r)).await
     }
     /// Protobuf to create a new handler
-    fn proto_create_game(players: &[CreateGamePlayer], map: &str, realtime: bool) -> Request {
+    fn proto_create_game(players: &[CreateGamePlayer], map: &str, config: &GameConfig) -> Request {
         use sc2_proto::sc2api::{LocalMap, RequestCreateGame};
 
         let mut r_local_map = LocalMap::new();
@@ -156,7 +182,11 @@ impl Player {
 
         let mut r_create_game = RequestCreateGame::new();
         r_create_game.set_local_map(r_local_map);
-        r_create_game.set_realtime(realtime);
+        r_create_game.set_realtime(config.realtime());
+        r_create_game.set_disable_fog(config.disable_fog());
+        if let Some(random_seed) = config.random_seed() {
+            r_create_game.set_random_seed(random_seed);
+        }
 
         r_create_game.player_setup = players.iter().map(CreateGamePlayer::as_proto).collect();
 
@@ -165,7 +195,7 @@ impl Player {
         request
     }
 
-    pub async fn create_game(&mut self, map: &str, realtime: bool) -> Result<(), PlayerError> {
+    pub async fn create_game(&mut self, map: &str, config: &GameConfig) -> Result<(), PlayerError> {
         let ping_request = create_ping_request();
         for _ in 0..10 {
             match self.sc2_query(&ping_request).await {
@@ -179,10 +209,13 @@ impl Player {
             }
         }
         // Craft CreateGame request
-        let player_configs: Vec<CreateGamePlayer> = vec![CreateGamePlayer::Participant; 2];
+        let player_configs: Vec<CreateGamePlayer> = [PlayerNum::One, PlayerNum::Two]
+            .iter()
+            .map(|player_num| CreateGamePlayer::from_match_player(&config.players[player_num]))
+            .collect();
 
         // Send CreateGame request to first procs
-        let proto = Self::proto_create_game(&player_configs, map, realtime);
+        let proto = Self::proto_create_game(&player_configs, map, config);
         let response = self.sc2_query(&proto).await?;
 
         let resp_create_game = response.create_game();
@@ -293,12 +326,16 @@ impl Player {
                 match File::create(&path).await {
                     Ok(mut buffer) => {
                         let data: &[u8] = response.save_replay().data();
-                        buffer
-                            .write_all(data)
-                            .await
-                            .expect("Could not write to replay file");
-                        info!("Replay saved to {:?}", &path);
-                        true
+                        match buffer.write_all(data).await {
+                            Ok(_) => {
+                                info!("Replay saved to {:?}", &path);
+                                true
+                            }
+                            Err(e) => {
+                                error!("Failed to write replay file {:?}: {:?}", &path, e);
+                                false
+                            }
+                        }
                     }
                     Err(e) => {
                         error!("Failed to create replay file {:?}: {:?}", &path, e);
@@ -329,6 +366,7 @@ impl Player {
                 let resp = self.sc2_query(&msg).await?;
                 self.bot_send_response(&resp).await?;
             } else if msg.has_join_game() {
+                self.raw_requested = msg.join_game().options.raw();
                 let req_raw = proto_join_game_participant(&msg, &port_config, config, player_num);
 
                 let resp = self.sc2_query(&req_raw).await?;
@@ -359,44 +397,67 @@ impl Player {
         port_config: PortConfig,
         player_num: PlayerNum,
     ) -> Result<PlayerResult, PlayerError> {
-        let mut r_vars = RuntimeVars::new(&config);
+        let mut r_vars = RuntimeVars::new(&config, player_num, self.pause.clone());
         self.bot_ws_timeout = r_vars.timeout_secs;
         let mut response: Response;
 
         r_vars.player_id = self
             .wait_for_join_game(port_config, &config, player_num)
             .await?;
+        self.joined = true;
+        let mut sanitizer =
+            ResponseSanitizer::new(&config, player_num, r_vars.player_id(), self.raw_requested);
 
         loop {
             match self.bot_recv_request().await {
                 Ok(request) => {
                     r_vars.record_frame_time();
+                    r_vars.record_step_time(&request);
+
+                    if self.pause.is_paused() {
+                        info!("Match paused, holding request until resumed");
+                        self.pause.wait_until_resumed().await;
+                    }
 
                     if config.disable_debug && request.has_debug() {
                         let debug_response = create_empty_debug_response(&request);
                         self.bot_send_response(&debug_response).await?;
+                        r_vars.response_sent(&debug_response);
                         continue;
-                    } else if request.has_leave_game() || request.has_quit() {
+                    } else if config.realtime_step_emulation() && request.has_step() {
+                        if let Some(realtime) = r_vars.realtime.as_mut() {
+                            let (wait, simulation_loop) =
+                                realtime.emulate_step(request.step().count(), Instant::now());
+                            sleep(wait).await;
+                            let step_response = create_step_response(&request, simulation_loop);
+                            self.bot_send_response(&step_response).await?;
+                            r_vars.response_sent(&step_response);
+                            r_vars.start_timing();
+                            r_vars.start_time();
+                            continue;
+                        }
+                    } else if request.has_leave_game() {
                         self.save_replay(r_vars.replay_path()).await;
                         r_vars.set_surrender_flag();
+                    } else if request.has_quit() {
+                        self.save_replay(r_vars.replay_path()).await;
+                        r_vars.set_quit_flag();
                     }
 
-                    r_vars.add_tags(&request);
+                    r_vars.add_annotations(&request);
+                    r_vars.summary.record_chat(&request);
 
                     response = self.sc2_query(&request).await?;
+                    if response.status() == Status::ended {
+                        r_vars.set_game_ended();
+                    }
 
                     if response.has_game_info() {
-                        for pi in &mut response.mut_game_info().player_info {
-                            if pi.player_id() != r_vars.player_id() {
-                                pi.player_name =
-                                    Some(config.players[&player_num.other_player()].name.clone());
-                                pi.race_actual = pi.race_requested;
-                            } else {
-                                pi.player_name = Some(config.players[&player_num].name.clone());
-                            }
-                        }
+                        r_vars.record_races(response.game_info());
                     }
+                    sanitizer.sanitize(&mut response);
                     self.bot_send_response(&response).await?;
+                    r_vars.response_sent(&response);
 
                     r_vars.start_timing();
                     r_vars.start_time();
@@ -404,7 +465,8 @@ impl Player {
                     if response.has_leave_game() || response.has_quit() {
                         // self.save_replay(r_vars.replay_path()).await;
                         r_vars.record_avg_frame_time();
-                        let result = r_vars.build_result(Sc2Result::Defeat);
+                        let end_reason = r_vars.leave_end_reason();
+                        let result = r_vars.build_result(Sc2Result::Defeat, end_reason);
 
                         return Ok(result);
                     } else if response.has_observation() {
@@ -412,6 +474,7 @@ impl Player {
 
                         let observation = response.observation();
                         r_vars.set_game_loops(observation.observation.game_loop());
+                        r_vars.summary.record_observation(&observation.observation);
 
                         let observation_results = &observation.player_result;
 
@@ -422,7 +485,7 @@ impl Player {
                                 .map(|x| Sc2Result::from_proto(x.result()))
                                 .unwrap();
                             self.save_replay(r_vars.replay_path()).await;
-                            let result = r_vars.build_result(sc2_result);
+                            let result = r_vars.build_result(sc2_result, EndReason::Normal);
 
                             return Ok(result);
                         }
@@ -435,7 +498,7 @@ impl Player {
                             request.set_leave_game(leave_game);
                             let _resp = self.sc2_query(&request).await;
                             debug!("Max time reached");
-                            let result = r_vars.build_result(Sc2Result::Tie);
+                            let result = r_vars.build_result(Sc2Result::Tie, EndReason::Normal);
                             return Ok(result);
                         }
                     }
@@ -444,7 +507,8 @@ impl Player {
                     error!("{:?}", e);
                     return match e {
                         PlayerError::NoMessageAvailable => {
-                            Ok(r_vars.build_result(Sc2Result::Crash))
+                            let end_reason = r_vars.crash_end_reason();
+                            Ok(r_vars.build_result(Sc2Result::Crash, end_reason))
                         }
                         PlayerError::BotWebsocket(error) => {
                             error!("{:?}", error);
@@ -454,11 +518,12 @@ impl Player {
                             let leave_game = RequestLeaveGame::new();
                             request.set_leave_game(leave_game);
                             let _resp = self.sc2_query(&request).await;
-                            Ok(r_vars.build_result(Sc2Result::Crash))
+                            let end_reason = r_vars.crash_end_reason();
+                            Ok(r_vars.build_result(Sc2Result::Crash, end_reason))
                         }
                         PlayerError::Sc2Websocket(error) => {
                             error!("{:?}", error);
-                            Ok(r_vars.build_result(Sc2Result::SC2Crash))
+                            Ok(r_vars.build_result(Sc2Result::SC2Crash, EndReason::GameCrash))
                         }
                         PlayerError::BotUnexpectedMessage(message) => {
                             error!("BotUnexpectedMessage: {:?}", message);
@@ -469,13 +534,14 @@ impl Player {
                             request.set_leave_game(leave_game);
                             let _resp = self.sc2_query(&request).await;
 
-                            Ok(r_vars.build_result(Sc2Result::Crash))
+                            let end_reason = r_vars.crash_end_reason();
+                            Ok(r_vars.build_result(Sc2Result::Crash, end_reason))
                         }
                         PlayerError::Sc2UnexpectedMessage(message) => {
                             error!("SC2UnexpectedMessage: {:?}", message);
                             self.save_replay(r_vars.replay_path()).await;
                             r_vars.record_avg_frame_time();
-                            Ok(r_vars.build_result(Sc2Result::SC2Crash))
+                            Ok(r_vars.build_result(Sc2Result::SC2Crash, EndReason::GameCrash))
                         }
                         PlayerError::BotTimeout(d) => {
                             error!("Bot Timeout of {:?}s reached", d);
@@ -486,7 +552,7 @@ impl Player {
                             request.set_leave_game(leave_game);
                             let _resp = self.sc2_query(&request).await;
 
-                            Ok(r_vars.build_result(Sc2Result::Timeout))
+                            Ok(r_vars.build_result(Sc2Result::Timeout, EndReason::Timeout))
                         }
                         other => Err(other),
                     };
@@ -502,9 +568,22 @@ impl Player {
 enum CreateGamePlayer {
     Participant,
     Observer,
+    Computer {
+        race: Race,
+        settings: ComputerSettings,
+    },
 }
 
 impl CreateGamePlayer {
+    fn from_match_player(player: &MatchPlayer) -> Self {
+        match player.kind {
+            PlayerKind::Computer(settings) => Self::Computer {
+                race: player.race.to_race(),
+                settings,
+            },
+            PlayerKind::Bot(_) | PlayerKind::Human => Self::Participant,
+        }
+    }
     fn as_proto(&self) -> sc2_proto::sc2api::PlayerSetup {
         use sc2_proto::sc2api::{PlayerSetup, PlayerType};
         let mut ps = PlayerSetup::new();
@@ -515,6 +594,12 @@ impl CreateGamePlayer {
             Self::Observer => {
                 ps.type_ = Some(EnumOrUnknown::new(PlayerType::Observer));
             }
+            Self::Computer { race, settings } => {
+                ps.type_ = Some(EnumOrUnknown::new(PlayerType::Computer));
+                ps.set_race(*race);
+                ps.set_difficulty(settings.difficulty);
+                ps.set_ai_build(settings.build);
+            }
         }
         ps
     }
@@ -527,7 +612,11 @@ fn proto_join_game_participant(
     player_num: PlayerNum,
 ) -> Request {
     let mut r_join_game = RequestJoinGame::new();
-    let mut player_data = PlayerData::from_join_request(request.join_game());
+    let mut player_data = if config.players[&player_num].kind.is_human() {
+        PlayerData::from_human_join_request(request.join_game())
+    } else {
+        PlayerData::from_join_request(request.join_game())
+    };
 
     if config.validate_race {
         player_data.race = config.players[&player_num].race.to_race()
@@ -538,7 +627,10 @@ fn proto_join_game_participant(
 
     r_join_game.set_race(player_data.race);
 
-    port_config.apply_proto(&mut r_join_game);
+    // Games against the built-in computer only have a single participant and don't need ports
+    if config.participant_count() > 1 {
+        port_config.apply_proto(&mut r_join_game);
+    }
 
     let mut request = request.clone();
     request.set_join_game(r_join_game);
@@ -554,6 +646,17 @@ fn create_empty_debug_response(request: &Request) -> Response {
     debug_response
 }
 
+/// Synthetic response for a step request in a realtime game
+fn create_step_response(request: &Request, simulation_loop: u32) -> Response {
+    let mut step_response = Response::new();
+    let mut response_step = ResponseStep::new();
+    response_step.set_simulation_loop(simulation_loop);
+    step_response.set_id(request.id());
+    step_response.set_status(Status::in_game);
+    step_response.set_step(response_step);
+    step_response
+}
+
 fn create_ping_request() -> Request {
     let mut request = Request::new();
     let ping = RequestPing::new();
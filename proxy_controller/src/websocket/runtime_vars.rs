@@ -1,41 +1,72 @@
-use indexmap::IndexSet;
-use sc2_proto::sc2api::Request;
+use sc2_proto::sc2api::{Request, Response, ResponseGameInfo};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+use crate::game::annotations::AnnotationCollector;
 use crate::game::game_config::GameConfig;
+use crate::game::pause::PauseControl;
 use crate::game::player_result::PlayerResult;
+use crate::game::race::BotRace;
+use crate::game::realtime::RealtimeTracker;
 use crate::game::sc2_result::Sc2Result;
+use crate::game::step_time_histogram::StepTimeHistogram;
+use crate::summary::recorder::SummaryRecorder;
+use common::models::aiarena::aiarena_end_reason::EndReason;
+use common::PlayerNum;
 
 pub struct RuntimeVars {
     pub timeout_secs: Duration,
     pub replay_path: PathBuf,
     pub start_timer: bool,
     pub start_time: Instant,
+    pub pause: PauseControl,
+    pub paused_at_start: Duration,
     pub avg_frame_time: f32,
     pub surrender: bool,
-    pub tags: IndexSet<String>,
+    pub quit: bool,
+    pub game_ended: bool,
+    pub annotations: AnnotationCollector,
     pub player_id: Option<u32>,
     pub game_loops: u32,
     pub frame_time: f32,
+    pub step_times: StepTimeHistogram,
+    step_timer: StepTimer,
+    /// When the last observation of a realtime game was sent, with the paused time at that point
+    pub observation_sent: Option<(Instant, Duration)>,
+    pub realtime: Option<RealtimeTracker>,
+    pub summary: SummaryRecorder,
+    pub actual_races: HashMap<u32, BotRace>,
 }
 
 impl RuntimeVars {
     #[must_use]
-    pub fn new(config: &GameConfig) -> Self {
-        let replay_dir = std::path::Path::new(config.replay_path());
-        let replay_path = replay_dir.join(&config.replay_name);
+    pub fn new(config: &GameConfig, player_num: PlayerNum, pause: PauseControl) -> Self {
+        let replay_path = config.player_replay_path(player_num);
         Self {
             timeout_secs: Duration::from_secs(config.timeout_secs),
             replay_path,
             start_timer: false,
             start_time: Instant::now(),
+            paused_at_start: pause.paused_time(),
+            pause,
             avg_frame_time: 0_f32,
             surrender: false,
-            tags: IndexSet::with_capacity(10),
+            quit: false,
+            game_ended: false,
+            annotations: AnnotationCollector::new(
+                config.max_annotations,
+                config.max_annotation_length,
+            ),
             player_id: None,
             game_loops: 0,
             frame_time: 0.0,
+            step_times: StepTimeHistogram::new(config.max_frame_time),
+            step_timer: StepTimer::default(),
+            observation_sent: None,
+            realtime: config.realtime().then(RealtimeTracker::default),
+            summary: SummaryRecorder::default(),
+            actual_races: HashMap::new(),
         }
     }
 
@@ -44,17 +75,65 @@ impl RuntimeVars {
     }
     pub fn start_time(&mut self) {
         self.start_time = Instant::now();
+        self.paused_at_start = self.pause.paused_time();
     }
     pub fn record_avg_frame_time(&mut self) {
         self.avg_frame_time = nan_check(self.frame_time / self.game_loops as f32);
     }
     pub fn record_frame_time(&mut self) {
         if self.start_timer {
-            self.frame_time += self.start_time.elapsed().as_secs_f32();
+            // Time spent paused is not the bot's fault
+            let paused = self
+                .pause
+                .paused_time()
+                .saturating_sub(self.paused_at_start);
+            let step_time = self.start_time.elapsed().saturating_sub(paused);
+            self.frame_time += step_time.as_secs_f32();
         }
     }
+    /// Records the time the bot took from the response to the previous step to the next step
+    /// request. The time SC2 takes to answer the requests in between is not counted.
+    ///
+    /// In a realtime game, the lag is the time between an observation and the next step or
+    /// observation request, which is when the bot is done acting on it.
+    pub fn record_step_time(&mut self, request: &Request) {
+        let step_time = self.step_timer.request_received(
+            request.has_step(),
+            Instant::now(),
+            self.pause.paused_time(),
+        );
+        if let Some(step_time) = step_time {
+            self.step_times.record(step_time);
+            self.summary.record_step_time(step_time);
+        }
+        if request.has_step() || request.has_observation() {
+            if let Some(observation_sent) = self.observation_sent.take() {
+                let lag = self.unpaused_since(observation_sent);
+                if let Some(realtime) = self.realtime.as_mut() {
+                    realtime.record_lag(lag);
+                }
+            }
+        }
+    }
+    /// Starts timing the bot once a response is sent
+    pub fn response_sent(&mut self, response: &Response) {
+        let sent = (Instant::now(), self.pause.paused_time());
+        self.step_timer
+            .response_sent(response.has_step(), sent.0, sent.1);
+        if response.has_observation() && self.realtime.is_some() {
+            self.observation_sent = Some(sent);
+        }
+    }
+    /// Time since `sent`, leaving out the time spent paused
+    fn unpaused_since(&self, (sent_at, paused_at_send): (Instant, Duration)) -> Duration {
+        let paused = self.pause.paused_time().saturating_sub(paused_at_send);
+        sent_at.elapsed().saturating_sub(paused)
+    }
     pub fn set_game_loops(&mut self, game_loops: u32) {
         self.game_loops = game_loops;
+        if let Some(realtime) = self.realtime.as_mut() {
+            realtime.record_observation(game_loops, Instant::now());
+        }
     }
     pub fn replay_path(&self) -> &str {
         self.replay_path.to_str().unwrap()
@@ -62,6 +141,30 @@ impl RuntimeVars {
     pub fn set_surrender_flag(&mut self) {
         self.surrender = true;
     }
+    pub fn set_quit_flag(&mut self) {
+        self.quit = true;
+    }
+    pub fn set_game_ended(&mut self) {
+        self.game_ended = true;
+    }
+    /// End reason for a player that left the game through leave_game or quit
+    pub const fn leave_end_reason(&self) -> EndReason {
+        if self.surrender {
+            EndReason::Surrender
+        } else if self.quit {
+            EndReason::Quit
+        } else {
+            EndReason::Normal
+        }
+    }
+    /// End reason for a bot that disconnected or misbehaved
+    pub const fn crash_end_reason(&self) -> EndReason {
+        if self.game_ended {
+            EndReason::DisconnectAfterGameEnd
+        } else {
+            EndReason::GameCrash
+        }
+    }
     pub fn player_id(&self) -> u32 {
         self.player_id.unwrap()
     }
@@ -70,31 +173,85 @@ impl RuntimeVars {
         self.player_id = Some(player_id);
     }
 
-    pub fn add_tags(&mut self, request: &Request) {
-        for tag in request
+    /// Keeps the actual race of each player, so random picks can be resolved after the game
+    pub fn record_races(&mut self, game_info: &ResponseGameInfo) {
+        for player_info in game_info
+            .player_info
+            .iter()
+            .filter(|player_info| player_info.has_race_actual())
+        {
+            self.actual_races.insert(
+                player_info.player_id(),
+                BotRace::from_race(player_info.race_actual()),
+            );
+        }
+    }
+
+    /// Collects tags and annotations from the chat messages in the request
+    pub fn add_annotations(&mut self, request: &Request) {
+        for message in request
             .action()
             .actions
             .iter()
             .filter(|a| a.action_chat.has_message())
-            .filter_map(|x| {
-                let msg = x.action_chat.message();
-                if msg.contains("Tag:") {
-                    msg.strip_prefix("Tag:").map(String::from)
-                } else {
-                    None
-                }
-            })
+            .map(|a| a.action_chat.message())
         {
-            self.tags.insert(tag);
+            self.annotations.add_message(message, self.game_loops);
         }
     }
-    pub fn build_result(self, result: Sc2Result) -> PlayerResult {
+    pub fn build_result(self, result: Sc2Result, end_reason: EndReason) -> PlayerResult {
+        let (tags, annotations) = self.annotations.finish();
         PlayerResult {
             game_loops: self.game_loops,
             frame_time: self.avg_frame_time,
             player_id: self.player_id.unwrap(),
-            tags: self.tags,
+            tags,
+            step_time_stats: self.step_times.stats(),
+            realtime_stats: self.realtime.as_ref().map(RealtimeTracker::stats),
+            summary: Some(self.summary.finish()),
+            annotations: Some(annotations),
+            actual_races: self.actual_races,
             result,
+            end_reason,
+        }
+    }
+}
+
+/// Adds up the time a bot spends on its side of each request, from the response to one step to
+/// the next step request.
+#[derive(Debug, Default)]
+struct StepTimer {
+    /// Bot time since the response to the last step, once one was sent
+    elapsed: Option<Duration>,
+    /// When the last response was sent, with the paused time at that point
+    response_sent: Option<(Instant, Duration)>,
+}
+
+impl StepTimer {
+    /// Adds the time since the last response, and returns the step time on a step request
+    fn request_received(
+        &mut self,
+        is_step: bool,
+        now: Instant,
+        paused: Duration,
+    ) -> Option<Duration> {
+        if let (Some(elapsed), Some((sent_at, paused_at_send))) =
+            (self.elapsed.as_mut(), self.response_sent.take())
+        {
+            let pause = paused.saturating_sub(paused_at_send);
+            *elapsed += now.saturating_duration_since(sent_at).saturating_sub(pause);
+        }
+        if is_step {
+            self.elapsed.take()
+        } else {
+            None
+        }
+    }
+
+    fn response_sent(&mut self, is_step: bool, now: Instant, paused: Duration) {
+        self.response_sent = Some((now, paused));
+        if is_step {
+            self.elapsed = Some(Duration::ZERO);
         }
     }
 }
@@ -106,3 +263,38 @@ fn nan_check(number: f32) -> f32 {
         number
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_step_time_leaves_out_sc2_time() {
+        let start = Instant::now();
+        let mut timer = StepTimer::default();
+        assert_eq!(timer.request_received(true, start, Duration::ZERO), None);
+
+        timer.response_sent(true, start, Duration::ZERO);
+        // The bot asks for an observation after 20ms, which SC2 takes 50ms to answer
+        assert_eq!(
+            timer.request_received(false, start + ms(20), Duration::ZERO),
+            None
+        );
+        timer.response_sent(false, start + ms(70), Duration::ZERO);
+        let step_time = timer.request_received(true, start + ms(90), Duration::ZERO);
+        assert_eq!(step_time, Some(ms(40)));
+    }
+
+    #[test]
+    fn test_step_time_leaves_out_pauses() {
+        let start = Instant::now();
+        let mut timer = StepTimer::default();
+        timer.response_sent(true, start, Duration::ZERO);
+        let step_time = timer.request_received(true, start + ms(100), ms(30));
+        assert_eq!(step_time, Some(ms(70)));
+    }
+}
@@ -0,0 +1,329 @@
+//! Sanitization of SC2 responses before they are forwarded to a bot
+
+use crate::game::game_config::GameConfig;
+use common::configuration::ac_config::OpponentRacePolicy;
+use common::PlayerNum;
+use sc2_proto::raw::Alliance;
+use sc2_proto::sc2api::{PlayerInfo, Response, ResponseObservation};
+
+/// Applies the identity policy of a match to every response sent to a bot.
+///
+/// Player names are replaced with the names from the match, and the actual race of the opponent
+/// is hidden according to the configured [`OpponentRacePolicy`]. Bots always get raw data from
+/// SC2, so scouting is detected from the enemy units in it, and it is removed before the
+/// observation reaches a bot that did not ask for raw data.
+#[derive(Debug, Clone)]
+pub struct ResponseSanitizer {
+    player_id: u32,
+    player_name: String,
+    opponent_name: String,
+    policy: OpponentRacePolicy,
+    opponent_scouted: bool,
+    raw_requested: bool,
+}
+
+impl ResponseSanitizer {
+    pub fn new(
+        config: &GameConfig,
+        player_num: PlayerNum,
+        player_id: u32,
+        raw_requested: bool,
+    ) -> Self {
+        Self {
+            player_id,
+            player_name: config.players[&player_num].name.clone(),
+            opponent_name: config.players[&player_num.other_player()].name.clone(),
+            policy: config.opponent_race_policy(),
+            opponent_scouted: false,
+            raw_requested,
+        }
+    }
+
+    /// Whether the bot has observed at least one enemy unit
+    pub const fn opponent_scouted(&self) -> bool {
+        self.opponent_scouted
+    }
+
+    pub fn sanitize(&mut self, response: &mut Response) {
+        if response.has_observation() {
+            self.sanitize_observation(response.mut_observation());
+        }
+        if response.has_game_info() {
+            for player_info in &mut response.mut_game_info().player_info {
+                self.sanitize_player_info(player_info);
+            }
+        }
+        if response.has_replay_info() {
+            for player_info_extra in &mut response.mut_replay_info().player_info {
+                if let Some(player_info) = player_info_extra.player_info.as_mut() {
+                    self.sanitize_player_info(player_info);
+                }
+            }
+        }
+    }
+
+    fn sanitize_observation(&mut self, response: &mut ResponseObservation) {
+        if let Some(observation) = response.observation.as_mut() {
+            if !self.opponent_scouted {
+                self.opponent_scouted = observation
+                    .raw_data
+                    .units
+                    .iter()
+                    .any(|unit| unit.alliance() == Alliance::Enemy);
+            }
+            // Only the bot's own state belongs here, never that of another player
+            if observation.player_common.is_some()
+                && observation.player_common.player_id() != self.player_id
+            {
+                observation.player_common.clear();
+            }
+            if !self.raw_requested {
+                observation.raw_data.clear();
+            }
+        }
+        if !self.raw_requested {
+            for action in &mut response.actions {
+                action.action_raw.clear();
+            }
+        }
+    }
+
+    fn sanitize_player_info(&self, player_info: &mut PlayerInfo) {
+        if player_info.player_id() == self.player_id {
+            player_info.player_name = Some(self.player_name.clone());
+        } else {
+            player_info.player_name = Some(self.opponent_name.clone());
+            if self.hide_opponent_race() {
+                player_info.race_actual = player_info.race_requested;
+            }
+        }
+    }
+
+    fn hide_opponent_race(&self) -> bool {
+        match self.policy {
+            OpponentRacePolicy::Reveal => false,
+            OpponentRacePolicy::UntilScouted => !self.opponent_scouted,
+            OpponentRacePolicy::Never => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::race::BotRace;
+    use crate::matches::player_kind::PlayerKind;
+    use crate::matches::MatchPlayer;
+    use common::models::bot_controller::BotType;
+    use protobuf::MessageField;
+    use sc2_proto::common::Race;
+    use sc2_proto::raw::Unit;
+    use sc2_proto::sc2api::{Observation, PlayerInfoExtra, ResponseGameInfo, ResponseReplayInfo};
+    use std::collections::HashMap;
+
+    fn match_player(name: &str, race: BotRace) -> MatchPlayer {
+        MatchPlayer {
+            id: name.to_string(),
+            name: name.to_string(),
+            race,
+            kind: PlayerKind::Bot(BotType::Python),
+        }
+    }
+
+    fn game_config(policy: OpponentRacePolicy) -> GameConfig {
+        GameConfig {
+            map: "AutomatonLE".to_string(),
+            max_game_time: 0,
+            max_frame_time: 0,
+            timeout_secs: 0,
+            strikes: 0,
+            replay_path: "".to_string(),
+            match_id: 1,
+            replay_name_template: "".to_string(),
+            disable_debug: false,
+            real_time: false,
+            realtime_step_emulation: false,
+            disable_fog: false,
+            random_seed: None,
+            sc2_version: None,
+            visualize: false,
+            validate_race: false,
+            opponent_race_policy: policy,
+            max_annotations: 0,
+            max_annotation_length: 0,
+            players: HashMap::from([
+                (PlayerNum::One, match_player("basic_bot", BotRace::Terran)),
+                (PlayerNum::Two, match_player("random_bot", BotRace::Random)),
+            ]),
+        }
+    }
+
+    fn player_info(player_id: u32, requested: Race, actual: Race) -> PlayerInfo {
+        let mut player_info = PlayerInfo::new();
+        player_info.set_player_id(player_id);
+        player_info.set_race_requested(requested);
+        player_info.set_race_actual(actual);
+        player_info.set_player_name("Unknown".to_string());
+        player_info
+    }
+
+    fn game_info_response() -> Response {
+        let mut game_info = ResponseGameInfo::new();
+        game_info.player_info = vec![
+            player_info(1, Race::Terran, Race::Terran),
+            player_info(2, Race::Random, Race::Zerg),
+        ];
+        let mut response = Response::new();
+        response.set_game_info(game_info);
+        response
+    }
+
+    fn observation_response(alliance: Alliance) -> Response {
+        let mut unit = Unit::new();
+        unit.set_alliance(alliance);
+        let mut observation = Observation::new();
+        observation
+            .raw_data
+            .mut_or_insert_default()
+            .units
+            .push(unit);
+        let mut response_observation = ResponseObservation::new();
+        response_observation.observation = MessageField::some(observation);
+        let mut response = Response::new();
+        response.set_observation(response_observation);
+        response
+    }
+
+    fn player_common(player_id: u32) -> Response {
+        let mut response = observation_response(Alliance::Neutral);
+        let observation = response
+            .mut_observation()
+            .observation
+            .mut_or_insert_default();
+        let player_common = observation.player_common.mut_or_insert_default();
+        player_common.set_player_id(player_id);
+        player_common.set_minerals(50);
+        response
+    }
+
+    fn opponent_race(response: &Response) -> Race {
+        response.game_info().player_info[1].race_actual()
+    }
+
+    #[test]
+    fn test_player_names_are_replaced() {
+        let config = game_config(OpponentRacePolicy::Reveal);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, true);
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        let player_info = &response.game_info().player_info;
+        assert_eq!(player_info[0].player_name(), "basic_bot");
+        assert_eq!(player_info[1].player_name(), "random_bot");
+    }
+
+    #[test]
+    fn test_opponent_race_hidden_until_scouted() {
+        let config = game_config(OpponentRacePolicy::UntilScouted);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, true);
+
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        assert_eq!(opponent_race(&response), Race::Random);
+
+        sanitizer.sanitize(&mut observation_response(Alliance::Neutral));
+        assert!(!sanitizer.opponent_scouted());
+
+        sanitizer.sanitize(&mut observation_response(Alliance::Enemy));
+        assert!(sanitizer.opponent_scouted());
+
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        assert_eq!(opponent_race(&response), Race::Zerg);
+    }
+
+    #[test]
+    fn test_scouting_without_raw_interface() {
+        let config = game_config(OpponentRacePolicy::UntilScouted);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, false);
+
+        let mut response = observation_response(Alliance::Enemy);
+        sanitizer.sanitize(&mut response);
+        assert!(sanitizer.opponent_scouted());
+        // The bot did not ask for raw data, so it does not get to see the units
+        assert!(response.observation().observation.raw_data.is_none());
+
+        // Every game info request is sanitized with the scouting state at that time
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        assert_eq!(opponent_race(&response), Race::Zerg);
+    }
+
+    #[test]
+    fn test_player_common_of_other_players_is_removed() {
+        let config = game_config(OpponentRacePolicy::Reveal);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, true);
+
+        let mut own = player_common(1);
+        sanitizer.sanitize(&mut own);
+        let observation = &own.observation().observation;
+        assert_eq!(observation.player_common.minerals(), 50);
+
+        let mut other = player_common(2);
+        sanitizer.sanitize(&mut other);
+        assert!(other.observation().observation.player_common.is_none());
+    }
+
+    #[test]
+    fn test_own_race_is_never_hidden() {
+        let config = game_config(OpponentRacePolicy::Never);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::Two, 2, true);
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        let player_info = &response.game_info().player_info;
+        assert_eq!(player_info[0].race_actual(), Race::Terran);
+        assert_eq!(player_info[1].race_actual(), Race::Zerg);
+        assert_eq!(player_info[0].player_name(), "basic_bot");
+        assert_eq!(player_info[1].player_name(), "random_bot");
+    }
+
+    #[test]
+    fn test_opponent_race_never_revealed() {
+        let config = game_config(OpponentRacePolicy::Never);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, true);
+        sanitizer.sanitize(&mut observation_response(Alliance::Enemy));
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        assert_eq!(opponent_race(&response), Race::Random);
+    }
+
+    #[test]
+    fn test_opponent_race_revealed() {
+        let config = game_config(OpponentRacePolicy::Reveal);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, true);
+        let mut response = game_info_response();
+        sanitizer.sanitize(&mut response);
+        assert_eq!(opponent_race(&response), Race::Zerg);
+    }
+
+    #[test]
+    fn test_replay_info_is_sanitized() {
+        let config = game_config(OpponentRacePolicy::UntilScouted);
+        let mut sanitizer = ResponseSanitizer::new(&config, PlayerNum::One, 1, true);
+        let mut replay_info = ResponseReplayInfo::new();
+        for player_info in [
+            player_info(1, Race::Terran, Race::Terran),
+            player_info(2, Race::Random, Race::Protoss),
+        ] {
+            let mut player_info_extra = PlayerInfoExtra::new();
+            player_info_extra.player_info = MessageField::some(player_info);
+            replay_info.player_info.push(player_info_extra);
+        }
+        let mut response = Response::new();
+        response.set_replay_info(replay_info);
+        sanitizer.sanitize(&mut response);
+
+        let opponent = &response.replay_info().player_info[1].player_info;
+        assert_eq!(opponent.race_actual(), Race::Random);
+        assert_eq!(opponent.player_name(), "random_bot");
+    }
+}
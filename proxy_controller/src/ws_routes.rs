@@ -10,35 +10,110 @@ use crate::game::game_config::GameConfig;
 use crate::game::player_result::PlayerResult;
 use crate::game::sc2_result::Sc2Result;
 use axum::extract::ws::WebSocket;
-use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
-use axum::response::IntoResponse;
+use axum::extract::{ConnectInfo, Query, State, WebSocketUpgrade};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use common::models::aiarena::aiarena_end_reason::EndReason;
 use common::models::aiarena::aiarena_result::AiArenaResult;
 use common::PlayerNum;
 use parking_lot::RwLock;
+use serde::Deserialize;
 use tokio::net::TcpStream;
 use tokio::time::sleep;
 use tracing::{debug, error, Instrument};
 
+use crate::bot_listener::BotListenerState;
 use crate::state::{ProxyState, SC2Url};
 use crate::websocket::errors::player_error::PlayerError;
 use crate::websocket::player::Player;
 use crate::websocket::port_config::PortConfig;
 
+#[derive(Debug, Default, Deserialize)]
+pub struct SC2ApiParams {
+    /// Set by a human-driven SC2 client joining the human side of the current match
+    #[serde(default)]
+    human: bool,
+}
+
+/// Who a connection belongs to, which follows from the endpoint it came in on
+#[derive(Debug, Clone, Copy)]
+enum Connection {
+    Human,
+    Bot(PlayerNum),
+    /// A bot on the main proxy port, which takes the first bot player that is not connected
+    LegacyBot,
+}
+
+/// `/sc2api` on the main proxy port. Humans connect here, and bots too unless
+/// `LEGACY_BOT_ENDPOINT` is disabled, which leaves bots to their own listener.
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(params): Query<SC2ApiParams>,
     State(state): State<Arc<RwLock<ProxyState>>>,
-) -> impl IntoResponse {
+) -> Response {
+    let connection = if params.human {
+        Connection::Human
+    } else if state.read().settings.legacy_bot_endpoint {
+        Connection::LegacyBot
+    } else {
+        error!(
+            "Rejected connection from {:?}: bots connect to their own port",
+            addr
+        );
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    upgrade(ws, state, addr, connection)
+}
+
+/// `/sc2api` on the listener of a single bot
+pub async fn bot_websocket_handler(
+    ws: WebSocketUpgrade,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(listener): State<BotListenerState>,
+) -> Response {
+    upgrade(
+        ws,
+        listener.proxy_state,
+        addr,
+        Connection::Bot(listener.player_num),
+    )
+}
+
+fn upgrade(
+    ws: WebSocketUpgrade,
+    state: Arc<RwLock<ProxyState>>,
+    addr: SocketAddr,
+    connection: Connection,
+) -> Response {
     ws.max_message_size(128 << 20) // 128MiB
         .max_frame_size(32 << 20) // 32MiB
         .accept_unmasked_frames(true)
-        .on_upgrade(move |socket| websocket(socket, state, addr))
+        .on_upgrade(move |socket| websocket(socket, state, addr, connection))
 }
 
 #[tracing::instrument(skip(bot_ws, state), fields(bot_name))]
-async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: SocketAddr) {
+async fn websocket(
+    bot_ws: WebSocket,
+    state: Arc<RwLock<ProxyState>>,
+    addr: SocketAddr,
+    connection: Connection,
+) {
     debug!("Connection from {:?}", addr);
     state.write().add_client(addr);
+    let assigned = match connection {
+        Connection::Human => state.write().assign_human(addr),
+        Connection::Bot(player_num) => state.write().assign_bot(addr, player_num),
+        Connection::LegacyBot => state.write().assign_next_bot(addr),
+    };
+    if !assigned {
+        error!(
+            "Rejected {:?} connection: the player is not free",
+            connection
+        );
+        state.write().remove_client(addr);
+        return;
+    }
     let settings = state.read().settings.clone();
 
     let sc2_url = state.write().get_free_sc2_url();
@@ -63,17 +138,8 @@ async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: Sock
     }
 
     let sc2_ws = sc2_ws.unwrap();
-    let mut client_ws = Player::new(bot_ws, sc2_ws, addr);
-
-    loop {
-        let p_details = { state.read().get_player_details(addr) };
-
-        if p_details.as_ref().and_then(|x| x.player_num()).is_none() {
-            sleep(Duration::from_secs(3)).await;
-        } else {
-            break;
-        }
-    }
+    let pause = state.read().pause.clone();
+    let mut client_ws = Player::new(bot_ws, sc2_ws, addr, pause);
 
     let p_details = { state.read().get_player_details(addr) };
     debug!("Player Details: {:?}", p_details);
@@ -85,8 +151,11 @@ async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: Sock
     }
     let player_num = p_details.as_ref().and_then(|x| x.player_num()).unwrap();
 
-    if let PlayerNum::One = player_num {
-        match client_ws.create_game(&map, settings.realtime).await {
+    let current_match = state.read().current_match.clone().unwrap();
+    // The first participant in the match creates the game
+    if current_match.participants().first() == Some(&player_num) {
+        let game_config = GameConfig::new(&current_match, &settings);
+        match client_ws.create_game(&map, &game_config).await {
             Ok(_) => {
                 let mut s = state.write();
                 debug!("Setting port_config and ready state");
@@ -117,14 +186,12 @@ async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: Sock
         }
     }
     if state.read().ready {
-        let current_match = state.read().current_match.clone().unwrap();
-
         let ac_config = state.read().settings.clone();
         let game_config = GameConfig::new(&current_match, &ac_config);
         state.write().game_config = Some(game_config.clone());
         let port_config = state.read().port_config.clone().unwrap();
         debug!("Starting Client Run");
-        let p_result = match client_ws
+        let mut p_result = match client_ws
             .run(game_config, port_config, player_num)
             .instrument(tracing::Span::current())
             .await
@@ -132,9 +199,17 @@ async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: Sock
             Ok(result) => result,
             Err(e) => {
                 let mut temp_result = Sc2Result::SC2Crash;
+                let mut end_reason = if client_ws.has_joined() {
+                    EndReason::GameCrash
+                } else {
+                    EndReason::InitCrash
+                };
                 error!("{:?}", e);
                 match e {
-                    PlayerError::BotQuit => temp_result = Sc2Result::Defeat,
+                    PlayerError::BotQuit => {
+                        temp_result = Sc2Result::Defeat;
+                        end_reason = EndReason::Quit;
+                    }
                     PlayerError::NoMessageAvailable => {
                         temp_result = Sc2Result::SC2Crash;
                         state.write().game_result.as_mut().unwrap().set_error();
@@ -184,6 +259,7 @@ async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: Sock
                     PlayerError::BotTimeout(e) => {
                         error!("{:?}", e);
                         temp_result = Sc2Result::Timeout;
+                        end_reason = EndReason::Timeout;
                         state.write().game_result.as_mut().unwrap().set_error();
                     }
                 }
@@ -192,10 +268,28 @@ async fn websocket(bot_ws: WebSocket, state: Arc<RwLock<ProxyState>>, addr: Sock
                     frame_time: 0.0,
                     player_id: 0,
                     tags: indexmap::IndexSet::default(),
+                    step_time_stats: None,
+                    realtime_stats: None,
+                    annotations: None,
+                    actual_races: Default::default(),
+                    summary: None,
                     result: temp_result,
+                    end_reason,
                 }
             }
         };
+        if p_result.end_reason == EndReason::GameCrash {
+            // The opponent might have ended the game before this player noticed
+            let game_concluded = state
+                .read()
+                .game_result
+                .as_ref()
+                .and_then(|r| r.end_reason(player_num.other_player()))
+                .map_or(false, EndReason::concludes_game);
+            if game_concluded {
+                p_result.set_end_reason(EndReason::DisconnectAfterGameEnd);
+            }
+        }
         debug!("{:?}", &p_result);
         state
             .write()
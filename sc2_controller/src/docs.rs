@@ -8,7 +8,9 @@ use utoipa::OpenApi;
     openapi(
         paths(
             crate::routes::terminate_sc2,
+            crate::routes::preflight,
             crate::routes::start_sc2,
+            crate::routes::versions,
             common::api::process::stats,
             common::api::process::stats_host,
             common::api::process::terminate_all,
@@ -18,10 +20,17 @@ use utoipa::OpenApi;
         ),
         components(schemas(
             common::models::Status,
+            common::preflight::PreflightReport,
+            common::preflight::PreflightCheck,
+            common::preflight::CheckStatus,
             common::models::TerminateResponse,
             common::models::StartResponse,
+            common::models::sc2_controller::StartSc2,
+            common::models::sc2_controller::Sc2Version,
+            common::models::sc2_controller::InstalledVersions,
             common::models::ProcessStatusResponse,
-            common::api::process::ProcStatus
+            common::api::process::ProcStatus,
+            common::procs::limits::LimitViolation
         ))
     )
 )]
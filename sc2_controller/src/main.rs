@@ -1,10 +1,13 @@
 #[cfg(feature = "swagger")]
 mod docs;
+mod preflight;
 mod routes;
 
 #[cfg(feature = "swagger")]
 use crate::docs::ApiDoc;
-use crate::routes::{download_controller_log, find_map, start_sc2, terminate_sc2};
+use crate::routes::{
+    download_controller_log, find_map, preflight, start_sc2, terminate_sc2, versions,
+};
 use axum::http::Request;
 use axum::response::Response;
 use axum::routing::{get, post};
@@ -16,6 +19,7 @@ use common::api::process::{stats_all, ProcessMap};
 use common::api::state::AppState;
 use common::configuration::{get_config_from_proxy, get_host_url, get_proxy_url_from_env};
 use common::logging::init_logging;
+use common::preflight::{configuration_failed, exit_with_report};
 use std::path::Path;
 use std::str::FromStr;
 use std::{net::SocketAddr, time::Duration};
@@ -34,6 +38,7 @@ static PREFIX: &str = "ACSC2";
 async fn main() {
     let matches = command!()
         .arg(arg!(--port <VALUE>).value_parser(value_parser!(u16)))
+        .arg(arg!(--check "Check the environment, print a report and exit"))
         .get_matches();
 
     let port = *matches.get_one::<u16>("port").unwrap_or(&8083);
@@ -44,9 +49,15 @@ async fn main() {
     let config_url = format!("http://{proxy_url}/configuration");
     let health_url = format!("http://{proxy_url}/health");
 
-    let settings = get_config_from_proxy(config_url, health_url, PREFIX)
-        .await
-        .unwrap(); //panic if we can't get the config
+    let check = matches.get_flag("check");
+    let settings = match get_config_from_proxy(config_url, health_url, PREFIX).await {
+        Ok(settings) => settings,
+        Err(e) if check => exit_with_report(&configuration_failed("sc2_controller", e)),
+        Err(e) => panic!("Could not get the config from the proxy: {e:?}"),
+    };
+    if check {
+        exit_with_report(&preflight::run_checks(&settings));
+    }
 
     let log_level = &settings.logging_level;
     let env_log = std::env::var("RUST_LOG")
@@ -69,6 +80,7 @@ async fn main() {
         settings,
         shutdown_sender: tx,
         extra_info: Default::default(),
+        process_limits: Default::default(),
     };
     #[allow(unused_mut)]
     let mut router = Router::<AppState>::new();
@@ -81,6 +93,7 @@ async fn main() {
     // Compose the routes
     let app = router
         .route("/start", post(start_sc2))
+        .route("/versions", get(versions))
         .route("/stats/:port", get(stats))
         .route("/stats/host", get(stats_host))
         .route("/stats_all", get(stats_all))
@@ -88,6 +101,7 @@ async fn main() {
         .route("/terminate/:port", post(terminate_sc2))
         .route("/terminate_all", post(terminate_all))
         .route("/shutdown", post(shutdown))
+        .route("/preflight", get(preflight))
         .route("/find_map/:map_name", get(find_map))
         .route("/download/controller_log", get(download_controller_log))
         .layer(
@@ -0,0 +1,82 @@
+use common::configuration::ac_config::ACConfig;
+use common::paths;
+use common::preflight::{
+    check_directory, check_program, common_checks, CheckStatus, PreflightCheck, PreflightReport,
+};
+use std::path::Path;
+
+pub fn run_checks(settings: &ACConfig) -> PreflightReport {
+    let mut checks = common_checks(settings);
+    checks.push(match paths::executable() {
+        Ok(executable) => check_program("SC2 executable", &executable.to_string_lossy()),
+        Err(e) => PreflightCheck::failed(
+            "SC2 executable",
+            format!(
+                "No SC2 binary in {}: {e}",
+                paths::base_dir().join("Versions").display()
+            ),
+            "Install SC2 or point SC2PATH or SC2_PROXY_BASE at the install directory",
+        ),
+    });
+    if let Some(sc2_version) = settings.sc2_version {
+        checks.push(match paths::executable_for_version(Some(sc2_version)) {
+            Ok(executable) => {
+                check_program("Configured SC2 version", &executable.to_string_lossy())
+            }
+            Err(e) => PreflightCheck::failed(
+                "Configured SC2 version",
+                e.to_string(),
+                "Install the configured version or change SC2_VERSION",
+            ),
+        });
+    }
+    checks.push(check_directory("SC2 working directory", &paths::cwd_dir()));
+    checks.push(check_maps());
+    PreflightReport::new("sc2_controller", checks)
+}
+
+fn check_maps() -> PreflightCheck {
+    let map_dir = paths::map_dir();
+    let check = check_directory("Map directory", &map_dir);
+    if check.status != CheckStatus::Ok {
+        return check;
+    }
+    let map_count = walk_maps(&map_dir);
+    if map_count == 0 {
+        PreflightCheck::failed(
+            "Map directory",
+            format!("No .SC2Map files in {}", map_dir.display()),
+            "Download the ladder maps into the Maps directory",
+        )
+    } else {
+        PreflightCheck::ok(
+            "Map directory",
+            format!("{map_count} maps in {}", map_dir.display()),
+        )
+    }
+}
+
+/// Counts map files the same way `find_map` looks for them: at the top level and one level deep
+fn walk_maps(map_dir: &Path) -> usize {
+    let is_map = |path: &Path| {
+        path.extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("SC2Map"))
+    };
+    let entries = match std::fs::read_dir(map_dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .map(|path| {
+            if path.is_dir() {
+                std::fs::read_dir(&path).map_or(0, |inner| {
+                    inner.flatten().filter(|e| is_map(&e.path())).count()
+                })
+            } else {
+                usize::from(is_map(&path))
+            }
+        })
+        .sum()
+}
@@ -7,15 +7,20 @@ use common::api::errors::download_error::DownloadError;
 use common::api::errors::map_error::MapError;
 use common::api::errors::process_error::ProcessError;
 use common::api::state::AppState;
+use common::configuration::ac_config::ACConfig;
 use common::configuration::get_proxy_url_from_env;
 use common::models::bot_controller::MapData;
+use common::models::sc2_controller::{InstalledVersions, Sc2Version, StartSc2};
 use common::models::{StartResponse, Status, TerminateResponse};
 use common::paths;
 use common::portpicker::pick_unused_port_in_range;
+use common::preflight::PreflightReport;
+use common::procs::limits::{exit_violation, LimitedProcess, ResourceLimits};
 use common::utilities::directory::ensure_directory_structure;
 use common::utilities::portpicker::Port;
 use reqwest::header::HeaderName;
 use reqwest::Client;
+use std::path::PathBuf;
 use tempfile::TempDir;
 use tokio::io::AsyncWriteExt;
 use tokio_util::io::ReaderStream;
@@ -38,17 +43,34 @@ pub async fn terminate_sc2(
     Path(port): Path<Port>,
     State(state): State<AppState>,
 ) -> Result<Json<TerminateResponse>, AppError> {
-    if let Some((_, mut child)) = state.process_map.write().remove_entry(&port) {
+    let child = state.process_map.write().remove_entry(&port);
+    let mut violations = Vec::new();
+    if let Some((_, mut child)) = child {
         tracing::info!("Terminating SC2 on port {}", port);
-        if let Err(e) = child.kill() {
-            Err(ProcessError::TerminateError(e.to_string()).into())
-        } else {
-            let response = TerminateResponse {
-                status: Status::Success,
-            };
-
-            Ok(Json(response))
+        match child.try_status() {
+            Ok(Some(exit_status)) => violations.extend(exit_violation(exit_status)),
+            _ => {
+                if let Err(e) = child.kill() {
+                    return Err(ProcessError::TerminateError(e.to_string()).into());
+                }
+            }
         }
+        let limited = state.process_limits.write().remove(&port);
+        if let Some(limited) = limited {
+            // Without the live address space, only setup errors, cgroup events and recorded
+            // violations are left to report
+            violations.extend(limited.violations(0));
+            // The cgroup can only be removed once the process is gone
+            let _ = child.status().await;
+            limited.cleanup();
+        }
+        if !violations.is_empty() {
+            tracing::warn!("SC2 on port {} ran into limits: {:?}", port, violations);
+        }
+        Ok(Json(TerminateResponse {
+            status: Status::Success,
+            violations,
+        }))
     } else {
         Err(ProcessError::TerminateError("Process Key entry does not exist".to_string()).into())
     }
@@ -56,13 +78,18 @@ pub async fn terminate_sc2(
 
 #[tracing::instrument(skip(state))]
 #[cfg_attr(feature = "swagger",utoipa::path(
-    get,
+    post,
     path = "/start",
+    request_body(content = Option<StartSc2>, description = "SC2 version to start, defaults to the latest"),
     responses(
         (status = 200, description = "Request Completed", body = StartResponse)
     )
 ))]
-pub async fn start_sc2(State(state): State<AppState>) -> Result<Json<StartResponse>, AppError> {
+pub async fn start_sc2(
+    State(state): State<AppState>,
+    start_sc2: Option<Json<StartSc2>>,
+) -> Result<Json<StartResponse>, AppError> {
+    let sc2_version = start_sc2.and_then(|Json(s)| s.sc2_version);
     let ws_port = pick_unused_port_in_range(9000..10000)
         .ok_or_else(|| ProcessError::Custom("Could not allocate port".to_string()))?;
     let tempdir = TempDir::new()
@@ -74,39 +101,68 @@ pub async fn start_sc2(State(state): State<AppState>) -> Result<Json<StartRespon
         .await
         .map_err(|e| ProcessError::StartError(format!("{e:?}")))?;
 
-    if let Ok(executable) = paths::executable() {
-        let process_result = (async_process::Command::new(executable)
-            .arg("-listen")
-            .arg("0.0.0.0")
-            .arg("-port")
-            .arg(ws_port.to_string())
-            .arg("-dataDir")
-            .arg(paths::base_dir().to_str().unwrap())
-            .arg("-displayMode")
-            .arg("0")
-            .arg("-tempDir")
-            .arg(tempdir.path().to_str().unwrap())
-            .current_dir(paths::cwd_dir()))
-        .spawn();
-
-        match process_result {
-            Ok(process) => {
-                state.process_map.write().insert(ws_port, process);
-                let start_response = StartResponse {
-                    status: Status::Success,
-                    status_reason: "".to_string(),
-                    port: ws_port,
-                    process_key: ws_port,
-                };
-                Ok(Json(start_response))
+    let executable = paths::executable_for_version(sc2_version)
+        .map_err(|e| ProcessError::StartError(format!("Could not find executable: {e}")))?;
+    info!("Starting {:?}", &executable);
+    let mut command = async_process::Command::new(executable);
+    command
+        .arg("-listen")
+        .arg("0.0.0.0")
+        .arg("-port")
+        .arg(ws_port.to_string())
+        .arg("-dataDir")
+        .arg(paths::base_dir().to_str().unwrap())
+        .arg("-displayMode")
+        .arg("0")
+        .arg("-tempDir")
+        .arg(tempdir.path().to_str().unwrap())
+        .current_dir(paths::cwd_dir());
+    let limits = resource_limits(&state.settings);
+    limits
+        .apply_to_command(&mut command)
+        .map_err(|e| ProcessError::StartError(format!("Invalid SC2 limits: {e}")))?;
+    let process_result = command.spawn();
+
+    match process_result {
+        Ok(process) => {
+            if !limits.is_empty() {
+                let limited = LimitedProcess::new(&limits, &format!("sc2_{ws_port}"), process.id());
+                state.process_limits.write().insert(ws_port, limited);
             }
-            Err(e) => Err(ProcessError::StartError(e.to_string()).into()),
+            state.process_map.write().insert(ws_port, process);
+            let start_response = StartResponse {
+                status: Status::Success,
+                status_reason: "".to_string(),
+                port: ws_port,
+                process_key: ws_port,
+                violations: Vec::new(),
+            };
+            Ok(Json(start_response))
         }
-    } else {
-        Err(ProcessError::StartError("Could not find executable".to_string()).into())
+        Err(e) => Err(ProcessError::StartError(e.to_string()).into()),
     }
 }
 
+#[tracing::instrument]
+#[cfg_attr(feature = "swagger", utoipa::path(
+    get,
+    path = "/versions",
+    responses(
+        (status = 200, description = "Installed SC2 versions", body = InstalledVersions)
+    )
+))]
+pub async fn versions() -> Result<Json<InstalledVersions>, AppError> {
+    let versions = paths::installed_versions(&paths::versions_dir())
+        .map_err(|e| ProcessError::Custom(format!("Could not list SC2 versions: {e}")))?
+        .into_iter()
+        .map(|(base_build, path)| Sc2Version {
+            base_build,
+            path: path.to_string_lossy().to_string(),
+        })
+        .collect();
+    Ok(Json(InstalledVersions { versions }))
+}
+
 pub async fn download_controller_log(
     State(state): State<AppState>,
 ) -> Result<
@@ -204,3 +260,27 @@ pub async fn find_map(Path(map_name): Path<String>) -> Result<Json<MapData>, App
             })
         })
 }
+
+#[tracing::instrument(skip(state))]
+#[cfg_attr(feature = "swagger", utoipa::path(
+    get,
+    path = "/preflight",
+    responses(
+        (status = 200, description = "Environment check report", body = PreflightReport)
+    )
+))]
+pub async fn preflight(State(state): State<AppState>) -> Json<PreflightReport> {
+    Json(crate::preflight::run_checks(&state.settings))
+}
+
+fn resource_limits(settings: &ACConfig) -> ResourceLimits {
+    ResourceLimits {
+        cpu_affinity: settings.sc2_cpu_affinity.clone(),
+        memory_limit_mb: settings.sc2_memory_limit_mb,
+        nice: settings.sc2_nice,
+        max_processes: None,
+        max_open_files: None,
+        cgroup: settings.sc2_cgroup.as_ref().map(PathBuf::from),
+        cpu_quota: settings.sc2_cpu_quota,
+    }
+}